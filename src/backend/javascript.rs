@@ -0,0 +1,317 @@
+//! The javascript backend, generating an ES module for each file. Each definition becomes an exported
+//! `const` and each function an arrow function. Every other file a file imports is imported as a whole
+//! with `await import`, so names defined in other files are accessed through the module they're in.
+//! Unlike an `import` declaration, which would run the imported file before anything else in the file,
+//! this runs any `print`s in the imported file at the point the file is imported.
+use crate::{
+    builtins::{builtin_instance, Builtin},
+    incremental::{CompilerHandle, GetStatement, TypeCheck},
+    lexer::escape,
+    name_resolution::Origin,
+    parser::{
+        ast::{Expression, InstanceDefinition, MatchArm, Pattern, TopLevelStatement, TypeDefinition},
+        ids::{ExprId, TopLevelId},
+    },
+    type_inference::Evidence,
+};
+
+use super::{instance_name, module_name, Backend, Context, RUNTIME_MODULE};
+
+pub struct JavaScript;
+
+impl Backend for JavaScript {
+    fn extension(&self) -> &'static str {
+        "mjs"
+    }
+
+    fn runtime(&self) -> &'static str {
+        include_str!("runtime.mjs")
+    }
+
+    /// The runtime has no `print`s, so unlike other imports it doesn't matter when it is run
+    fn import_runtime(&self) -> String {
+        format!("import * as {} from \"./{RUNTIME_MODULE}.{}\";\n", module_variable(RUNTIME_MODULE), self.extension())
+    }
+
+    fn compile_statement(&self, statement: &TopLevelStatement, compiler: &CompilerHandle) -> String {
+        match statement {
+            TopLevelStatement::Import { file_name, .. } => {
+                let module = module_name(file_name.name.as_str());
+                format!("const {} = await import(\"./{module}.{}\");\n", module_variable(&module), self.extension())
+            },
+            // A definition with constraints is a function taking the dictionary for each of them
+            TopLevelStatement::Definition(definition) => {
+                let context = Context::new(&definition.id, compiler);
+                let constraints = TypeCheck(definition.id.clone()).get(compiler).typ.constraints.len();
+                let body = expr_string(&definition.body, &context);
+                let name = javascript_name(&definition.name.name);
+                format!("\nexport const {name} = {}{body};\n", dictionary_parameters(constraints))
+            },
+            TopLevelStatement::Print(expression, id) => {
+                let context = Context::new(id, compiler);
+                let runtime = module_variable(RUNTIME_MODULE);
+                format!("globalThis.console.log({runtime}.show({}));\n", expr_string(expression, &context))
+            },
+            TopLevelStatement::TypeDefinition(definition) => type_definition_string(definition),
+            // Aliases and classes only matter to the type checker. For classes, their instances are what is
+            // passed around at runtime.
+            TopLevelStatement::TypeAlias(_) | TopLevelStatement::Class(_) => String::new(),
+            TopLevelStatement::Instance(instance) => instance_string(instance, &Context::new(&instance.id, compiler)),
+        }
+    }
+}
+
+/// Arrow functions, conditionals, and records are always parenthesized so that none of them
+/// need to worry about the precedence of the expressions around them.
+fn expr_string(expr: &Expression, context: &Context) -> String {
+    match expr {
+        Expression::IntegerLiteral(x, _) => x.to_string(),
+        Expression::BoolLiteral(b, _) => b.to_string(),
+        // Javascript understands each escape sequence our string literals may contain
+        Expression::StringLiteral(s, _) => format!("\"{}\"", escape(s)),
+        // `throw` is a statement, so it needs a function body to be thrown from within an expression
+        Expression::Hole { name: _, id } => {
+            let location = id.location(&context.item, context.compiler);
+            let message = format!("Reached a hole at {location}");
+            format!("(() => {{ throw new globalThis.Error(\"{}\"); }})()", escape(&message))
+        },
+        // Names without an origin are built-ins, unless they failed to resolve
+        Expression::Variable(identifier) => match Builtin::from_name(&identifier.name) {
+            Some(builtin) if !context.origins.contains_key(&identifier.id) => {
+                builtin_string(builtin, identifier.id, context)
+            },
+            _ => variable_string(&identifier.name, identifier.id, context),
+        },
+        Expression::FunctionCall { function, argument, id: _ } => {
+            format!("{}({})", expr_string(function, context), expr_string(argument, context))
+        },
+        Expression::Lambda { parameter, body, id: _ } => lambda_string(parameter, body, context),
+        Expression::If { condition, then_branch, else_branch, id: _ } => {
+            let (condition, then_branch) = (expr_string(condition, context), expr_string(then_branch, context));
+            format!("({condition} ? {then_branch} : {})", expr_string(else_branch, context))
+        },
+        // `let x = value in body` becomes `((x) => body)(value)`, just like in the python backend
+        Expression::Let { name, value, body, id: _ } => {
+            let name = javascript_name(&name.name);
+            format!("(({name}) => {})({})", expr_string(body, context), expr_string(value, context))
+        },
+        // The value being matched is bound to `_match` so it is only evaluated once. Each arm then
+        // becomes a conditional expression testing whether its pattern matches, falling through to
+        // the next arm otherwise. Matches are checked to be exhaustive, so the last arm needs no test.
+        Expression::Match { scrutinee, arms, id: _ } => {
+            let (last, rest) = arms.split_last().expect("The parser ensures each match has at least one arm");
+            let arms = rest.iter().rev().fold(arm_body_string(last, context), |otherwise, arm| {
+                let mut tests = Vec::new();
+                pattern_tests(&arm.pattern, "_match".to_string(), &mut tests);
+                let test = if tests.is_empty() { "true".to_string() } else { tests.join(" && ") };
+                format!("({test} ? {} : {otherwise})", arm_body_string(arm, context))
+            });
+            format!("((_match) => {arms})({})", expr_string(scrutinee, context))
+        },
+        // Tuples and lists are both javascript arrays, and records are objects. Tuples use a subclass of
+        // `Array` so that they can be printed differently from lists.
+        Expression::Tuple { elements, id: _ } => {
+            let elements: Vec<_> = elements.iter().map(|element| expr_string(element, context)).collect();
+            format!("{}.Tuple.of({})", module_variable(RUNTIME_MODULE), elements.join(", "))
+        },
+        Expression::List { elements, id: _ } => {
+            let elements: Vec<_> = elements.iter().map(|element| expr_string(element, context)).collect();
+            format!("[{}]", elements.join(", "))
+        },
+        Expression::Record { fields, id: _ } => {
+            let fields: Vec<_> =
+                fields.iter().map(|(name, value)| format!("\"{name}\": {}", expr_string(value, context))).collect();
+            format!("({{{}}})", fields.join(", "))
+        },
+        Expression::FieldAccess { record, field, id: _ } => format!("{}[\"{field}\"]", expr_string(record, context)),
+    }
+}
+
+/// A variable referring to something with constraints is given the dictionary for each of them.
+/// A method is instead looked up in the dictionary for its class.
+fn variable_string(name: &str, variable: ExprId, context: &Context) -> String {
+    let evidence = context.evidence.get(&variable).into_iter().flatten();
+    let mut dictionaries = evidence.map(|evidence| evidence_string(evidence, context));
+
+    let name = match context.origins.get(&variable) {
+        Some(Origin::Method(..)) => {
+            let dictionary = dictionaries.next().expect("Each use of a method has evidence for its class");
+            return format!("{dictionary}[\"{name}\"]");
+        },
+        Some(Origin::TopLevelDefinition(id) | Origin::Constructor(id, _)) => global_name(name, id, &context.item),
+        _ => javascript_name(name),
+    };
+    dictionaries.fold(name, |function, dictionary| format!("{function}({dictionary})"))
+}
+
+/// An operator is looked up in the dictionary for its class, like a method. The operators of built-in
+/// instances are the same for each type, so an operator used at one of these is used directly instead.
+fn builtin_string(builtin: Builtin, variable: ExprId, context: &Context) -> String {
+    match context.evidence.get(&variable).map(Vec::as_slice) {
+        Some([Evidence::Instance(id, _)]) if builtin_instance(id).is_some() => builtin.javascript().to_string(),
+        Some([evidence]) => format!("{}[\"{}\"]", evidence_string(evidence, context), builtin.method_name()),
+        _ => builtin.javascript().to_string(),
+    }
+}
+
+/// The dictionary given by some evidence. Instances with a context are functions taking a dictionary
+/// for each constraint in it. Built-in instances aren't defined anywhere, so their object is written
+/// out in full.
+fn evidence_string(evidence: &Evidence, context: &Context) -> String {
+    match evidence {
+        Evidence::Parameter(index) => format!("_dict{index}"),
+        Evidence::Instance(id, _) if let Some((class, _)) = builtin_instance(id) => {
+            let methods =
+                class.methods().map(|method| format!("\"{}\": {}", method.method_name(), method.javascript()));
+            format!("({{{}}})", methods.collect::<Vec<_>>().join(", "))
+        },
+        Evidence::Instance(id, arguments) => {
+            let TopLevelStatement::Instance(instance) = GetStatement(id.clone()).get(context.compiler) else {
+                unreachable!("Evidence should refer to an instance")
+            };
+            let instance = global_name(&instance_name(&instance), id, &context.item);
+            let arguments = arguments.iter().map(|argument| evidence_string(argument, context));
+            arguments.fold(instance, |instance, argument| format!("{instance}({argument})"))
+        },
+    }
+}
+
+/// The name of a top-level definition, constructor, or instance defined by the statement `definition`
+/// when used within the statement `item`. If they're in different files, it is accessed through
+/// the module it is imported as.
+fn global_name(name: &str, definition: &TopLevelId, item: &TopLevelId) -> String {
+    if definition.file_path == item.file_path {
+        javascript_name(name)
+    } else {
+        format!("{}.{}", module_variable(&module_name(&definition.file_path)), javascript_name(name))
+    }
+}
+
+/// The variable a module is imported as, including the runtime module. Names in our language can't
+/// contain a `$`, so starting with one means this can't clash with any definition.
+fn module_variable(module: &str) -> String {
+    format!("${module}")
+}
+
+/// `(_dict0) => (_dict1) => ` for a definition with two constraints
+fn dictionary_parameters(count: usize) -> String {
+    (0..count).map(|index| format!("(_dict{index}) => ")).collect()
+}
+
+/// An instance is an object from the name of each method to its value, e.g.
+/// `Show_Int = ({"show": globalThis.String})`. An instance with a context is a function
+/// taking the dictionary for each constraint of its context instead.
+fn instance_string(instance: &InstanceDefinition, context: &Context) -> String {
+    let methods: Vec<_> =
+        instance.methods.iter().map(|(name, body)| format!("\"{name}\": {}", expr_string(body, context))).collect();
+    let parameters = dictionary_parameters(instance.constraints.len());
+    format!("\nexport const {} = {parameters}({{{}}});\n", instance_name(instance), methods.join(", "))
+}
+
+/// A lambda whose parameter is a pattern takes a single `_arg` and binds each variable in the
+/// pattern to the part of `_arg` it matches, the same way a match arm does.
+fn lambda_string(parameter: &Pattern, body: &Expression, context: &Context) -> String {
+    match parameter {
+        Pattern::Variable(name) => format!("(({}) => {})", javascript_name(&name.name), expr_string(body, context)),
+        Pattern::Wildcard(_) => format!("((_) => {})", expr_string(body, context)),
+        pattern => {
+            let mut bindings = Vec::new();
+            pattern_bindings(pattern, "_arg".to_string(), &mut bindings);
+
+            if bindings.is_empty() {
+                format!("((_arg) => {})", expr_string(body, context))
+            } else {
+                let (names, values): (Vec<_>, Vec<_>) = bindings.into_iter().unzip();
+                let body = expr_string(body, context);
+                format!("((_arg) => (({}) => {body})({}))", names.join(", "), values.join(", "))
+            }
+        },
+    }
+}
+
+/// Constructor values are arrays of the constructor's name followed by each of its fields,
+/// e.g. `Some 3` is `$_runtime.Constructor.of("Some", 3)`. Like tuples, these use a subclass of `Array`
+/// so that they can be printed differently. Each constructor is defined as a curried function
+/// creating such an array, or just the array itself if it has no fields.
+fn type_definition_string(definition: &TypeDefinition) -> String {
+    let mut text = String::from("\n");
+    for constructor in definition.constructors.iter() {
+        let name = &constructor.name.name;
+        let fields: Vec<_> = (0..constructor.fields.len()).map(|i| format!("x{i}")).collect();
+
+        let lambdas: String = fields.iter().map(|field| format!("({field}) => ")).collect();
+        let array = std::iter::once(format!("\"{name}\"")).chain(fields).collect::<Vec<_>>().join(", ");
+        let runtime = module_variable(RUNTIME_MODULE);
+        text += &format!("export const {} = {lambdas}{runtime}.Constructor.of({array});\n", javascript_name(name));
+    }
+    text
+}
+
+/// Names which are reserved in javascript are given a `_` suffix. Unlike python, most of these are
+/// lowercase so they may be the name of any definition or variable. `globalThis` is included since
+/// it is how the generated code refers to javascript's own globals.
+fn javascript_name(name: &str) -> String {
+    match name {
+        "arguments" | "await" | "break" | "case" | "catch" | "class" | "const" | "continue" | "debugger"
+        | "default" | "delete" | "do" | "else" | "enum" | "eval" | "export" | "extends" | "false" | "finally"
+        | "for" | "function" | "globalThis" | "if" | "implements" | "import" | "in" | "instanceof" | "interface"
+        | "let" | "new" | "null" | "package" | "private" | "protected" | "public" | "return" | "static" | "super"
+        | "switch" | "this" | "throw" | "true" | "try" | "typeof" | "undefined" | "var" | "void" | "while" | "with"
+        | "yield" => {
+            format!("{name}_")
+        },
+        _ => name.to_string(),
+    }
+}
+
+/// The body of a match arm with each variable in its pattern bound to the part of `_match` it matches
+fn arm_body_string(arm: &MatchArm, context: &Context) -> String {
+    let mut bindings = Vec::new();
+    pattern_bindings(&arm.pattern, "_match".to_string(), &mut bindings);
+    let body = expr_string(&arm.body, context);
+
+    if bindings.is_empty() {
+        body
+    } else {
+        let (names, values): (Vec<_>, Vec<_>) = bindings.into_iter().unzip();
+        format!("(({}) => {body})({})", names.join(", "), values.join(", "))
+    }
+}
+
+/// Collect each test needed to check whether the javascript expression `value` matches the pattern
+fn pattern_tests(pattern: &Pattern, value: String, tests: &mut Vec<String>) {
+    match pattern {
+        Pattern::Wildcard(_) | Pattern::Variable(_) => (),
+        Pattern::IntegerLiteral(x, _) => tests.push(format!("{value} === {x}")),
+        Pattern::BoolLiteral(b, _) => tests.push(format!("{value} === {b}")),
+        Pattern::Constructor { name, arguments, id: _ } => {
+            tests.push(format!("{value}[0] === \"{name}\""));
+            for (i, argument) in arguments.iter().enumerate() {
+                pattern_tests(argument, format!("{value}[{}]", i + 1), tests);
+            }
+        },
+        Pattern::Tuple { elements, id: _ } => {
+            for (i, element) in elements.iter().enumerate() {
+                pattern_tests(element, format!("{value}[{i}]"), tests);
+            }
+        },
+    }
+}
+
+/// Collect each variable in the pattern along with the part of the javascript expression `value` it binds
+fn pattern_bindings(pattern: &Pattern, value: String, bindings: &mut Vec<(String, String)>) {
+    match pattern {
+        Pattern::Wildcard(_) | Pattern::IntegerLiteral(..) | Pattern::BoolLiteral(..) => (),
+        Pattern::Variable(identifier) => bindings.push((javascript_name(&identifier.name), value)),
+        Pattern::Constructor { arguments, .. } => {
+            for (i, argument) in arguments.iter().enumerate() {
+                pattern_bindings(argument, format!("{value}[{}]", i + 1), bindings);
+            }
+        },
+        Pattern::Tuple { elements, id: _ } => {
+            for (i, element) in elements.iter().enumerate() {
+                pattern_bindings(element, format!("{value}[{i}]"), bindings);
+            }
+        },
+    }
+}
@@ -1,62 +1,147 @@
-use crate::{errors::Errors, incremental::{self, parse, CompileFile, CompilerHandle, TypeCheck, VisibleDefinitions}, parser::ast::{Expression, TopLevelStatement}};
+//! Code generation. Each target language has its own `Backend`, which generates the code for a single
+//! top-level statement at a time. `CompileFile` then joins the code for each statement in a file.
+//!
+//! Neither backend needs any type information. Values of every type are represented with the data
+//! structures the target language already has, e.g. constructor values are tuples or arrays starting
+//! with the name of their constructor. Each generated file imports the target's runtime module, which
+//! defines what the target language lacks, such as printing values the same way the interpreter does.
+use std::{collections::BTreeMap, path::Path};
 
-/// Compile a given source file to python, returning any errors in the file.
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::Errors,
+    incremental::{
+        self, CheckFile, CompileFile, CompileStatement, CompilerHandle, GetStatement, Resolve, TypeCheck, parse,
+    },
+    name_resolution::Origin,
+    parser::{
+        ast::{InstanceDefinition, TopLevelStatement, Type},
+        ids::{ExprId, TopLevelId},
+    },
+    type_inference::Evidence,
+};
+
+mod javascript;
+mod python;
+
+/// A language we can compile to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Target {
+    Python,
+    /// JavaScript ES modules, which may be run with e.g. `node`
+    JavaScript,
+}
+
+impl Target {
+    pub const ALL: [Target; 2] = [Target::Python, Target::JavaScript];
+
+    fn backend(self) -> &'static dyn Backend {
+        match self {
+            Target::Python => &python::Python,
+            Target::JavaScript => &javascript::JavaScript,
+        }
+    }
+
+    /// The extension of each generated file, without the leading `.`
+    pub fn extension(self) -> &'static str {
+        self.backend().extension()
+    }
+
+    /// The code of the runtime module, written alongside the generated files as `RUNTIME_MODULE`
+    pub fn runtime(self) -> &'static str {
+        self.backend().runtime()
+    }
+}
+
+/// The name of the module each generated file imports the runtime from. Names in our language can't
+/// start with `_`, so no file a program imports can have the same name.
+pub const RUNTIME_MODULE: &str = "_runtime";
+
+/// Generates the code for each top-level statement in some target language
+trait Backend {
+    fn extension(&self) -> &'static str;
+
+    fn runtime(&self) -> &'static str;
+
+    /// The code importing the runtime module which each generated file starts with
+    fn import_runtime(&self) -> String;
+
+    /// The code for a single top-level statement, including any trailing newline
+    fn compile_statement(&self, statement: &TopLevelStatement, compiler: &CompilerHandle) -> String;
+}
+
+/// Compile a given source file to the target language, returning any errors in the file.
 pub fn compile_file_impl(context: &CompileFile, compiler: &CompilerHandle) -> (String, Errors) {
     incremental::enter_query();
     incremental::println(format!("Compiling {}", context.file_name));
 
-    // Ignore errors for this parse, they'll be included in the VisibleDefinitions call.
+    // Ignore errors for this parse, they'll be included in the CheckFile call.
     let ast = parse(context.file_name.clone(), compiler).0;
-    let mut text = String::new();
-
-    // inc-complete doesn't currently provide an accumulator abstraction so we have to manually
-    // call VisibleDefinitions to collect the errors that are discarded in resolution.
-    let (_, mut errors) = VisibleDefinitions { file_name: context.file_name.clone() }.get(compiler);
-
-    for statement in ast.statements.iter() {
-        // Since we're compiling to python we don't actually need any type informtation
-        // but we still want to type check and any real compiler would need the information
-        // so we type check each top-level item anyway.
-        let results = TypeCheck(statement.id().clone()).get(compiler);
-        errors.extend(results.errors);
-
-        match statement {
-            TopLevelStatement::Import { file_name, id: _ } => {
-                let translated_name = file_name.name.replace(".ex", "");
-                text += &format!("from {translated_name} import *\n");
-            },
-            TopLevelStatement::Definition(definition) => {
-                text += &format!("\n{} = {}\n", definition.name, expr_string(&definition.body));
-            },
-            TopLevelStatement::Print(expression, _) => {
-                text += &format!("print({})\n", expr_string(expression));
-            },
-        }
-    }
+
+    // Since we're compiling to python or javascript we don't actually need any type informtation
+    // but we still want to type check and any real compiler would need the information
+    // so we type check each top-level item anyway.
+    let errors = CheckFile { file_name: context.file_name.clone() }.get(compiler);
+
+    // Each statement is compiled separately, so this only needs to join the cached code of each
+    // statement which didn't change.
+    let statements = ast.statements.iter().map(|statement| CompileStatement(statement.id().clone(), context.target));
+    let text = statements.map(|statement| statement.get(compiler));
+    let text = std::iter::once(context.target.backend().import_runtime()).chain(text).collect();
 
     incremental::exit_query();
     (text, errors)
 }
 
-fn expr_string(expr: &Expression) -> String {
-    match expr {
-        Expression::IntegerLiteral(x, _) => x.to_string(),
-        Expression::Variable(identifier) => {
-            if identifier.name.as_ref() == "+" || identifier.name.as_ref() == "-" {
-                format!("(lambda x: lambda y: x {} y)", identifier)
-            } else {
-                identifier.to_string()
-            }
-        }
-        Expression::FunctionCall { function, argument, id: _ } => {
-            if matches!(function.as_ref(), Expression::Lambda { .. }) {
-                format!("({})({})", expr_string(function), expr_string(argument))
-            } else {
-                format!("{}({})", expr_string(function), expr_string(argument))
-            }
-        },
-        Expression::Lambda { parameter_name, body, id: _ } => {
-            format!("lambda {}: {}", parameter_name, expr_string(body))
-        },
+/// Compile a single top-level statement to the target language
+pub fn compile_statement_impl(context: &CompileStatement, compiler: &CompilerHandle) -> String {
+    incremental::enter_query();
+    let statement = GetStatement(context.0.clone()).get(compiler);
+    incremental::println(format!("Compiling {statement}"));
+
+    let text = context.1.backend().compile_statement(&statement, compiler);
+
+    incremental::exit_query();
+    text
+}
+
+/// The results of earlier passes needed to compile the expressions within a statement
+struct Context<'local, 'inner> {
+    item: TopLevelId,
+    origins: BTreeMap<ExprId, Origin>,
+    evidence: BTreeMap<ExprId, Vec<Evidence>>,
+    compiler: &'local CompilerHandle<'inner>,
+}
+
+impl<'local, 'inner> Context<'local, 'inner> {
+    fn new(item: &TopLevelId, compiler: &'local CompilerHandle<'inner>) -> Self {
+        let origins = Resolve(item.clone()).get(compiler).origins;
+        let evidence = TypeCheck(item.clone()).get(compiler).evidence;
+        Context { item: item.clone(), origins, evidence, compiler }
     }
 }
+
+/// The name of the module generated for the given source file. Since all output files are
+/// written to the same directory, this is just the file name without any directories or extension.
+pub fn module_name(file_name: &str) -> String {
+    let path = Path::new(file_name);
+    path.file_stem().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+}
+
+/// Instances are named after their class and the outermost type constructor of their type, e.g. `Show_List`.
+/// Instances can't overlap so this is unique among the instances visible in a file.
+fn instance_name(instance: &InstanceDefinition) -> String {
+    let typ = match &instance.typ {
+        Type::Int => "Int".to_string(),
+        Type::Bool => "Bool".to_string(),
+        Type::String => "String".to_string(),
+        Type::Named { name, .. } => name.name.to_string(),
+        Type::Tuple(elements) => format!("Tuple{}", elements.len()),
+        Type::Function { .. } => "Function".to_string(),
+        Type::Generic(_) | Type::Record { .. } => {
+            unreachable!("Instances for these types are rejected by name resolution")
+        },
+    };
+    format!("{}_{typ}", instance.class)
+}
@@ -0,0 +1,334 @@
+//! The python backend. Each definition becomes a global variable and each function a `lambda`,
+//! since every expression in our language is an expression in python as well.
+use std::sync::Arc;
+
+use crate::{
+    builtins::{builtin_instance, Builtin},
+    incremental::{CompilerHandle, ExportedDefinitions, ExportedInstances, GetStatement, TypeCheck},
+    lexer::escape,
+    name_resolution::Origin,
+    parser::{
+        ast::{Expression, Identifier, InstanceDefinition, MatchArm, Pattern, TopLevelStatement, TypeDefinition},
+        ids::ExprId,
+    },
+    type_inference::Evidence,
+};
+
+use super::{instance_name, module_name, Backend, Context, RUNTIME_MODULE};
+
+pub struct Python;
+
+impl Backend for Python {
+    fn extension(&self) -> &'static str {
+        "py"
+    }
+
+    fn runtime(&self) -> &'static str {
+        include_str!("runtime.py")
+    }
+
+    /// Importing the runtime as a whole means it isn't imported again by a `from <file> import *`
+    fn import_runtime(&self) -> String {
+        format!("import {RUNTIME_MODULE}\n")
+    }
+
+    fn compile_statement(&self, statement: &TopLevelStatement, compiler: &CompilerHandle) -> String {
+        match statement {
+            TopLevelStatement::Import { file_name, names: None, id: _ } => {
+                format!("from {} import *\n", module_name(file_name.name.as_str()))
+            },
+            TopLevelStatement::Import { file_name, names: Some(names), id: _ } => {
+                import_names_string(&file_name.name, names, compiler)
+            },
+            // A definition with constraints is a function taking the dictionary for each of them
+            TopLevelStatement::Definition(definition) => {
+                let context = Context::new(&definition.id, compiler);
+                let constraints = TypeCheck(definition.id.clone()).get(compiler).typ.constraints.len();
+                let body = expr_string(&definition.body, &context);
+                format!("\n{} = {}{body}\n", python_name(&definition.name.name), dictionary_parameters(constraints))
+            },
+            TopLevelStatement::Print(expression, id) => {
+                let context = Context::new(id, compiler);
+                format!("print({RUNTIME_MODULE}.show({}))\n", expr_string(expression, &context))
+            },
+            TopLevelStatement::TypeDefinition(definition) => type_definition_string(definition),
+            // Aliases and classes only matter to the type checker. For classes, their instances are what is
+            // passed around at runtime.
+            TopLevelStatement::TypeAlias(_) | TopLevelStatement::Class(_) => String::new(),
+            TopLevelStatement::Instance(instance) => instance_string(instance, &Context::new(&instance.id, compiler)),
+        }
+    }
+}
+
+/// An import listing names only imports the python globals for those names, along with each instance
+/// in the file since those are referred to by name in python. Types, classes, and methods aren't globals
+/// in python. If there is nothing to import, the module is still imported so any `print`s in it run.
+fn import_names_string(file_name: &Arc<String>, names: &[Identifier], compiler: &CompilerHandle) -> String {
+    let module = module_name(file_name);
+    let (exports, _) = ExportedDefinitions { file_name: file_name.clone() }.get(compiler);
+    let (instances, _) = ExportedInstances { file_name: file_name.clone() }.get(compiler);
+
+    let values = names.iter().filter(|name| {
+        matches!(exports.values.get(&name.name), Some(Origin::TopLevelDefinition(_) | Origin::Constructor(..)))
+    });
+    let mut globals: Vec<_> = values.map(|name| python_name(&name.name)).collect();
+
+    for instance in instances.into_values() {
+        if let TopLevelStatement::Instance(instance) = GetStatement(instance).get(compiler) {
+            globals.push(instance_name(&instance));
+        }
+    }
+
+    if globals.is_empty() {
+        format!("__import__(\"{module}\")\n")
+    } else {
+        format!("from {module} import {}\n", globals.join(", "))
+    }
+}
+
+fn expr_string(expr: &Expression, context: &Context) -> String {
+    match expr {
+        Expression::IntegerLiteral(x, _) => x.to_string(),
+        Expression::BoolLiteral(true, _) => "True".to_string(),
+        Expression::BoolLiteral(false, _) => "False".to_string(),
+        // Python understands each escape sequence our string literals may contain
+        Expression::StringLiteral(s, _) => format!("\"{}\"", escape(s)),
+        // Python has no raise expression, but throwing into an empty generator raises from within one
+        Expression::Hole { name: _, id } => {
+            let location = id.location(&context.item, context.compiler);
+            let message = escape(&format!("Reached a hole at {location}"));
+            format!("(_ for _ in ()).throw(__import__('builtins').Exception(\"{message}\"))")
+        },
+        // Names without an origin are built-ins, unless they failed to resolve
+        Expression::Variable(identifier) => match Builtin::from_name(&identifier.name) {
+            Some(builtin) if !context.origins.contains_key(&identifier.id) => {
+                builtin_string(builtin, identifier.id, context)
+            },
+            _ => variable_string(&identifier.name, identifier.id, context),
+        },
+        Expression::FunctionCall { function, argument, id: _ } => {
+            if matches!(function.as_ref(), Expression::Lambda { .. }) {
+                format!("({})({})", expr_string(function, context), expr_string(argument, context))
+            } else {
+                format!("{}({})", expr_string(function, context), expr_string(argument, context))
+            }
+        },
+        Expression::Lambda { parameter, body, id: _ } => lambda_string(parameter, body, context),
+        // Python's conditional expression binds looser than any operator so it is always parenthesized.
+        // A lambda in its `then` branch would extend over the rest of the expression, so wrap that too.
+        Expression::If { condition, then_branch, else_branch, id: _ } => {
+            let then_branch = if matches!(then_branch.as_ref(), Expression::Lambda { .. }) {
+                format!("({})", expr_string(then_branch, context))
+            } else {
+                expr_string(then_branch, context)
+            };
+            format!("({then_branch} if {} else {})", expr_string(condition, context), expr_string(else_branch, context))
+        },
+        // Python lambdas can't contain assignments, so `let x = value in body` becomes
+        // `(lambda x: body)(value)`. Creating the lambda has no effects, so `value` is still
+        // evaluated before `body`.
+        Expression::Let { name, value, body, id: _ } => {
+            let (body, value) = (expr_string(body, context), expr_string(value, context));
+            format!("(lambda {}: {body})({value})", python_name(&name.name))
+        },
+        // The value being matched is bound to `_match` so it is only evaluated once. Each arm then
+        // becomes a conditional expression testing whether its pattern matches, falling through to
+        // the next arm otherwise. Matches are checked to be exhaustive, so the last arm needs no test.
+        Expression::Match { scrutinee, arms, id: _ } => {
+            let (last, rest) = arms.split_last().expect("The parser ensures each match has at least one arm");
+            let arms = rest.iter().rev().fold(arm_body_string(last, context), |otherwise, arm| {
+                let mut tests = Vec::new();
+                pattern_tests(&arm.pattern, "_match".to_string(), &mut tests);
+                let test = if tests.is_empty() { "True".to_string() } else { tests.join(" and ") };
+                format!("({} if {test} else {otherwise})", arm_body_string(arm, context))
+            });
+            format!("(lambda _match: {arms})({})", expr_string(scrutinee, context))
+        },
+        // Tuples and records are python tuples and dictionaries
+        Expression::Tuple { elements, id: _ } => {
+            let elements: Vec<_> = elements.iter().map(|element| expr_string(element, context)).collect();
+            format!("({})", elements.join(", "))
+        },
+        Expression::List { elements, id: _ } => {
+            let elements: Vec<_> = elements.iter().map(|element| expr_string(element, context)).collect();
+            format!("[{}]", elements.join(", "))
+        },
+        Expression::Record { fields, id: _ } => {
+            let fields: Vec<_> =
+                fields.iter().map(|(name, value)| format!("\"{name}\": {}", expr_string(value, context))).collect();
+            format!("{{{}}}", fields.join(", "))
+        },
+        Expression::FieldAccess { record, field, id: _ } => format!("{}[\"{field}\"]", expr_string(record, context)),
+    }
+}
+
+/// A variable referring to something with constraints is given the dictionary for each of them.
+/// A method is instead looked up in the dictionary for its class.
+fn variable_string(name: &str, variable: ExprId, context: &Context) -> String {
+    let evidence = context.evidence.get(&variable).into_iter().flatten();
+    let mut dictionaries = evidence.map(|evidence| evidence_string(evidence, context.compiler));
+
+    match context.origins.get(&variable) {
+        Some(Origin::Method(..)) => {
+            let dictionary = dictionaries.next().expect("Each use of a method has evidence for its class");
+            format!("{dictionary}[\"{name}\"]")
+        },
+        _ => dictionaries.fold(python_name(name), |function, dictionary| format!("{function}({dictionary})")),
+    }
+}
+
+/// An operator is looked up in the dictionary for its class, like a method. The operators of built-in
+/// instances are the same for each type, so an operator used at one of these is used directly instead.
+fn builtin_string(builtin: Builtin, variable: ExprId, context: &Context) -> String {
+    match context.evidence.get(&variable).map(Vec::as_slice) {
+        Some([Evidence::Instance(id, _)]) if builtin_instance(id).is_some() => builtin.python().to_string(),
+        Some([evidence]) => format!("{}[\"{}\"]", evidence_string(evidence, context.compiler), builtin.method_name()),
+        _ => builtin.python().to_string(),
+    }
+}
+
+/// The dictionary given by some evidence. Instances with a context are functions taking a dictionary
+/// for each constraint in it. Built-in instances aren't defined anywhere, so their dictionary is
+/// written out in full.
+fn evidence_string(evidence: &Evidence, compiler: &CompilerHandle) -> String {
+    match evidence {
+        Evidence::Parameter(index) => format!("_dict{index}"),
+        Evidence::Instance(id, _) if let Some((class, _)) = builtin_instance(id) => {
+            let methods = class.methods().map(|method| format!("\"{}\": {}", method.method_name(), method.python()));
+            format!("{{{}}}", methods.collect::<Vec<_>>().join(", "))
+        },
+        Evidence::Instance(id, arguments) => {
+            let TopLevelStatement::Instance(instance) = GetStatement(id.clone()).get(compiler) else {
+                unreachable!("Evidence should refer to an instance")
+            };
+            arguments.iter().fold(instance_name(&instance), |instance, argument| {
+                format!("{instance}({})", evidence_string(argument, compiler))
+            })
+        },
+    }
+}
+
+/// `lambda _dict0: lambda _dict1: ` for a definition with two constraints
+fn dictionary_parameters(count: usize) -> String {
+    (0..count).map(|index| format!("lambda _dict{index}: ")).collect()
+}
+
+/// An instance is a dictionary from the name of each method to its value, e.g.
+/// `Show_Int = {"show": int_to_string}`. An instance with a context is a function
+/// taking the dictionary for each constraint of its context instead.
+fn instance_string(instance: &InstanceDefinition, context: &Context) -> String {
+    let methods: Vec<_> =
+        instance.methods.iter().map(|(name, body)| format!("\"{name}\": {}", expr_string(body, context))).collect();
+    let parameters = dictionary_parameters(instance.constraints.len());
+    format!("\n{} = {parameters}{{{}}}\n", instance_name(instance), methods.join(", "))
+}
+
+/// A lambda whose parameter is a pattern takes a single `_arg` and binds each variable in the
+/// pattern to the part of `_arg` it matches, the same way a match arm does.
+fn lambda_string(parameter: &Pattern, body: &Expression, context: &Context) -> String {
+    match parameter {
+        Pattern::Variable(name) => format!("lambda {}: {}", python_name(&name.name), expr_string(body, context)),
+        Pattern::Wildcard(_) => format!("lambda _: {}", expr_string(body, context)),
+        pattern => {
+            let mut bindings = Vec::new();
+            pattern_bindings(pattern, "_arg".to_string(), &mut bindings);
+
+            if bindings.is_empty() {
+                format!("lambda _arg: {}", expr_string(body, context))
+            } else {
+                let (names, values): (Vec<_>, Vec<_>) = bindings.into_iter().unzip();
+                let body = expr_string(body, context);
+                format!("lambda _arg: (lambda {}: {body})({})", names.join(", "), values.join(", "))
+            }
+        },
+    }
+}
+
+/// Constructor values are tuples of the constructor's name followed by each of its fields,
+/// e.g. `Some 3` is `_runtime.Constructor(("Some", 3))`. These use a subclass of `tuple` so that they
+/// can be printed differently from tuples. Each constructor is defined as a curried function
+/// creating such a tuple, or just the tuple itself if it has no fields.
+fn type_definition_string(definition: &TypeDefinition) -> String {
+    let mut text = String::from("\n");
+    for constructor in definition.constructors.iter() {
+        let name = &constructor.name.name;
+        let fields: Vec<_> = (0..constructor.fields.len()).map(|i| format!("x{i}")).collect();
+
+        let lambdas: String = fields.iter().map(|field| format!("lambda {field}: ")).collect();
+        let tuple = std::iter::once(format!("\"{name}\"")).chain(fields).collect::<Vec<_>>().join(", ");
+        let tuple = if constructor.fields.is_empty() { format!("({tuple},)") } else { format!("({tuple})") };
+        text += &format!("{} = {lambdas}{RUNTIME_MODULE}.Constructor({tuple})\n", python_name(name));
+    }
+    text
+}
+
+/// Names which are keywords in python are given a `_` suffix. This is used for every name we emit,
+/// including definitions, constructors, parameters, and let bindings, so the suffix is applied consistently.
+fn python_name(name: &str) -> String {
+    match name {
+        "False" | "None" | "True" | "and" | "as" | "assert" | "async" | "await" | "break" | "class" | "continue"
+        | "def" | "del" | "elif" | "else" | "except" | "finally" | "for" | "from" | "global" | "if" | "import"
+        | "in" | "is" | "lambda" | "nonlocal" | "not" | "or" | "pass" | "raise" | "return" | "try" | "while"
+        | "with" | "yield" => format!("{name}_"),
+        _ => name.to_string(),
+    }
+}
+
+/// The body of a match arm with each variable in its pattern bound to the part of `_match` it matches.
+/// Like the `then` branch of an `if`, a lambda here is parenthesized so it doesn't extend over the rest.
+fn arm_body_string(arm: &MatchArm, context: &Context) -> String {
+    let mut bindings = Vec::new();
+    pattern_bindings(&arm.pattern, "_match".to_string(), &mut bindings);
+
+    let body = if matches!(arm.body.as_ref(), Expression::Lambda { .. }) {
+        format!("({})", expr_string(&arm.body, context))
+    } else {
+        expr_string(&arm.body, context)
+    };
+
+    if bindings.is_empty() {
+        body
+    } else {
+        let (names, values): (Vec<_>, Vec<_>) = bindings.into_iter().unzip();
+        format!("(lambda {}: {body})({})", names.join(", "), values.join(", "))
+    }
+}
+
+/// Collect each test needed to check whether the python expression `value` matches the pattern
+fn pattern_tests(pattern: &Pattern, value: String, tests: &mut Vec<String>) {
+    match pattern {
+        Pattern::Wildcard(_) | Pattern::Variable(_) => (),
+        Pattern::IntegerLiteral(x, _) => tests.push(format!("{value} == {x}")),
+        Pattern::BoolLiteral(true, _) => tests.push(format!("{value} == True")),
+        Pattern::BoolLiteral(false, _) => tests.push(format!("{value} == False")),
+        Pattern::Constructor { name, arguments, id: _ } => {
+            tests.push(format!("{value}[0] == \"{name}\""));
+            for (i, argument) in arguments.iter().enumerate() {
+                pattern_tests(argument, format!("{value}[{}]", i + 1), tests);
+            }
+        },
+        Pattern::Tuple { elements, id: _ } => {
+            for (i, element) in elements.iter().enumerate() {
+                pattern_tests(element, format!("{value}[{i}]"), tests);
+            }
+        },
+    }
+}
+
+/// Collect each variable in the pattern along with the part of the python expression `value` it binds
+fn pattern_bindings(pattern: &Pattern, value: String, bindings: &mut Vec<(String, String)>) {
+    match pattern {
+        Pattern::Wildcard(_) | Pattern::IntegerLiteral(..) | Pattern::BoolLiteral(..) => (),
+        Pattern::Variable(identifier) => bindings.push((python_name(&identifier.name), value)),
+        Pattern::Constructor { arguments, .. } => {
+            for (i, argument) in arguments.iter().enumerate() {
+                pattern_bindings(argument, format!("{value}[{}]", i + 1), bindings);
+            }
+        },
+        Pattern::Tuple { elements, id: _ } => {
+            for (i, element) in elements.iter().enumerate() {
+                pattern_bindings(element, format!("{value}[{i}]"), bindings);
+            }
+        },
+    }
+}
@@ -0,0 +1,45 @@
+// The runtime imported by each file generated by the javascript backend. This is written to the output
+// directory as `_runtime.mjs`. Being its own module, none of the definitions in a program can shadow
+// the javascript globals used here.
+
+/** A constructor value: the name of the constructor followed by each of its fields */
+export class Constructor extends Array {}
+
+/** A tuple value. Lists are plain arrays, so tuples need their own class to be printed differently. */
+export class Tuple extends Array {}
+
+/** Format a value the same way the interpreter prints it. Strings are only quoted within other values. */
+export function show(value, nested = false) {
+    switch (typeof value) {
+        case "boolean":
+        case "number":
+            return String(value);
+        case "string":
+            return nested ? `"${escape(value)}"` : value;
+        case "function":
+            return "<function>";
+    }
+    if (value instanceof Constructor) {
+        const [name, ...fields] = value;
+        const shown = fields.map((field) =>
+            field instanceof Constructor && field.length > 1 ? `(${show(field)})` : show(field, true));
+        return [name, ...shown].join(" ");
+    }
+    if (value instanceof Tuple) {
+        return `(${Array.from(value, (element) => show(element, true)).join(", ")})`;
+    }
+    if (Array.isArray(value)) {
+        return `[${value.map((element) => show(element, true)).join(", ")}]`;
+    }
+    const names = Object.keys(value).sort();
+    if (names.length === 0) {
+        return "{}";
+    }
+    return `{ ${names.map((name) => `${name} = ${show(value[name], true)}`).join(", ")} }`;
+}
+
+/** Escape a string the same way the lexer's `escape` does */
+function escape(string) {
+    const replacements = { "\\": "\\\\", "\"": "\\\"", "\n": "\\n", "\t": "\\t", "\r": "\\r" };
+    return Array.from(string, (char) => replacements[char] ?? char).join("");
+}
@@ -0,0 +1,38 @@
+# The runtime imported by each file generated by the python backend. This is written to the output
+# directory as `_runtime.py`. Being its own module, none of the definitions in a program can shadow
+# the python builtins used here.
+
+
+class Constructor(tuple):
+    """A constructor value: the name of the constructor followed by each of its fields"""
+
+
+def show(value, nested=False):
+    """Format a value the same way the interpreter prints it. Strings are only quoted within other values."""
+    # bool is a subclass of int so it must be checked first
+    if isinstance(value, bool):
+        return "true" if value else "false"
+    if isinstance(value, int):
+        return str(value)
+    if isinstance(value, str):
+        return '"' + escape(value) + '"' if nested else value
+    if callable(value):
+        return "<function>"
+    if isinstance(value, Constructor):
+        name, *fields = value
+        fields = [f"({show(field)})" if isinstance(field, Constructor) and len(field) > 1 else show(field, True)
+                  for field in fields]
+        return " ".join([name, *fields])
+    if isinstance(value, tuple):
+        return "(" + ", ".join(show(element, True) for element in value) + ")"
+    if isinstance(value, list):
+        return "[" + ", ".join(show(element, True) for element in value) + "]"
+    if not value:
+        return "{}"
+    return "{ " + ", ".join(f"{name} = {show(value[name], True)}" for name in sorted(value)) + " }"
+
+
+def escape(string):
+    """Escape a string the same way the lexer's `escape` does"""
+    replacements = {"\\": "\\\\", "\"": "\\\"", "\n": "\\n", "\t": "\\t", "\r": "\\r"}
+    return "".join(replacements.get(char, char) for char in string)
@@ -0,0 +1,410 @@
+//! The table of built-in operators and functions.
+//!
+//! Each operator in the source is parsed into a call to a variable named after the operator, so
+//! `a * b` becomes `(*) a b`. Built-in functions like `int_to_string` are ordinary variables.
+//! These variables are never defined anywhere in the program. Instead,
+//! every pass which needs to know about them - name resolution, type inference, the backends,
+//! and the interpreter - looks them up in this table. Adding a new builtin only requires adding it
+//! here, along with a token and a precedence level for it in the lexer and parser if it is an operator.
+//!
+//! Built-in functions are only used when no definition or local variable of the same name is in
+//! scope, so existing programs defining their own `map` or `length` keep working. Likewise the
+//! built-in `List` type is only used when no `type List` is in scope.
+//!
+//! The operators are methods of the built-in classes `Num`, `Eq`, and `Ord`, so `a + b` works for any
+//! type with a `Num` instance. These classes have instances for `Int`, and `Eq` for `String` as well.
+//! Programs may give them instances for other types, defining each operator by its `method_name`.
+//! Like the `List` type, a built-in class is only used when no class of the same name is in scope.
+use std::sync::Arc;
+
+use crate::{
+    interpreter::Value,
+    parser::ids::TopLevelId,
+    type_inference::types::{Type, TypeBindings},
+};
+
+/// The name of the built-in list type, which takes a single type argument for its elements
+pub const LIST_TYPE: &str = "List";
+
+/// The file the ids of built-in classes and instances are in. No source file can have this name, so
+/// these ids never refer to a statement: anything looking up the statement of a class or instance
+/// checks for the built-in ones first.
+pub const BUILTIN_FILE: &str = "<builtin>";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Builtin {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Concatenate,
+    IntToString,
+    Cons,
+    Head,
+    Tail,
+    Map,
+    Fold,
+    Length,
+}
+
+pub const BUILTINS: [Builtin; 19] = [
+    Builtin::Add,
+    Builtin::Subtract,
+    Builtin::Multiply,
+    Builtin::Divide,
+    Builtin::Modulo,
+    Builtin::Equal,
+    Builtin::NotEqual,
+    Builtin::Less,
+    Builtin::LessEqual,
+    Builtin::Greater,
+    Builtin::GreaterEqual,
+    Builtin::Concatenate,
+    Builtin::IntToString,
+    Builtin::Cons,
+    Builtin::Head,
+    Builtin::Tail,
+    Builtin::Map,
+    Builtin::Fold,
+    Builtin::Length,
+];
+
+impl Builtin {
+    /// Returns the builtin with the given name, or `None` if the name isn't a builtin
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        BUILTINS.into_iter().find(|builtin| builtin.name() == name)
+    }
+
+    /// The name of the builtin. For operators this is the same as the operator it is written with.
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Add => "+",
+            Builtin::Subtract => "-",
+            Builtin::Multiply => "*",
+            Builtin::Divide => "/",
+            Builtin::Modulo => "%",
+            Builtin::Equal => "==",
+            Builtin::NotEqual => "!=",
+            Builtin::Less => "<",
+            Builtin::LessEqual => "<=",
+            Builtin::Greater => ">",
+            Builtin::GreaterEqual => ">=",
+            Builtin::Concatenate => "++",
+            Builtin::IntToString => "int_to_string",
+            Builtin::Cons => "cons",
+            Builtin::Head => "head",
+            Builtin::Tail => "tail",
+            Builtin::Map => "map",
+            Builtin::Fold => "fold",
+            Builtin::Length => "length",
+        }
+    }
+
+    /// The built-in class this operator is a method of, or `None` for other builtins
+    pub fn class(self) -> Option<BuiltinClass> {
+        match self {
+            Builtin::Add | Builtin::Subtract | Builtin::Multiply | Builtin::Divide | Builtin::Modulo => {
+                Some(BuiltinClass::Num)
+            },
+            Builtin::Equal | Builtin::NotEqual => Some(BuiltinClass::Eq),
+            Builtin::Less | Builtin::LessEqual | Builtin::Greater | Builtin::GreaterEqual => Some(BuiltinClass::Ord),
+            Builtin::Concatenate
+            | Builtin::IntToString
+            | Builtin::Cons
+            | Builtin::Head
+            | Builtin::Tail
+            | Builtin::Map
+            | Builtin::Fold
+            | Builtin::Length => None,
+        }
+    }
+
+    /// The name an instance defines this operator by, since operators can't be written as names.
+    /// Builtins which aren't methods of a class just have their own name.
+    pub fn method_name(self) -> &'static str {
+        match self {
+            Builtin::Add => "add",
+            Builtin::Subtract => "subtract",
+            Builtin::Multiply => "multiply",
+            Builtin::Divide => "divide",
+            Builtin::Modulo => "modulo",
+            Builtin::Equal => "equal",
+            Builtin::NotEqual => "not_equal",
+            Builtin::Less => "less",
+            Builtin::LessEqual => "less_equal",
+            Builtin::Greater => "greater",
+            Builtin::GreaterEqual => "greater_equal",
+            _ => self.name(),
+        }
+    }
+
+    /// Arithmetic operators have the type `a -> a -> a` and comparisons `a -> a -> Bool`, where `a` is
+    /// the generic of the operator's class. `++` is `String -> String -> String`. The list functions are
+    /// generic over the types of elements, e.g. `map` is `(a -> b) -> List a -> List b`.
+    pub fn typ(self) -> Type {
+        let function = |parameter, return_type| Type::Function {
+            parameter: Arc::new(parameter),
+            return_type: Arc::new(return_type),
+        };
+        let list = |element| Type::List(Arc::new(element));
+        let a = || Type::Generic(Arc::new("a".to_string()));
+        let b = || Type::Generic(Arc::new("b".to_string()));
+
+        match self {
+            Builtin::Concatenate => function(Type::String, function(Type::String, Type::String)),
+            Builtin::IntToString => function(Type::Int, Type::String),
+            Builtin::Cons => function(a(), function(list(a()), list(a()))),
+            Builtin::Head => function(list(a()), a()),
+            Builtin::Tail => function(list(a()), list(a())),
+            Builtin::Map => function(function(a(), b()), function(list(a()), list(b()))),
+            Builtin::Fold => function(function(b(), function(a(), b())), function(b(), function(list(a()), b()))),
+            Builtin::Length => function(list(a()), Type::Int),
+            _ if self.is_comparison() => function(a(), function(a(), Type::Bool)),
+            _ => function(a(), function(a(), a())),
+        }
+    }
+
+    /// The number of arguments the builtin takes
+    pub fn arity(self) -> usize {
+        match self {
+            Builtin::IntToString | Builtin::Head | Builtin::Tail | Builtin::Length => 1,
+            Builtin::Fold => 3,
+            _ => 2,
+        }
+    }
+
+    fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            Builtin::Equal
+                | Builtin::NotEqual
+                | Builtin::Less
+                | Builtin::LessEqual
+                | Builtin::Greater
+                | Builtin::GreaterEqual
+        )
+    }
+
+    /// The python expression for the builtin as a curried function. Lists are python lists.
+    /// Operators are used as is for each built-in instance, since `==` and `!=` compare strings too.
+    ///
+    /// Python's `//` and `%` round towards negative infinity, which `evaluate` matches.
+    /// `tail` indexes the first element of the list so that it fails on an empty list like `head` does.
+    /// Python's own builtins are found through the `builtins` module since a definition may shadow them.
+    pub fn python(self) -> &'static str {
+        match self {
+            Builtin::Add => "(lambda x: lambda y: x + y)",
+            Builtin::Subtract => "(lambda x: lambda y: x - y)",
+            Builtin::Multiply => "(lambda x: lambda y: x * y)",
+            Builtin::Divide => "(lambda x: lambda y: x // y)",
+            Builtin::Modulo => "(lambda x: lambda y: x % y)",
+            Builtin::Equal => "(lambda x: lambda y: x == y)",
+            Builtin::NotEqual => "(lambda x: lambda y: x != y)",
+            Builtin::Less => "(lambda x: lambda y: x < y)",
+            Builtin::LessEqual => "(lambda x: lambda y: x <= y)",
+            Builtin::Greater => "(lambda x: lambda y: x > y)",
+            Builtin::GreaterEqual => "(lambda x: lambda y: x >= y)",
+            Builtin::Concatenate => "(lambda x: lambda y: x + y)",
+            Builtin::IntToString => "__import__('builtins').str",
+            Builtin::Cons => "(lambda x: lambda xs: [x] + xs)",
+            Builtin::Head => "(lambda xs: xs[0])",
+            Builtin::Tail => "(lambda xs: (xs[0], xs[1:])[1])",
+            Builtin::Map => "(lambda f: lambda xs: [f(x) for x in xs])",
+            Builtin::Fold => {
+                "(lambda f: lambda acc: lambda xs: __import__('functools').reduce(lambda acc, x: f(acc)(x), xs, acc))"
+            },
+            Builtin::Length => "__import__('builtins').len",
+        }
+    }
+
+    /// The javascript expression for the builtin as a curried function. Lists are javascript arrays.
+    /// Like in python, operators are used as is for each built-in instance.
+    ///
+    /// Like the python backend, `/` and `%` round towards negative infinity. Since javascript doesn't
+    /// fail on division by zero or on indexing past the end of an array, those errors are thrown by hand.
+    /// Javascript's own globals are found through `globalThis` since a definition or constructor may shadow them.
+    pub fn javascript(self) -> &'static str {
+        match self {
+            Builtin::Add => "((x) => (y) => x + y)",
+            Builtin::Subtract => "((x) => (y) => x - y)",
+            Builtin::Multiply => "((x) => (y) => x * y)",
+            Builtin::Divide => {
+                "((x) => (y) => { if (y === 0) throw new globalThis.Error(\"Division by zero\"); \
+                return globalThis.Math.floor(x / y); })"
+            },
+            Builtin::Modulo => {
+                "((x) => (y) => { if (y === 0) throw new globalThis.Error(\"Division by zero\"); \
+                return ((x % y) + y) % y; })"
+            },
+            Builtin::Equal => "((x) => (y) => x === y)",
+            Builtin::NotEqual => "((x) => (y) => x !== y)",
+            Builtin::Less => "((x) => (y) => x < y)",
+            Builtin::LessEqual => "((x) => (y) => x <= y)",
+            Builtin::Greater => "((x) => (y) => x > y)",
+            Builtin::GreaterEqual => "((x) => (y) => x >= y)",
+            Builtin::Concatenate => "((x) => (y) => x + y)",
+            Builtin::IntToString => "globalThis.String",
+            Builtin::Cons => "((x) => (xs) => [x, ...xs])",
+            Builtin::Head => {
+                "((xs) => { if (xs.length === 0) throw new globalThis.Error(\"`head` of an empty list\"); \
+                return xs[0]; })"
+            },
+            Builtin::Tail => {
+                "((xs) => { if (xs.length === 0) throw new globalThis.Error(\"`tail` of an empty list\"); \
+                return xs.slice(1); })"
+            },
+            Builtin::Map => "((f) => (xs) => xs.map((x) => f(x)))",
+            Builtin::Fold => "((f) => (acc) => (xs) => xs.reduce((acc, x) => f(acc)(x), acc))",
+            Builtin::Length => "((xs) => xs.length)",
+        }
+    }
+
+    /// Apply the builtin to each of its arguments. Returns an error on overflow, division by zero,
+    /// or taking the `head` or `tail` of an empty list.
+    ///
+    /// `map` and `fold` call the function they're given, so they're evaluated by the interpreter instead.
+    pub fn evaluate(self, arguments: &[Value]) -> Result<Value, String> {
+        match (self, arguments) {
+            (Builtin::Concatenate, [Value::String(x), Value::String(y)]) => {
+                Ok(Value::String(Arc::new(format!("{x}{y}"))))
+            },
+            (Builtin::IntToString, [Value::Int(x)]) => Ok(Value::String(Arc::new(x.to_string()))),
+            (Builtin::Cons, [x, Value::List(xs)]) => {
+                Ok(Value::List(std::iter::once(x.clone()).chain(xs.iter().cloned()).collect()))
+            },
+            (Builtin::Head, [Value::List(xs)]) => xs.first().cloned().ok_or_else(|| "`head` of an empty list".into()),
+            (Builtin::Tail, [Value::List(xs)]) => match xs.split_first() {
+                Some((_, rest)) => Ok(Value::List(rest.to_vec())),
+                None => Err("`tail` of an empty list".into()),
+            },
+            (Builtin::Length, [Value::List(xs)]) => Ok(Value::Int(xs.len() as i64)),
+            (Builtin::Equal, [Value::String(x), Value::String(y)]) => Ok(Value::Bool(x == y)),
+            (Builtin::NotEqual, [Value::String(x), Value::String(y)]) => Ok(Value::Bool(x != y)),
+            (_, [Value::Int(x), Value::Int(y)]) => self.evaluate_integer_operator(*x, *y),
+            _ => unreachable!("Type checking ensures `{}` is called with arguments of the right type", self.name()),
+        }
+    }
+
+    fn evaluate_integer_operator(self, x: i64, y: i64) -> Result<Value, String> {
+        let overflow = || format!("Integer overflow in `{x} {} {y}`", self.name());
+        let division_by_zero = || format!("Division by zero in `{x} {} {y}`", self.name());
+
+        match self {
+            Builtin::Add => x.checked_add(y).map(Value::Int).ok_or_else(overflow),
+            Builtin::Subtract => x.checked_sub(y).map(Value::Int).ok_or_else(overflow),
+            Builtin::Multiply => x.checked_mul(y).map(Value::Int).ok_or_else(overflow),
+            Builtin::Divide if y == 0 => Err(division_by_zero()),
+            Builtin::Divide => {
+                let quotient = x.checked_div(y).ok_or_else(overflow)?;
+                // Round towards negative infinity like python does
+                let round_down = x % y != 0 && (x < 0) != (y < 0);
+                Ok(Value::Int(if round_down { quotient - 1 } else { quotient }))
+            },
+            Builtin::Modulo if y == 0 => Err(division_by_zero()),
+            // `rem_euclid` differs from python's `%` for negative divisors, so adjust the remainder ourselves
+            Builtin::Modulo => {
+                let remainder = x.checked_rem(y).ok_or_else(overflow)?;
+                Ok(Value::Int(if remainder != 0 && (remainder < 0) != (y < 0) { remainder + y } else { remainder }))
+            },
+            Builtin::Equal => Ok(Value::Bool(x == y)),
+            Builtin::NotEqual => Ok(Value::Bool(x != y)),
+            Builtin::Less => Ok(Value::Bool(x < y)),
+            Builtin::LessEqual => Ok(Value::Bool(x <= y)),
+            Builtin::Greater => Ok(Value::Bool(x > y)),
+            Builtin::GreaterEqual => Ok(Value::Bool(x >= y)),
+            Builtin::Concatenate
+            | Builtin::IntToString
+            | Builtin::Cons
+            | Builtin::Head
+            | Builtin::Tail
+            | Builtin::Map
+            | Builtin::Fold
+            | Builtin::Length => unreachable!("`{}` does not take two integers", self.name()),
+        }
+    }
+}
+
+/// The classes of the built-in operators. Each of their instances is a built-in instance or an
+/// `instance` statement defining each operator of the class by its `Builtin::method_name`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BuiltinClass {
+    Num,
+    Eq,
+    Ord,
+}
+
+pub const BUILTIN_CLASSES: [BuiltinClass; 3] = [BuiltinClass::Num, BuiltinClass::Eq, BuiltinClass::Ord];
+
+impl BuiltinClass {
+    /// Returns the built-in class with the given name, or `None` if the name isn't a built-in class
+    pub fn from_name(name: &str) -> Option<BuiltinClass> {
+        BUILTIN_CLASSES.into_iter().find(|class| class.name() == name)
+    }
+
+    /// Returns the built-in class with the given id, or `None` if the id is of a `class` statement
+    pub fn from_id(id: &TopLevelId) -> Option<BuiltinClass> {
+        if id.file_path.as_str() != BUILTIN_FILE {
+            return None;
+        }
+        BUILTIN_CLASSES.into_iter().find(|class| class.id() == *id)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BuiltinClass::Num => "Num",
+            BuiltinClass::Eq => "Eq",
+            BuiltinClass::Ord => "Ord",
+        }
+    }
+
+    /// The id standing in for the `class` statement of this class
+    pub fn id(self) -> TopLevelId {
+        TopLevelId::new_builtin(Arc::new(BUILTIN_FILE.to_string()), self.name())
+    }
+
+    /// The operators which are the methods of this class, each with the generic `a` standing in for
+    /// the type of the instance
+    pub fn methods(self) -> impl Iterator<Item = Builtin> {
+        BUILTINS.into_iter().filter(move |builtin| builtin.class() == Some(self))
+    }
+
+    /// The types this class has a built-in instance for. Each operator is the same function for
+    /// each of these types, so the built-in instances of a class only differ in their type.
+    pub fn instance_types(self) -> Vec<Type> {
+        match self {
+            BuiltinClass::Num | BuiltinClass::Ord => vec![Type::Int],
+            BuiltinClass::Eq => vec![Type::Int, Type::String],
+        }
+    }
+
+    /// The id standing in for the `instance` statement of this class's built-in instance for the type
+    /// constructor of `typ`, or `None` if it has no built-in instance for it
+    pub fn instance(self, typ: &Type) -> Option<TopLevelId> {
+        let mut types = self.instance_types().into_iter();
+        let instance_type = types.find(|instance_type| instance_type.constructor() == typ.constructor())?;
+        Some(self.instance_id(&instance_type))
+    }
+
+    fn instance_id(self, typ: &Type) -> TopLevelId {
+        let name = format!("{}_{}", self.name(), typ.display(&TypeBindings::new()));
+        TopLevelId::new_builtin(Arc::new(BUILTIN_FILE.to_string()), &name)
+    }
+}
+
+/// Returns the class and type of the built-in instance with the given id, or `None` if the id is of
+/// an `instance` statement
+pub fn builtin_instance(id: &TopLevelId) -> Option<(BuiltinClass, Type)> {
+    if id.file_path.as_str() != BUILTIN_FILE {
+        return None;
+    }
+    let mut instances =
+        BUILTIN_CLASSES.into_iter().flat_map(|class| class.instance_types().into_iter().map(move |typ| (class, typ)));
+    instances.find(|(class, typ)| class.instance_id(typ) == *id)
+}
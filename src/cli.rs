@@ -0,0 +1,129 @@
+//! Parsing of command-line arguments. This is small enough that we parse arguments by hand
+//! rather than pulling in an argument parsing library.
+use std::{path::PathBuf, sync::Arc};
+
+use crate::backend::Target;
+
+pub const USAGE: &str = "\
+Usage: modern_compiler_architecture [COMMAND] [FILE] [OPTIONS]
+
+Commands:
+  build   Compile FILE and each file it imports to python or javascript (default)
+  check   Type check FILE and each file it imports without generating any code
+  run     Type check FILE and each file it imports, then interpret it
+  clean   Delete the incremental cache and any generated files
+  watch   Build FILE, then rebuild it each time it or a file it imports changes
+  repl    Start an interactive session. FILE is not used
+  lsp     Start a language server communicating over stdin and stdout
+
+Arguments:
+  FILE    The entry file of the program [default: input.ex]
+
+Options:
+  -o, --output-dir <DIR>  Directory to write generated files to [default: .]
+      --cache-dir <DIR>   Directory to store incremental compilation metadata in [default: .]
+      --target <TARGET>   Language to compile to, either `python` or `javascript` [default: python]
+      --message-format <FORMAT>
+                          How to print diagnostics, either `human` or `json` [default: human]
+  -h, --help              Print this message";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Command {
+    Build,
+    Check,
+    Run,
+    Clean,
+    Lsp,
+    Watch,
+    Repl,
+    Help,
+}
+
+/// How diagnostics are printed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Rendered with source snippets for a person to read
+    Human,
+    /// One JSON object per line for other tools to consume. Nothing else is printed to stdout
+    /// in this mode.
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub command: Command,
+    pub input_file: Arc<String>,
+    pub output_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    pub message_format: MessageFormat,
+    pub target: Target,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            command: Command::Build,
+            input_file: Arc::new("input.ex".to_string()),
+            output_dir: PathBuf::from("."),
+            cache_dir: PathBuf::from("."),
+            message_format: MessageFormat::Human,
+            target: Target::Python,
+        }
+    }
+}
+
+/// Parse the given arguments (excluding the program name).
+/// Options may be given either as `--flag value` or `--flag=value`.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut command = None;
+    let mut input_file = None;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with('-') => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+
+        let mut value = |flag: &str| {
+            inline_value.clone().or_else(|| args.next()).ok_or_else(|| format!("`{flag}` expects a value"))
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => command = Some(Command::Help),
+            "-o" | "--output-dir" => options.output_dir = PathBuf::from(value(&flag)?),
+            "--cache-dir" => options.cache_dir = PathBuf::from(value(&flag)?),
+            "--message-format" => {
+                options.message_format = match value(&flag)?.as_str() {
+                    "human" => MessageFormat::Human,
+                    "json" => MessageFormat::Json,
+                    other => return Err(format!("Unknown message format `{other}`, expected `human` or `json`")),
+                }
+            },
+            "--target" => {
+                options.target = match value(&flag)?.as_str() {
+                    "python" => Target::Python,
+                    "javascript" | "js" => Target::JavaScript,
+                    other => return Err(format!("Unknown target `{other}`, expected `python` or `javascript`")),
+                }
+            },
+            other if other.starts_with('-') => return Err(format!("Unknown option `{other}`")),
+            "build" if command.is_none() && input_file.is_none() => command = Some(Command::Build),
+            "check" if command.is_none() && input_file.is_none() => command = Some(Command::Check),
+            "run" if command.is_none() && input_file.is_none() => command = Some(Command::Run),
+            "clean" if command.is_none() && input_file.is_none() => command = Some(Command::Clean),
+            "lsp" if command.is_none() && input_file.is_none() => command = Some(Command::Lsp),
+            "repl" if command.is_none() && input_file.is_none() => command = Some(Command::Repl),
+            "watch" if command.is_none() && input_file.is_none() => command = Some(Command::Watch),
+            _ if input_file.is_none() => input_file = Some(Arc::new(arg)),
+            _ => return Err(format!("Unexpected argument `{arg}`")),
+        }
+    }
+
+    options.command = command.unwrap_or(Command::Build);
+    if let Some(input_file) = input_file {
+        options.input_file = input_file;
+    }
+    Ok(options)
+}
@@ -1,11 +1,18 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use crate::{
+    builtins::BuiltinClass,
     errors::{Error, Errors, Location},
     incremental::{
-        self, parse, CompilerHandle, Definitions, ExportedDefinitions, GetImports, VisibleDefinitions
+        self, parse, CompilerHandle, Definitions, ExportedDefinitions, ExportedInstances, GetImports, GetStatement,
+        Instances, Resolve, VisibleDefinitions, VisibleInstances,
     },
-    parser::ast::TopLevelStatement,
+    name_resolution::{class_id, Origin},
+    parser::{
+        ast::{Identifier, TopLevelStatement},
+        ids::TopLevelId,
+    },
+    type_inference::types::{Type, TypeConstructor},
 };
 
 /// Collect all definitions which should be visible to expressions within this file.
@@ -21,23 +28,17 @@ pub fn visible_definitions_impl(context: &VisibleDefinitions, db: &CompilerHandl
     let ast = parse(context.file_name.clone(), db).0;
 
     for item in ast.statements.iter() {
-        if let TopLevelStatement::Import { file_name, id: import_id } = item {
+        if let TopLevelStatement::Import { file_name, names, id: import_id } = item {
             // Ignore errors from imported files. We want to only collect errors
             // from this file. Otherwise we'll duplicate errors.
-            let (exports, _errors) = ExportedDefinitions { file_name: file_name.name.clone() }.get(db);
-
-            for (exported_name, exported_id) in exports {
-                if let Some(existing) = definitions.get(&exported_name) {
-                    // This reports the location the item was defined in, not the location it was imported at.
-                    // I could improve this but instead I'll leave it as an exercise for the reader!
-                    let first_location = existing.location(db);
-                    let second_location = import_id.location(db);
-                    let name = exported_name;
-                    errors.push(Error::ImportedNameAlreadyInScope { name, first_location, second_location });
-                } else {
-                    definitions.insert(exported_name, exported_id);
-                }
+            let (mut exports, _errors) = ExportedDefinitions { file_name: file_name.name.clone() }.get(db);
+            if let Some(names) = names {
+                select_imported_names(&mut exports, names, import_id, &mut errors, db);
             }
+
+            import(&mut definitions.values, exports.values, import_id, &mut errors, db);
+            import(&mut definitions.types, exports.types, import_id, &mut errors, db);
+            import(&mut definitions.classes, exports.classes, import_id, &mut errors, db);
         }
     }
 
@@ -45,6 +46,64 @@ pub fn visible_definitions_impl(context: &VisibleDefinitions, db: &CompilerHandl
     (definitions, errors)
 }
 
+/// Keep only the names listed by an `import foo (a, B)`, issuing an error for any which `foo` doesn't define.
+/// A listed name is imported from each namespace it is in, e.g. both a type and a constructor of the same name.
+fn select_imported_names(
+    exports: &mut Definitions, names: &[Identifier], import_id: &TopLevelId, errors: &mut Errors, db: &CompilerHandle,
+) {
+    for Identifier { name, id } in names {
+        let exported =
+            exports.values.contains_key(name) || exports.types.contains_key(name) || exports.classes.contains_key(name);
+
+        if !exported {
+            let location = id.location(import_id, db);
+            errors.push(Error::NameNotInScope { name: name.clone(), location });
+        }
+    }
+
+    let is_listed = |name: &Arc<String>| names.iter().any(|listed| listed.name == *name);
+    exports.values.retain(|name, _| is_listed(name));
+    exports.types.retain(|name, _| is_listed(name));
+    exports.classes.retain(|name, _| is_listed(name));
+}
+
+/// Add each imported name to the given namespace, issuing an error for any already in it
+fn import<T: Definition>(
+    namespace: &mut BTreeMap<Arc<String>, T>, exports: BTreeMap<Arc<String>, T>, import_id: &TopLevelId,
+    errors: &mut Errors, db: &CompilerHandle,
+) {
+    for (exported_name, exported) in exports {
+        if let Some(existing) = namespace.get(&exported_name) {
+            // This reports the location the item was defined in, not the location it was imported at.
+            // I could improve this but instead I'll leave it as an exercise for the reader!
+            let first_location = existing.statement().location(db);
+            let second_location = import_id.location(db);
+            let name = exported_name;
+            errors.push(Error::ImportedNameAlreadyInScope { name, first_location, second_location });
+        } else {
+            namespace.insert(exported_name, exported);
+        }
+    }
+}
+
+/// Anything stored in a namespace of `Definitions`
+trait Definition {
+    /// The top-level statement this is defined in
+    fn statement(&self) -> &TopLevelId;
+}
+
+impl Definition for TopLevelId {
+    fn statement(&self) -> &TopLevelId {
+        self
+    }
+}
+
+impl Definition for Origin {
+    fn statement(&self) -> &TopLevelId {
+        self.top_level_id().expect("Only global names are collected in `Definitions`")
+    }
+}
+
 /// Collect only the exported definitions within a file.
 /// For this small example language, this is all top-level definitions in a file, except for imported ones.
 pub fn exported_definitions_impl(context: &ExportedDefinitions, db: &CompilerHandle) -> (Definitions, Errors) {
@@ -56,15 +115,33 @@ pub fn exported_definitions_impl(context: &ExportedDefinitions, db: &CompilerHan
 
     // Collect each definition, issuing an error if there is a duplicate name (imports are not counted)
     for item in ast.statements.iter() {
-        if let TopLevelStatement::Definition(definition) = item {
-            if let Some(existing) = definitions.get(&definition.name.name) {
-                let first_location = existing.location(db);
-                let second_location = definition.name.id.location(&definition.id, db);
-                let name = definition.name.name.clone();
-                errors.push(Error::NameAlreadyInScope { name, first_location, second_location });
-            } else {
-                definitions.insert(definition.name.name.clone(), definition.id.clone());
-            }
+        match item {
+            TopLevelStatement::Definition(definition) => {
+                let origin = Origin::TopLevelDefinition(definition.id.clone());
+                define(&mut definitions.values, &definition.name, origin, &definition.id, &mut errors, db);
+            },
+            TopLevelStatement::TypeDefinition(definition) => {
+                let id = &definition.id;
+                define(&mut definitions.types, &definition.name, id.clone(), id, &mut errors, db);
+
+                for constructor in definition.constructors.iter() {
+                    let origin = Origin::Constructor(id.clone(), constructor.name.name.clone());
+                    define(&mut definitions.values, &constructor.name, origin, id, &mut errors, db);
+                }
+            },
+            TopLevelStatement::TypeAlias(alias) => {
+                define(&mut definitions.types, &alias.name, alias.id.clone(), &alias.id, &mut errors, db);
+            },
+            TopLevelStatement::Class(class) => {
+                let id = &class.id;
+                define(&mut definitions.classes, &class.name, id.clone(), id, &mut errors, db);
+
+                for (method, _) in class.methods.iter() {
+                    let origin = Origin::Method(id.clone(), method.name.clone());
+                    define(&mut definitions.values, method, origin, id, &mut errors, db);
+                }
+            },
+            TopLevelStatement::Import { .. } | TopLevelStatement::Print(..) | TopLevelStatement::Instance(_) => (),
         }
     }
 
@@ -72,6 +149,99 @@ pub fn exported_definitions_impl(context: &ExportedDefinitions, db: &CompilerHan
     (definitions, errors)
 }
 
+/// Add a name defined in the statement `item` to the given namespace, issuing an error if it is already in it
+fn define<T: Definition>(
+    namespace: &mut BTreeMap<Arc<String>, T>, name: &Identifier, definition: T, item: &TopLevelId, errors: &mut Errors,
+    db: &CompilerHandle,
+) {
+    if let Some(existing) = namespace.get(&name.name) {
+        let first_location = existing.statement().location(db);
+        let second_location = name.id.location(item, db);
+        let name = name.name.clone();
+        errors.push(Error::NameAlreadyInScope { name, first_location, second_location });
+    } else {
+        namespace.insert(name.name.clone(), definition);
+    }
+}
+
+/// Collect the instances usable within this file: each instance in this file or in one it imports.
+/// Like definitions, instances aren't imported transitively.
+pub fn visible_instances_impl(context: &VisibleInstances, db: &CompilerHandle) -> (Instances, Errors) {
+    incremental::enter_query();
+    incremental::println(format!("Collecting visible instances in {}", context.file_name));
+
+    let (mut instances, mut errors) = ExportedInstances { file_name: context.file_name.clone() }.get(db);
+    let ast = parse(context.file_name.clone(), db).0;
+
+    for item in ast.statements.iter() {
+        if let TopLevelStatement::Import { file_name, id: import_id, .. } = item {
+            // As with definitions, errors within the imported file are reported in that file
+            let (exports, _errors) = ExportedInstances { file_name: file_name.name.clone() }.get(db);
+
+            for (key, instance) in exports {
+                let second_location = import_id.location(db);
+                add_instance(&mut instances, key, instance, second_location, &mut errors, db);
+            }
+        }
+    }
+
+    incremental::exit_query();
+    (instances, errors)
+}
+
+/// Collect each instance defined within this file. Instances whose class or type failed to resolve
+/// are left out, their errors are issued by name resolution instead. So are instances for a type a
+/// built-in class already has a built-in instance for, issuing an error for each.
+pub fn exported_instances_impl(context: &ExportedInstances, db: &CompilerHandle) -> (Instances, Errors) {
+    incremental::enter_query();
+    incremental::println(format!("Collecting exported instances in {}", context.file_name));
+
+    // Parse errors are already included in ExportedDefinitions' errors
+    let ast = parse(context.file_name.clone(), db).0;
+    let mut instances = Instances::new();
+    let mut errors = Vec::new();
+
+    for item in ast.statements.iter() {
+        if let TopLevelStatement::Instance(instance) = item {
+            let origins = Resolve(instance.id.clone()).get(db).origins;
+            let Some(class) = class_id(&instance.class, &origins) else { continue };
+            let typ = Type::from_ast_type(&instance.typ, &origins, db);
+            let Some(constructor) = typ.constructor() else { continue };
+
+            let second_location = instance.class.id.location(&instance.id, db);
+            if BuiltinClass::from_id(&class).and_then(|class| class.instance(&typ)).is_some() {
+                let class = instance.class.name.clone();
+                errors.push(Error::BuiltinInstance { class, typ, location: second_location });
+                continue;
+            }
+
+            let key = (class, constructor);
+            add_instance(&mut instances, key, instance.id.clone(), second_location, &mut errors, db);
+        }
+    }
+
+    incremental::exit_query();
+    (instances, errors)
+}
+
+/// Add an instance found at `location`, issuing an error if there is already an instance of the same
+/// class for the same type constructor since we wouldn't know which to use
+fn add_instance(
+    instances: &mut Instances, key: (TopLevelId, TypeConstructor), instance: TopLevelId, second_location: Location,
+    errors: &mut Errors, db: &CompilerHandle,
+) {
+    if let Some(existing) = instances.get(&key) {
+        let TopLevelStatement::Instance(existing_instance) = GetStatement(existing.clone()).get(db) else {
+            unreachable!("Instances should refer to an instance statement")
+        };
+        let first_location = existing_instance.class.id.location(existing, db);
+        let class = existing_instance.class.name;
+        errors.push(Error::DuplicateInstance { class, first_location, second_location });
+    } else {
+        instances.insert(key, instance);
+    }
+}
+
 /// Collects the file names of all imports within this file.
 pub fn get_imports_impl(context: &GetImports, db: &CompilerHandle) -> Vec<(Arc<String>, Location)> {
     incremental::enter_query();
@@ -83,7 +253,7 @@ pub fn get_imports_impl(context: &GetImports, db: &CompilerHandle) -> Vec<(Arc<S
 
     // Collect each definition, issuing an error if there is a duplicate name (imports are not counted)
     for item in ast.statements.iter() {
-        if let TopLevelStatement::Import { file_name, id } = item {
+        if let TopLevelStatement::Import { file_name, id, .. } = item {
             // We don't care about duplicate imports.
             // This method is only used for finding input files and the top-level
             // will filter out any repeats.
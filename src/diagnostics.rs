@@ -0,0 +1,319 @@
+//! Rendering of errors for display in a terminal. Each error is shown along with a snippet of
+//! the source code it refers to, with the exact span of each location underlined:
+//!
+//! ```text
+//! error: `foo` was already defined
+//!  --> input.ex:7:5
+//!   |
+//! 7 | def foo = 2
+//!   |     ^^^ redefined here
+//!   |
+//!  ::: input.ex:3:5
+//!   |
+//! 3 | def foo = 1
+//!   |     --- first defined here
+//! ```
+//!
+//! The text of each file is retrieved from the `SourceFile` inputs of the compiler so we always
+//! display the same text the error was found in.
+//!
+//! Errors can also be converted to JSON with `to_json` for tools which consume our diagnostics.
+use std::{fmt::Write, io::IsTerminal};
+
+use crate::{
+    errors::{Error, Label, Severity},
+    incremental::{get_source_file, Compiler},
+    json::Json,
+};
+
+/// Spans covering more lines than this have their middle lines elided
+const MAX_SNIPPET_LINES: usize = 4;
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+pub struct DiagnosticRenderer<'c> {
+    compiler: &'c Compiler,
+    color: bool,
+}
+
+/// A single line of a source snippet with the range of characters to underline on that line
+struct SnippetLine {
+    line_number: usize,
+    text: String,
+    underline_start: usize,
+    underline_width: usize,
+}
+
+impl<'c> DiagnosticRenderer<'c> {
+    pub fn new(compiler: &'c Compiler, color: bool) -> Self {
+        Self { compiler, color }
+    }
+
+    /// Create a renderer which only uses color if stdout is a terminal and the user
+    /// hasn't opted out via the `NO_COLOR` environment variable.
+    pub fn for_stdout(compiler: &'c Compiler) -> Self {
+        let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        Self::new(compiler, color)
+    }
+
+    pub fn render(&self, error: &Error) -> String {
+        let labels = error.labels();
+        let snippets: Vec<_> = labels.iter().map(|label| self.snippet(label)).collect();
+
+        let gutter_width = snippets
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|line| line.line_number.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        let padding = " ".repeat(gutter_width);
+        let bar = self.paint("|", BLUE);
+
+        let severity_color = match error.severity() {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+
+        let mut out = String::new();
+        let severity = self.paint(&error.severity().to_string(), severity_color);
+        writeln!(out, "{severity}: {}", self.paint(&error.message(), BOLD)).unwrap();
+
+        for (i, (label, snippet)) in labels.iter().zip(snippets).enumerate() {
+            if i != 0 {
+                writeln!(out, "{padding} {bar}").unwrap();
+            }
+
+            let arrow = if label.primary { "-->" } else { ":::" };
+            writeln!(out, "{padding}{} {}", self.paint(arrow, BLUE), label.location).unwrap();
+            writeln!(out, "{padding} {bar}").unwrap();
+
+            let (marker, color) = if label.primary { ('^', severity_color) } else { ('-', BLUE) };
+            let last_line = snippet.len().saturating_sub(1);
+
+            for (line_index, line) in snippet.into_iter().enumerate() {
+                let Some(line) = line else {
+                    writeln!(out, "{}", self.paint("...", BLUE)).unwrap();
+                    continue;
+                };
+
+                let number = self.paint(&format!("{:>gutter_width$}", line.line_number), BLUE);
+                writeln!(out, "{number} {bar} {}", line.text).unwrap();
+
+                let mut underline = " ".repeat(line.underline_start);
+                underline.extend(std::iter::repeat_n(marker, line.underline_width));
+                if line_index == last_line && !label.message.is_empty() {
+                    underline = format!("{underline} {}", label.message);
+                }
+                writeln!(out, "{padding} {bar} {}", self.paint(&underline, color)).unwrap();
+            }
+        }
+
+        out
+    }
+
+    /// Returns each line the label's location spans. If the location spans too many lines,
+    /// the middle lines are replaced with a single `None`.
+    fn snippet(&self, label: &Label) -> Vec<Option<SnippetLine>> {
+        let text = get_source_file(label.location.file_name.clone(), self.compiler);
+
+        let start = floor_char_boundary(&text, label.location.start.byte_index);
+        let end = floor_char_boundary(&text, label.location.end.byte_index).max(start);
+
+        let mut line_start = text[..start].rfind('\n').map_or(0, |index| index + 1);
+        let mut line_number = text[..line_start].matches('\n').count() + 1;
+        let mut lines = Vec::new();
+
+        loop {
+            let line_end = text[line_start..].find('\n').map_or(text.len(), |index| line_start + index);
+            let line = &text[line_start..line_end];
+
+            // Columns are counted in characters rather than bytes so that multi-byte
+            // characters before the span don't shift the carets over.
+            let underline_start = start.max(line_start) - line_start;
+            let underline_end = end.min(line_end).max(line_start + underline_start) - line_start;
+
+            lines.push(Some(SnippetLine {
+                line_number,
+                text: line.replace('\t', " "),
+                underline_start: line[..underline_start].chars().count(),
+                underline_width: line[underline_start..underline_end].chars().count().max(1),
+            }));
+
+            // Stop once the span ends on this line (or within its trailing newline)
+            if end <= line_end + 1 || line_end == text.len() {
+                break;
+            }
+            line_start = line_end + 1;
+            line_number += 1;
+        }
+
+        if lines.len() > MAX_SNIPPET_LINES {
+            let last = lines.pop();
+            lines.truncate(2);
+            lines.push(None);
+            lines.push(last.flatten());
+        }
+        lines
+    }
+
+    fn paint(&self, text: &str, color: &str) -> String {
+        if self.color { format!("{color}{text}{RESET}") } else { text.to_string() }
+    }
+}
+
+/// Convert an error to a JSON object of the form:
+///
+/// ```json
+/// {"code":"E0005","severity":"error","message":"`foo` is not defined, was it a typo?","locations":[
+///     {"file":"input.ex","line":3,"column":11,"end_line":3,"end_column":14,"byte_start":40,"byte_end":43,
+///      "primary":true,"label":""}
+/// ]}
+/// ```
+///
+/// Line and column numbers are 1-based, and columns are counted in characters.
+/// Byte ranges are half-open: `byte_end` is one past the last byte of the location.
+pub fn to_json(error: &Error) -> Json {
+    let locations = error.labels().into_iter().map(|label| {
+        let location = label.location;
+        Json::object([
+            ("file", Json::from(location.file_name.as_str())),
+            ("line", location.start.line_number.into()),
+            ("column", location.start.column_number.into()),
+            ("end_line", location.end.line_number.into()),
+            ("end_column", location.end.column_number.into()),
+            ("byte_start", location.start.byte_index.into()),
+            ("byte_end", location.end.byte_index.into()),
+            ("primary", label.primary.into()),
+            ("label", label.message.into()),
+        ])
+    });
+
+    Json::object([
+        ("code", error.code().into()),
+        ("severity", error.severity().to_string().into()),
+        ("message", error.message().into()),
+        ("locations", Json::Array(locations.collect())),
+    ])
+}
+
+/// Clamp the given byte index to the text, moving it backward to the start of the
+/// character it is in if it is not already on a character boundary.
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::DiagnosticRenderer;
+    use crate::{
+        errors::{Error, LocationData, Position},
+        incremental::{set_source_file, Compiler},
+        type_inference::types::Type,
+    };
+
+    const FILE: &str = "main.ex";
+
+    const SOURCE: &str = "\
+def one = 1
+print foo one
+def pair = if true
+    then (one, 2)
+    else 3
+";
+
+    /// Render the error as it would be shown when printed to a terminal if `color` is set,
+    /// or to a file otherwise
+    fn render(error: &Error, color: bool) -> String {
+        let mut compiler = Compiler::default();
+        set_source_file(Arc::new(FILE.to_string()), SOURCE.to_string(), &mut compiler);
+        DiagnosticRenderer::new(&compiler, color).render(error)
+    }
+
+    /// The location of the first occurrence of `text` in `SOURCE`
+    fn location_of(text: &str) -> Arc<LocationData> {
+        let start = SOURCE.find(text).unwrap();
+        let file_name = Arc::new(FILE.to_string());
+        Arc::new(LocationData { file_name, start: position(start), end: position(start + text.len()) })
+    }
+
+    fn position(byte_index: usize) -> Position {
+        let before = &SOURCE[..byte_index];
+        let line_number = before.matches('\n').count() as u32 + 1;
+        let column_number = before.chars().rev().take_while(|char| *char != '\n').count() as u32 + 1;
+        Position { byte_index, line_number, column_number }
+    }
+
+    fn name_not_in_scope(name: &str) -> Error {
+        Error::NameNotInScope { name: Arc::new(name.to_string()), location: location_of(name) }
+    }
+
+    #[test]
+    fn underlines_a_span_within_one_line() {
+        let expected = "\
+error: `foo` is not defined, was it a typo?
+ --> main.ex:2:7
+  |
+2 | print foo one
+  |       ^^^
+";
+        assert_eq!(render(&name_not_in_scope("foo"), false), expected);
+    }
+
+    #[test]
+    fn underlines_each_line_of_a_span_over_several_lines() {
+        let location = location_of("if true\n    then (one, 2)\n    else 3");
+        let error = Error::ExpectedType { actual: Type::Int, expected: Type::Unit, path: Box::new([]), location };
+        let expected = "\
+error: Expected type `Unit` but found `Int`
+ --> main.ex:3:12
+  |
+3 | def pair = if true
+  |            ^^^^^^^
+4 |     then (one, 2)
+  | ^^^^^^^^^^^^^^^^^
+5 |     else 3
+  | ^^^^^^^^^^
+";
+        assert_eq!(render(&error, false), expected);
+    }
+
+    #[test]
+    fn places_a_caret_at_the_first_column() {
+        let expected = "\
+error: `print` is not defined, was it a typo?
+ --> main.ex:2:1
+  |
+2 | print foo one
+  | ^^^^^
+";
+        assert_eq!(render(&name_not_in_scope("print"), false), expected);
+    }
+
+    #[test]
+    fn only_colors_output_when_enabled() {
+        let error = name_not_in_scope("foo");
+        let colored = render(&error, true);
+        let plain = render(&error, false);
+        assert!(colored.contains('\x1b'), "{colored}");
+        assert!(!plain.contains('\x1b'), "{plain}");
+
+        // Without the escape codes, both are the same text
+        let mut uncolored = colored.clone();
+        for code in [super::RED, super::BLUE, super::BOLD, super::RESET] {
+            uncolored = uncolored.replace(code, "");
+        }
+        assert_eq!(uncolored, plain);
+    }
+}
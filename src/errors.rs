@@ -2,7 +2,10 @@ use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 
-use crate::lexer::tokens::Token;
+use crate::{
+    lexer::tokens::Token,
+    type_inference::types::{describe_type_position, Type, TypeBindings, TypePosition},
+};
 
 pub type Location = Arc<LocationData>;
 
@@ -44,54 +47,317 @@ pub enum Error {
     ImportedNameAlreadyInScope { name: Arc<String>, first_location: Location, second_location: Location },
     UnknownImportFile { file_name: Arc<String>, location: Location },
     NameNotInScope { name: Arc<String>, location: Location },
-    ExpectedType { actual: String, expected: String, location: Location },
-    RecursiveType { typ: String, location: Location },
+    ExpectedType { actual: Type, expected: Type, path: Box<[TypePosition]>, location: Location },
+    RecursiveType { type_variable: Type, typ: Type, location: Location },
+    InvalidName { name: Arc<String>, location: Location },
+    RenameConflict { name: Arc<String>, location: Location, existing_location: Location },
+    RenameShadowed { name: Arc<String>, location: Location },
+    RecursiveUseNeedsAnnotation { name: Arc<String>, actual: Type, expected: Type, location: Location },
+    IfBranchMismatch { then_type: Type, else_type: Type, then_location: Location, else_location: Location },
+    NonExhaustiveMatch { missing: String, location: Location },
+    RedundantMatchArm { location: Location },
+    ConstructorPatternArity { name: Arc<String>, expected: usize, actual: usize, location: Location },
+    TypeArgumentCount { name: Arc<String>, expected: usize, actual: usize, location: Location },
+    UnterminatedString { location: Location },
+    InvalidEscapeSequence { escape: char, location: Location },
+    DuplicateField { name: Arc<String>, first_location: Location, second_location: Location },
+    NoSuchField { field: Arc<String>, typ: Type, location: Location },
+    RefutableParameter { missing: String, location: Location },
+    NoInstance { class: Arc<String>, typ: Type, location: Location },
+    AmbiguousType { class: Arc<String>, typ: Type, location: Location },
+    DuplicateInstance { class: Arc<String>, first_location: Location, second_location: Location },
+    MissingMethod { name: Arc<String>, class: Arc<String>, location: Location },
+    NotAMethod { name: Arc<String>, class: Arc<String>, location: Location },
+    InvalidInstanceType { location: Location },
+    RecursiveTypeAlias { name: Arc<String>, location: Location },
+    TypedHole { name: Option<Arc<String>>, typ: Type, candidates: Vec<Arc<String>>, location: Location },
+    BuiltinInstance { class: Arc<String>, typ: Type, location: Location },
+}
+
+/// A location an error points to along with a short description of what is there.
+/// Each error has exactly one primary label and zero or more secondary labels.
+pub struct Label<'a> {
+    pub location: &'a Location,
+    pub message: &'static str,
+    pub primary: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    /// Warnings are reported like errors but don't stop the program from being compiled or run
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
 }
 
 impl Error {
+    /// A stable code identifying the kind of this error. These are meant for tools consuming
+    /// our diagnostics, so existing codes should never be changed or reused.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::ParserExpected { .. } => "E0001",
+            Error::NameAlreadyInScope { .. } => "E0002",
+            Error::ImportedNameAlreadyInScope { .. } => "E0003",
+            Error::UnknownImportFile { .. } => "E0004",
+            Error::NameNotInScope { .. } => "E0005",
+            Error::ExpectedType { .. } => "E0006",
+            Error::RecursiveType { .. } => "E0007",
+            Error::InvalidName { .. } => "E0008",
+            Error::RenameConflict { .. } => "E0009",
+            Error::RenameShadowed { .. } => "E0010",
+            Error::RecursiveUseNeedsAnnotation { .. } => "E0011",
+            Error::IfBranchMismatch { .. } => "E0012",
+            Error::NonExhaustiveMatch { .. } => "E0013",
+            Error::RedundantMatchArm { .. } => "E0014",
+            Error::ConstructorPatternArity { .. } => "E0015",
+            Error::TypeArgumentCount { .. } => "E0016",
+            Error::UnterminatedString { .. } => "E0017",
+            Error::InvalidEscapeSequence { .. } => "E0018",
+            Error::DuplicateField { .. } => "E0019",
+            Error::NoSuchField { .. } => "E0020",
+            Error::RefutableParameter { .. } => "E0021",
+            Error::NoInstance { .. } => "E0022",
+            Error::AmbiguousType { .. } => "E0023",
+            Error::DuplicateInstance { .. } => "E0024",
+            Error::MissingMethod { .. } => "E0025",
+            Error::NotAMethod { .. } => "E0026",
+            Error::InvalidInstanceType { .. } => "E0027",
+            Error::RecursiveTypeAlias { .. } => "E0028",
+            Error::TypedHole { .. } => "E0029",
+            Error::BuiltinInstance { .. } => "E0030",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            Error::TypedHole { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// The headline message of this error, without any location information
     pub fn message(&self) -> String {
         match self {
-            Error::ParserExpected { rule, found, location } => {
+            Error::ParserExpected { rule, found, location: _ } => {
                 let found = found.as_ref().map_or("(end of input)".to_string(), ToString::to_string);
-                format!("{location}: Expected {rule} but found `{found}`")
+                format!("Expected {rule} but found `{found}`")
+            },
+            Error::NameAlreadyInScope { name, .. } => {
+                format!("`{name}` was already defined")
+            },
+            Error::ImportedNameAlreadyInScope { name, .. } => {
+                format!("This imports `{name}`, which has already been defined")
+            },
+            Error::UnknownImportFile { file_name, location: _ } => {
+                format!("Cannot read source file `{file_name}`, does it exist?")
+            },
+            Error::NameNotInScope { name, location: _ } => {
+                format!("`{name}` is not defined, was it a typo?")
+            },
+            Error::ExpectedType { actual, expected, path, location: _ } => {
+                // Types in errors are already resolved, so they're displayed without any bindings
+                let bindings = TypeBindings::new();
+                let (actual_string, expected_string) = (actual.display(&bindings), expected.display(&bindings));
+                let mut message = format!("Expected type `{expected_string}` but found `{actual_string}`");
+
+                let parts = actual.at_path(path).zip(expected.at_path(path));
+                if let Some(position) = describe_type_position(path)
+                    && let Some((actual, expected)) = parts
+                {
+                    let (actual, expected) = (actual.display(&bindings), expected.display(&bindings));
+                    message += &format!(": {position} is `{actual}` instead of `{expected}`");
+                }
+                message
             },
-            Error::NameAlreadyInScope { name, first_location, second_location } => {
-                format!("{second_location}: `{name}` was already defined at {first_location}")
+            Error::RecursiveType { type_variable, typ, location: _ } => {
+                let bindings = TypeBindings::new();
+                let (type_variable, typ) = (type_variable.display(&bindings), typ.display(&bindings));
+                format!("Binding `{type_variable}` to `{typ}` here would create an infinitely recursive type")
             },
-            Error::ImportedNameAlreadyInScope { name, first_location, second_location } => {
+            Error::InvalidName { name, location: _ } => {
+                format!("`{name}` is not a valid name")
+            },
+            Error::RenameConflict { name, .. } => {
+                format!("Cannot rename to `{name}`, which is already defined")
+            },
+            Error::RenameShadowed { name, location: _ } => {
+                format!("Cannot rename to `{name}`, this would change which definition this name refers to")
+            },
+            Error::RecursiveUseNeedsAnnotation { name, actual, expected, location: _ } => {
+                let bindings = TypeBindings::new();
+                let (actual, expected) = (actual.display(&bindings), expected.display(&bindings));
                 format!(
-                    "{second_location}: This imports `{name}`, which has already been defined here: {first_location}"
+                    "`{name}` is used at type `{actual}` within its own recursive group, but has type `{expected}`. \
+                    Using a recursive definition at a different type requires a type annotation on `{name}`"
                 )
             },
-            Error::UnknownImportFile { file_name, location } => {
-                format!("{location}: Cannot read source file `{file_name}`, does it exist?")
+            Error::IfBranchMismatch { then_type, else_type, .. } => {
+                let bindings = TypeBindings::new();
+                let (then_type, else_type) = (then_type.display(&bindings), else_type.display(&bindings));
+                format!("The branches of this `if` have different types: `{then_type}` and `{else_type}`")
+            },
+            Error::NonExhaustiveMatch { missing, location: _ } => {
+                format!("This match does not handle every case, e.g. `{missing}`")
+            },
+            Error::RedundantMatchArm { location: _ } => {
+                "This match arm is never reached since earlier arms already handle each value it matches".to_string()
+            },
+            Error::ConstructorPatternArity { name, expected, actual, location: _ } => {
+                format!("`{name}` has {} but this pattern matches {actual}", plural(*expected, "field"))
+            },
+            Error::TypeArgumentCount { name, expected, actual, location: _ } => {
+                format!("`{name}` expects {} but was given {actual}", plural(*expected, "type argument"))
             },
-            Error::NameNotInScope { name, location } => {
-                format!("{location}: `{name}` is not defined, was it a typo?")
+            Error::UnterminatedString { location: _ } => {
+                "This string is missing a closing `\"` before the end of the line".to_string()
             },
-            Error::ExpectedType { actual, expected, location } => {
-                format!("{location}: Expected type `{expected}` but found `{actual}`")
+            Error::InvalidEscapeSequence { escape, location: _ } => {
+                format!("`\\{escape}` is not a valid escape sequence")
             },
-            Error::RecursiveType { typ, location } => {
-                format!("{location}: Binding here would create an infinitely recursive type with `{typ}`")
+            Error::DuplicateField { name, .. } => {
+                format!("Field `{name}` was already given")
             },
+            Error::NoSuchField { field, typ, location: _ } => {
+                format!("Type `{}` has no field `{field}`", typ.display(&TypeBindings::new()))
+            },
+            Error::RefutableParameter { missing, location: _ } => {
+                format!("This parameter does not match every argument, e.g. `{missing}`. Use a `match` instead")
+            },
+            Error::NoInstance { class, typ, location: _ } => {
+                format!("Type `{}` has no instance of `{class}`", typ.display(&TypeBindings::new()))
+            },
+            Error::AmbiguousType { class, typ, location: _ } => {
+                let typ = typ.display(&TypeBindings::new()).to_string();
+                format!("Cannot tell which instance of `{class}` to use here since type `{typ}` is unknown")
+            },
+            Error::DuplicateInstance { class, .. } => {
+                format!("There is already an instance of `{class}` for this type")
+            },
+            Error::BuiltinInstance { class, typ, location: _ } => {
+                format!("`{class}` already has a built-in instance for `{}`", typ.display(&TypeBindings::new()))
+            },
+            Error::MissingMethod { name, class, location: _ } => {
+                format!("This instance of `{class}` is missing method `{name}`")
+            },
+            Error::NotAMethod { name, class, location: _ } => {
+                format!("`{name}` is not a method of `{class}`")
+            },
+            Error::InvalidInstanceType { location: _ } => {
+                "Instances must be for a type applied to distinct generics, e.g. `Option a` or `(a, b)`".to_string()
+            },
+            Error::RecursiveTypeAlias { name, location: _ } => {
+                format!("Type alias `{name}` refers to itself, so it would never finish expanding")
+            },
+            Error::TypedHole { name, typ, candidates, location: _ } => {
+                let hole = name.as_ref().map_or("_".to_string(), |name| format!("?{name}"));
+                let bindings = TypeBindings::new();
+                let typ = typ.display(&bindings);
+                if candidates.is_empty() {
+                    format!("Hole `{hole}` has type `{typ}`. No names in scope have this type")
+                } else {
+                    let candidates: Vec<_> = candidates.iter().map(|name| format!("`{name}`")).collect();
+                    format!("Hole `{hole}` has type `{typ}`. Names in scope with this type: {}", candidates.join(", "))
+                }
+            },
+        }
+    }
+
+    /// The primary location of this error
+    pub fn location(&self) -> &Location {
+        match self {
+            Error::ParserExpected { location, .. }
+            | Error::UnknownImportFile { location, .. }
+            | Error::NameNotInScope { location, .. }
+            | Error::ExpectedType { location, .. }
+            | Error::RecursiveType { location, .. }
+            | Error::InvalidName { location, .. }
+            | Error::RenameConflict { location, .. }
+            | Error::RenameShadowed { location, .. }
+            | Error::RecursiveUseNeedsAnnotation { location, .. }
+            | Error::NonExhaustiveMatch { location, .. }
+            | Error::RedundantMatchArm { location }
+            | Error::ConstructorPatternArity { location, .. }
+            | Error::TypeArgumentCount { location, .. }
+            | Error::UnterminatedString { location }
+            | Error::InvalidEscapeSequence { location, .. }
+            | Error::NoSuchField { location, .. }
+            | Error::RefutableParameter { location, .. }
+            | Error::NoInstance { location, .. }
+            | Error::AmbiguousType { location, .. }
+            | Error::MissingMethod { location, .. }
+            | Error::NotAMethod { location, .. }
+            | Error::InvalidInstanceType { location }
+            | Error::RecursiveTypeAlias { location, .. }
+            | Error::TypedHole { location, .. }
+            | Error::BuiltinInstance { location, .. } => location,
+            Error::NameAlreadyInScope { second_location, .. }
+            | Error::DuplicateInstance { second_location, .. }
+            | Error::DuplicateField { second_location, .. }
+            | Error::ImportedNameAlreadyInScope { second_location, .. } => second_location,
+            Error::IfBranchMismatch { else_location, .. } => else_location,
         }
     }
+
+    /// Each location this error points to, starting with the primary location
+    pub fn labels(&self) -> Vec<Label<'_>> {
+        let primary = |location, message| Label { location, message, primary: true };
+        let secondary = |location, message| Label { location, message, primary: false };
+
+        match self {
+            Error::NameAlreadyInScope { first_location, second_location, .. } => {
+                vec![primary(second_location, "redefined here"), secondary(first_location, "first defined here")]
+            },
+            Error::DuplicateField { first_location, second_location, .. } => {
+                vec![primary(second_location, "given again here"), secondary(first_location, "first given here")]
+            },
+            Error::ImportedNameAlreadyInScope { first_location, second_location, .. } => {
+                vec![primary(second_location, "imported here"), secondary(first_location, "first defined here")]
+            },
+            Error::DuplicateInstance { first_location, second_location, .. } => {
+                vec![primary(second_location, "conflicting instance"), secondary(first_location, "first instance")]
+            },
+            Error::RenameConflict { location, existing_location, .. } => {
+                vec![primary(location, "renaming this"), secondary(existing_location, "already defined here")]
+            },
+            Error::IfBranchMismatch { then_location, else_location, .. } => {
+                vec![primary(else_location, "`else` branch"), secondary(then_location, "`then` branch")]
+            },
+            _ => vec![primary(self.location(), "")],
+        }
+    }
+}
+
+/// True if any of the given diagnostics is an error rather than a warning
+pub fn contains_errors(errors: &[Error]) -> bool {
+    errors.iter().any(|error| error.severity() == Severity::Error)
+}
+
+/// `plural(1, "field")` is "1 field" while `plural(2, "field")` is "2 fields"
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 { format!("1 {noun}") } else { format!("{count} {noun}s") }
 }
 
 impl std::fmt::Display for LocationData {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}:{}", self.file_name, self.start.line_number)
+        write!(f, "{}:{}:{}", self.file_name, self.start.line_number, self.start.column_number)
     }
 }
 impl std::fmt::Debug for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message())
+        write!(f, "{self}")
     }
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message())
+        write!(f, "{}: {}", self.location(), self.message())
     }
 }
@@ -1,4 +1,4 @@
-use std::{collections::BTreeSet, sync::Arc};
+use std::{collections::BTreeSet, path::Path, sync::Arc};
 
 use crate::{errors::{Error, Errors, Location}, incremental::{set_source_file, Compiler, GetImports}, read_file};
 
@@ -10,7 +10,7 @@ use crate::{errors::{Error, Errors, Location}, incremental::{set_source_file, Co
 /// may be able to test if a file has changed there, but inc-complete doesn't support this (yet) as
 /// of version 0.5.0.
 ///
-/// In `collect_all_changed_files`, we start by parsing the first INPUT_FILE which imports all
+/// In `collect_all_changed_files`, we start by parsing the program's input file which imports all
 /// other files. We collect each import and for each import we read the new file, set the source
 /// file input (which requires an exclusive &mut reference), and spawn a thread to parse that file
 /// and collect the imports. Spawning multiple threads here is advantageous when a file imports
@@ -18,7 +18,16 @@ use crate::{errors::{Error, Errors, Location}, incremental::{set_source_file, Co
 /// for this could be more efficient though. For example, the parser could accept the shared `queue`
 /// of files to parse as an argument, and push to this queue immediately when it finds an import.
 pub fn collect_all_changed_files(start_file: Arc<String>, compiler: &mut Compiler) -> (BTreeSet<Arc<String>>, Errors) {
-    let mut finder = Finder::new();
+    collect_all_changed_files_with(start_file, &|file| read_file(Path::new(file.as_str())), compiler)
+}
+
+/// Same as `collect_all_changed_files` but reads the source of each file with the given function
+/// instead of from disk. The language server uses this to prefer the contents of files that are
+/// open in the editor, which may not have been saved yet.
+pub fn collect_all_changed_files_with(
+    start_file: Arc<String>, read_source: &SourceReader, compiler: &mut Compiler,
+) -> (BTreeSet<Arc<String>>, Errors) {
+    let mut finder = Finder::new(read_source);
     let mut remaining_files = BTreeSet::new();
     remaining_files.insert(start_file);
 
@@ -31,17 +40,21 @@ pub fn collect_all_changed_files(start_file: Arc<String>, compiler: &mut Compile
 
 type FileName = Arc<String>;
 
-struct Finder {
+/// Retrieves the text of the given file, or an error message if it couldn't be read
+pub type SourceReader<'a> = dyn Fn(&FileName) -> Result<String, String> + 'a;
+
+struct Finder<'a> {
     queue: scc::Queue<(FileName, Location)>,
     done: BTreeSet<FileName>,
     thread_pool: rayon::ThreadPool,
     errors: Errors,
+    read_source: &'a SourceReader<'a>,
 }
 
-impl Finder {
-    fn new() -> Self {
+impl<'a> Finder<'a> {
+    fn new(read_source: &'a SourceReader<'a>) -> Self {
         let thread_pool = rayon::ThreadPoolBuilder::new().build().unwrap();
-        Self { thread_pool, queue: Default::default(), done: Default::default(), errors: Vec::new() }
+        Self { thread_pool, queue: Default::default(), done: Default::default(), errors: Vec::new(), read_source }
     }
 
     /// Search through all files in the queue, parse them, and wait until they finish.
@@ -77,7 +90,7 @@ impl Finder {
                 continue;
             }
 
-            let text = read_file(&file).unwrap_or_else(|_| {
+            let text = (self.read_source)(&file).unwrap_or_else(|_| {
                 self.errors.push(Error::UnknownImportFile { file_name: file.clone(), location });
 
                 // Treat file as an empty string. This will probably just lead to more errors but does
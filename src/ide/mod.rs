@@ -0,0 +1,597 @@
+//! Queries answering questions about a particular position in a file. These are the building
+//! blocks for editor features like hovering over an expression to see its type, or jumping to
+//! the definition of a name.
+//!
+//! Beyond single positions, `find_references_impl` and `rename_impl` work across every file of a
+//! program, which is identified by the root file it is compiled from.
+//!
+//! Positions are given as byte offsets into a file. Like every other query, the results are cached:
+//! asking about the same position twice without editing the file only computes the answer once.
+//! Since these depend on `Parse` and the per-statement `Resolve` and `TypeCheck` queries, editing
+//! a file only invalidates the answers for positions whose statements actually changed.
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    builtins::Builtin,
+    errors::{Error, Location, LocationData, Position},
+    incremental::{
+        self, parse, CompilerHandle, DefinitionAtPosition, FindReferences, GetImports, GetStatement, Parse, Rename,
+        Resolve, SymbolAtPosition, TypeAtPosition, TypeCheck, VisibleDefinitions,
+    },
+    lexer,
+    name_resolution::Origin,
+    parser::{
+        ast::{Expression, Pattern, TopLevelStatement},
+        ids::{ExprId, TopLevelId},
+    },
+    type_inference::types::Type,
+};
+
+/// Anything a name may refer to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Symbol {
+    /// A top-level definition, a type defined by a `type` statement, or a class
+    TopLevel(TopLevelId),
+    /// The parameter of a lambda. Since `ExprId`s are only unique within a top-level statement,
+    /// we also need the id of the statement the lambda is in. Variables bound by patterns are
+    /// parameters as well.
+    Parameter(TopLevelId, ExprId),
+    /// The name defined by a `let` binding, identified by the id of that name
+    LetBinding(TopLevelId, ExprId),
+    /// A constructor of the type defined by the given `type` statement
+    Constructor(TopLevelId, Arc<String>),
+    /// A method of the class defined by the given `class` statement
+    Method(TopLevelId, Arc<String>),
+}
+
+impl Symbol {
+    /// True if a variable in `statement` with the given origin refers to this symbol
+    fn is_origin(&self, origin: &Origin, statement: &TopLevelId) -> bool {
+        match (self, origin) {
+            (
+                Symbol::TopLevel(id),
+                Origin::TopLevelDefinition(origin_id)
+                | Origin::TypeDefinition(origin_id)
+                | Origin::TypeAlias(origin_id)
+                | Origin::Class(origin_id),
+            ) => id == origin_id,
+            (Symbol::Constructor(id, name), Origin::Constructor(origin_id, origin_name))
+            | (Symbol::Method(id, name), Origin::Method(origin_id, origin_name)) => {
+                id == origin_id && name == origin_name
+            },
+            (Symbol::Parameter(item, id), Origin::Parameter(origin_id))
+            | (Symbol::LetBinding(item, id), Origin::LetBinding(origin_id)) => item == statement && id == origin_id,
+            _ => false,
+        }
+    }
+
+    fn from_origin(origin: &Origin, statement: &TopLevelId) -> Symbol {
+        match origin {
+            Origin::TopLevelDefinition(id) | Origin::TypeDefinition(id) | Origin::TypeAlias(id) | Origin::Class(id) => {
+                Symbol::TopLevel(id.clone())
+            },
+            Origin::Constructor(id, name) => Symbol::Constructor(id.clone(), name.clone()),
+            Origin::Method(id, name) => Symbol::Method(id.clone(), name.clone()),
+            Origin::Parameter(id) => Symbol::Parameter(statement.clone(), *id),
+            Origin::LetBinding(id) => Symbol::LetBinding(statement.clone(), *id),
+        }
+    }
+
+    /// The location of the name of this symbol where it is defined
+    fn location(&self, compiler: &CompilerHandle) -> Location {
+        match self {
+            Symbol::TopLevel(id) => match GetStatement(id.clone()).get(compiler) {
+                TopLevelStatement::Definition(definition) => definition.name.id.location(id, compiler),
+                TopLevelStatement::TypeDefinition(definition) => definition.name.id.location(id, compiler),
+                TopLevelStatement::TypeAlias(alias) => alias.name.id.location(id, compiler),
+                TopLevelStatement::Class(class) => class.name.id.location(id, compiler),
+                _ => id.location(compiler),
+            },
+            Symbol::Parameter(item, id) | Symbol::LetBinding(item, id) => id.location(item, compiler),
+            Symbol::Constructor(id, name) => match GetStatement(id.clone()).get(compiler) {
+                TopLevelStatement::TypeDefinition(definition) => {
+                    let constructor = definition.constructors.iter().find(|constructor| constructor.name.name == *name);
+                    let name = constructor.map_or(&definition.name, |constructor| &constructor.name);
+                    name.id.location(id, compiler)
+                },
+                _ => id.location(compiler),
+            },
+            Symbol::Method(id, name) => match GetStatement(id.clone()).get(compiler) {
+                TopLevelStatement::Class(class) => {
+                    let method = class.methods.iter().find(|(method, _)| method.name == *name);
+                    method.map_or(&class.name, |(method, _)| method).id.location(id, compiler)
+                },
+                _ => id.location(compiler),
+            },
+        }
+    }
+
+    /// The top-level statement defining this symbol, if it may be used in other statements
+    fn global_definition(&self) -> Option<&TopLevelId> {
+        match self {
+            Symbol::TopLevel(id) | Symbol::Constructor(id, _) | Symbol::Method(id, _) => Some(id),
+            Symbol::Parameter(..) | Symbol::LetBinding(..) => None,
+        }
+    }
+
+    /// True if this symbol is a type or type alias rather than a value
+    fn is_type(&self, compiler: &CompilerHandle) -> bool {
+        matches!(self, Symbol::TopLevel(id) if matches!(GetStatement(id.clone()).get(compiler),
+            TopLevelStatement::TypeDefinition(_) | TopLevelStatement::TypeAlias(_)))
+    }
+
+    /// True if this symbol is a class rather than a value
+    fn is_class(&self, compiler: &CompilerHandle) -> bool {
+        matches!(self, Symbol::TopLevel(id)
+            if matches!(GetStatement(id.clone()).get(compiler), TopLevelStatement::Class(_)))
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Symbol::TopLevel(id) => write!(f, "{id}"),
+            Symbol::Parameter(item, id) => write!(f, "parameter {id} of {item}"),
+            Symbol::LetBinding(item, id) => write!(f, "let binding {id} of {item}"),
+            Symbol::Constructor(id, name) => write!(f, "constructor {name} of {id}"),
+            Symbol::Method(id, name) => write!(f, "method {name} of {id}"),
+        }
+    }
+}
+
+/// A single use of a symbol
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reference {
+    /// The top-level statement the use is in
+    pub statement: TopLevelId,
+    pub variable: ExprId,
+    pub location: Location,
+}
+
+/// Replace the text at `location` with `new_text`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub location: Location,
+    pub new_text: Arc<String>,
+}
+
+/// Find the innermost expression containing the given position along with its type.
+/// Hovering over the name of a definition gives the (possibly generic) type of that definition.
+///
+/// Type variables which were never solved are left as-is in the resulting type.
+pub fn type_at_position_impl(context: &TypeAtPosition, compiler: &CompilerHandle) -> Option<(Location, Type)> {
+    incremental::enter_query();
+    incremental::println(format!("Finding type at {}:{}", context.file_name, context.byte_index));
+
+    let result = type_at_position(context, compiler);
+    incremental::exit_query();
+    result
+}
+
+fn type_at_position(context: &TypeAtPosition, compiler: &CompilerHandle) -> Option<(Location, Type)> {
+    let (item, locations) = statement_at_position(&context.file_name, context.byte_index, compiler)?;
+    let statement = GetStatement(item.clone()).get(compiler);
+    let result = TypeCheck(item).get(compiler);
+    let mut types = result.expr_types;
+
+    // The name of a definition isn't an expression so it isn't given a type while type checking
+    if let TopLevelStatement::Definition(definition) = statement {
+        types.insert(definition.name.id, result.typ.typ);
+    }
+
+    let expressions = locations.into_iter().filter(|(id, _)| types.contains_key(id));
+    let (id, location) = innermost_expression(expressions, context.byte_index)?;
+    Some((location, types.remove(&id).unwrap()))
+}
+
+/// Find the symbol whose name is at the given position. This may be either a variable
+/// referring to a symbol, or the name of a definition, type, constructor, lambda parameter,
+/// pattern variable, or let binding itself.
+pub fn symbol_at_position_impl(context: &SymbolAtPosition, compiler: &CompilerHandle) -> Option<Symbol> {
+    incremental::enter_query();
+    incremental::println(format!("Finding symbol at {}:{}", context.file_name, context.byte_index));
+
+    let result = symbol_at_position(context, compiler);
+    incremental::exit_query();
+    result
+}
+
+fn symbol_at_position(context: &SymbolAtPosition, compiler: &CompilerHandle) -> Option<Symbol> {
+    let (item, locations) = statement_at_position(&context.file_name, context.byte_index, compiler)?;
+    let origins = Resolve(item.clone()).get(compiler).origins;
+    let mut symbols: BTreeMap<_, _> =
+        origins.iter().map(|(id, origin)| (*id, Symbol::from_origin(origin, &item))).collect();
+
+    match GetStatement(item.clone()).get(compiler) {
+        TopLevelStatement::Import { .. } => (),
+        TopLevelStatement::Definition(definition) => {
+            symbols.insert(definition.name.id, Symbol::TopLevel(item.clone()));
+            collect_locals(&definition.body, &item, &mut symbols);
+        },
+        TopLevelStatement::Print(expression, _) => collect_locals(&expression, &item, &mut symbols),
+        TopLevelStatement::TypeDefinition(definition) => {
+            symbols.insert(definition.name.id, Symbol::TopLevel(item.clone()));
+            for constructor in definition.constructors.iter() {
+                symbols.insert(constructor.name.id, Symbol::Constructor(item.clone(), constructor.name.name.clone()));
+            }
+        },
+        TopLevelStatement::TypeAlias(alias) => {
+            symbols.insert(alias.name.id, Symbol::TopLevel(item.clone()));
+        },
+        TopLevelStatement::Class(class) => {
+            symbols.insert(class.name.id, Symbol::TopLevel(item.clone()));
+            for (method, _) in class.methods.iter() {
+                symbols.insert(method.id, Symbol::Method(item.clone(), method.name.clone()));
+            }
+        },
+        // The class and method names of an instance are linked to the class by name resolution
+        TopLevelStatement::Instance(instance) => {
+            for (_, body) in instance.methods.iter() {
+                collect_locals(body, &item, &mut symbols);
+            }
+        },
+    }
+
+    let names = locations.into_iter().filter(|(id, _)| symbols.contains_key(id));
+    let (id, _) = innermost_expression(names, context.byte_index)?;
+    symbols.remove(&id)
+}
+
+/// Find the location of the definition of the name at the given position. This is either a
+/// top-level definition (possibly in another file), the parameter of an enclosing lambda, or
+/// an enclosing let binding. Positions within an import statement lead to the start of the
+/// imported file, unless they're on one of the names it lists.
+pub fn definition_at_position_impl(context: &DefinitionAtPosition, compiler: &CompilerHandle) -> Option<Location> {
+    incremental::enter_query();
+    incremental::println(format!("Finding definition at {}:{}", context.file_name, context.byte_index));
+
+    let result = definition_at_position(context, compiler);
+    incremental::exit_query();
+    result
+}
+
+fn definition_at_position(context: &DefinitionAtPosition, compiler: &CompilerHandle) -> Option<Location> {
+    let (item, _) = statement_at_position(&context.file_name, context.byte_index, compiler)?;
+    let symbol = SymbolAtPosition { file_name: context.file_name.clone(), byte_index: context.byte_index };
+    let symbol = symbol.get(compiler);
+
+    match GetStatement(item).get(compiler) {
+        TopLevelStatement::Import { file_name, .. } if symbol.is_none() => Some(start_of_file(&file_name.name)),
+        _ => Some(symbol?.location(compiler)),
+    }
+}
+
+/// Find every use of a symbol within the program starting from `root_file`, not including its
+/// definition. Top-level definitions, types, and constructors may be used in any file which can
+/// see them through `VisibleDefinitions`, while parameters and let bindings may only be used
+/// within the statement defining them.
+pub fn find_references_impl(context: &FindReferences, compiler: &CompilerHandle) -> Vec<Reference> {
+    incremental::enter_query();
+    incremental::println(format!("Finding references to {} in {}", context.symbol, context.root_file));
+
+    let statements = match &context.symbol {
+        Symbol::Parameter(item, _) | Symbol::LetBinding(item, _) => vec![item.clone()],
+        Symbol::TopLevel(definition) | Symbol::Constructor(definition, _) | Symbol::Method(definition, _) => {
+            let files = files_which_can_see(definition, &context.root_file, compiler);
+            let statements = files.into_iter().flat_map(|file| parse(file, compiler).0.statements.clone());
+            statements.map(|statement| statement.id().clone()).collect()
+        },
+    };
+
+    let mut references = Vec::new();
+    for statement in statements {
+        for (variable, origin) in Resolve(statement.clone()).get(compiler).origins {
+            if context.symbol.is_origin(&origin, &statement) {
+                let location = variable.location(&statement, compiler);
+                references.push(Reference { statement: statement.clone(), variable, location });
+            }
+        }
+    }
+
+    incremental::exit_query();
+    references
+}
+
+/// Rename a symbol in the program starting from `root_file`, returning an edit for its definition
+/// and for each use of it. Renaming fails if the new name isn't a valid name - including if types
+/// and constructors aren't given a capitalized name, or parameters are - if it would conflict
+/// with another definition visible in any file which can see the symbol, or if any use of a name
+/// would refer to a different definition afterward - e.g. if a use of the symbol would be shadowed
+/// by a parameter which already has the new name.
+pub fn rename_impl(context: &Rename, compiler: &CompilerHandle) -> Result<Vec<TextEdit>, Error> {
+    incremental::enter_query();
+    incremental::println(format!("Renaming {} to {}", context.symbol, context.new_name));
+    let result = rename(context, compiler);
+    incremental::exit_query();
+    result
+}
+
+fn rename(context: &Rename, compiler: &CompilerHandle) -> Result<Vec<TextEdit>, Error> {
+    let name = context.new_name.clone();
+    let location = context.symbol.location(compiler);
+
+    let is_type = context.symbol.is_type(compiler);
+    let is_class = context.symbol.is_class(compiler);
+    let must_be_capitalized = match &context.symbol {
+        Symbol::Constructor(..) => Some(true),
+        Symbol::Parameter(..) => Some(false),
+        Symbol::TopLevel(_) if is_type || is_class => Some(true),
+        Symbol::TopLevel(_) | Symbol::LetBinding(..) | Symbol::Method(..) => None,
+    };
+
+    let wrong_case = must_be_capitalized.is_some_and(|capitalized| capitalized != lexer::is_capitalized(&name));
+    if !lexer::is_valid_name(&name) || wrong_case {
+        return Err(Error::InvalidName { name, location });
+    }
+
+    if let Some(definition) = context.symbol.global_definition() {
+        for file in files_which_can_see(definition, &context.root_file, compiler) {
+            let (visible, _) = VisibleDefinitions { file_name: file }.get(compiler);
+
+            // Types, classes, and values are in separate namespaces so only names in the same one conflict
+            let existing = if is_type {
+                visible.types.get(&name).map(|id| Symbol::TopLevel(id.clone()))
+            } else if is_class {
+                visible.classes.get(&name).map(|id| Symbol::TopLevel(id.clone()))
+            } else {
+                visible.values.get(&name).map(|origin| Symbol::from_origin(origin, definition))
+            };
+
+            match existing {
+                Some(existing) if existing != context.symbol => {
+                    let existing_location = existing.location(compiler);
+                    return Err(Error::RenameConflict { name, location, existing_location });
+                },
+                _ => (),
+            }
+        }
+    }
+
+    let references =
+        FindReferences { root_file: context.root_file.clone(), symbol: context.symbol.clone() }.get(compiler);
+
+    let mut statements: BTreeSet<_> = references.iter().map(|reference| reference.statement.clone()).collect();
+    if let Symbol::Parameter(item, _) | Symbol::LetBinding(item, _) = &context.symbol {
+        statements.insert(item.clone());
+    }
+
+    for item in statements {
+        let bodies = match GetStatement(item.clone()).get(compiler) {
+            TopLevelStatement::Import { .. }
+            | TopLevelStatement::TypeDefinition(_)
+            | TopLevelStatement::TypeAlias(_)
+            | TopLevelStatement::Class(_) => continue,
+            TopLevelStatement::Definition(definition) => vec![definition.body],
+            TopLevelStatement::Print(expression, _) => vec![expression],
+            TopLevelStatement::Instance(instance) => instance.methods.into_iter().map(|(_, body)| body).collect(),
+        };
+
+        let origins = Resolve(item.clone()).get(compiler).origins;
+        for body in bodies {
+            let mut checker = ShadowingChecker::new(&item, &origins, &context.symbol, &name);
+            if let Err(variable) = checker.check_expr(&body) {
+                let location = variable.location(&item, compiler);
+                return Err(Error::RenameShadowed { name, location });
+            }
+        }
+    }
+
+    let locations = std::iter::once(location).chain(references.into_iter().map(|reference| reference.location));
+    Ok(locations.map(|location| TextEdit { location, new_text: name.clone() }).collect())
+}
+
+/// Checks whether each variable in a statement still refers to the same definition after a
+/// symbol is renamed. Since global definitions are checked for conflicts separately, this only
+/// needs to track which parameters, pattern variables, and let bindings are in scope.
+struct ShadowingChecker<'a> {
+    item: &'a TopLevelId,
+    origins: &'a BTreeMap<ExprId, Origin>,
+    symbol: &'a Symbol,
+    new_name: &'a Arc<String>,
+
+    /// Each parameter, pattern variable, and let binding in scope by its name after renaming.
+    /// Later ones shadow earlier ones.
+    locals_in_scope: Vec<(&'a Arc<String>, Origin)>,
+}
+
+impl<'a> ShadowingChecker<'a> {
+    fn new(
+        item: &'a TopLevelId, origins: &'a BTreeMap<ExprId, Origin>, symbol: &'a Symbol, new_name: &'a Arc<String>,
+    ) -> Self {
+        Self { item, origins, symbol, new_name, locals_in_scope: Vec::new() }
+    }
+
+    /// Returns the id of the first variable found which would refer to something else after renaming
+    fn check_expr(&mut self, expression: &'a Expression) -> Result<(), ExprId> {
+        match expression {
+            Expression::IntegerLiteral(..)
+            | Expression::BoolLiteral(..)
+            | Expression::StringLiteral(..)
+            | Expression::Hole { .. } => Ok(()),
+            Expression::Variable(identifier) => {
+                // Built-ins and names which failed to resolve have no origin. A built-in would be
+                // shadowed by a local or the renamed definition if either is given its name.
+                let Some(origin) = self.origins.get(&identifier.id) else {
+                    let is_builtin = Builtin::from_name(&identifier.name).is_some();
+                    let local_in_scope = self.locals_in_scope.iter().any(|(local, _)| **local == identifier.name);
+                    let renamed_global = self.symbol.global_definition().is_some() && *self.new_name == identifier.name;
+                    return if is_builtin && (local_in_scope || renamed_global) { Err(identifier.id) } else { Ok(()) };
+                };
+                let name = if self.symbol.is_origin(origin, self.item) { self.new_name } else { &identifier.name };
+
+                let local = self.locals_in_scope.iter().rev().find(|(local, _)| *local == name);
+                let new_origin = local.map_or(origin.clone(), |(_, origin)| origin.clone());
+                if new_origin == *origin {
+                    Ok(())
+                } else {
+                    Err(identifier.id)
+                }
+            },
+            Expression::FunctionCall { function, argument, id: _ } => {
+                self.check_expr(function)?;
+                self.check_expr(argument)
+            },
+            Expression::Lambda { parameter, body, id: _ } => self.check_in_pattern_scope(parameter, body),
+            Expression::If { condition, then_branch, else_branch, id: _ } => {
+                self.check_expr(condition)?;
+                self.check_expr(then_branch)?;
+                self.check_expr(else_branch)
+            },
+            Expression::Let { name: let_name, value, body, id: _ } => {
+                self.check_expr(value)?;
+
+                let symbol = Symbol::LetBinding(self.item.clone(), let_name.id);
+                let name = if symbol == *self.symbol { self.new_name } else { &let_name.name };
+
+                self.locals_in_scope.push((name, Origin::LetBinding(let_name.id)));
+                let result = self.check_expr(body);
+                self.locals_in_scope.pop();
+                result
+            },
+            Expression::Match { scrutinee, arms, id: _ } => {
+                self.check_expr(scrutinee)?;
+
+                for arm in arms {
+                    self.check_in_pattern_scope(&arm.pattern, &arm.body)?;
+                }
+                Ok(())
+            },
+            Expression::Tuple { elements, id: _ } | Expression::List { elements, id: _ } => {
+                elements.iter().try_for_each(|element| self.check_expr(element))
+            },
+            Expression::Record { fields, id: _ } => fields.iter().try_for_each(|(_, value)| self.check_expr(value)),
+            Expression::FieldAccess { record, .. } => self.check_expr(record),
+        }
+    }
+
+    /// Check `body` with each variable in `pattern` in scope
+    fn check_in_pattern_scope(&mut self, pattern: &'a Pattern, body: &'a Expression) -> Result<(), ExprId> {
+        let variables = pattern.variables();
+        for variable in variables.iter() {
+            let symbol = Symbol::Parameter(self.item.clone(), variable.id);
+            let name = if symbol == *self.symbol { self.new_name } else { &variable.name };
+            self.locals_in_scope.push((name, Origin::Parameter(variable.id)));
+        }
+
+        let result = self.check_expr(body);
+        self.locals_in_scope.truncate(self.locals_in_scope.len() - variables.len());
+        result
+    }
+}
+
+/// Collect the symbol of each lambda parameter, pattern variable, and let binding within an expression
+/// by the id of its name
+fn collect_locals(expression: &Expression, item: &TopLevelId, symbols: &mut BTreeMap<ExprId, Symbol>) {
+    match expression {
+        Expression::IntegerLiteral(..)
+        | Expression::BoolLiteral(..)
+        | Expression::StringLiteral(..)
+        | Expression::Hole { .. }
+        | Expression::Variable(_) => (),
+        Expression::FunctionCall { function, argument, id: _ } => {
+            collect_locals(function, item, symbols);
+            collect_locals(argument, item, symbols);
+        },
+        Expression::Lambda { parameter, body, id: _ } => {
+            for variable in parameter.variables() {
+                symbols.insert(variable.id, Symbol::Parameter(item.clone(), variable.id));
+            }
+            collect_locals(body, item, symbols);
+        },
+        Expression::If { condition, then_branch, else_branch, id: _ } => {
+            collect_locals(condition, item, symbols);
+            collect_locals(then_branch, item, symbols);
+            collect_locals(else_branch, item, symbols);
+        },
+        Expression::Let { name, value, body, id: _ } => {
+            symbols.insert(name.id, Symbol::LetBinding(item.clone(), name.id));
+            collect_locals(value, item, symbols);
+            collect_locals(body, item, symbols);
+        },
+        Expression::Match { scrutinee, arms, id: _ } => {
+            collect_locals(scrutinee, item, symbols);
+            for arm in arms {
+                for variable in arm.pattern.variables() {
+                    symbols.insert(variable.id, Symbol::Parameter(item.clone(), variable.id));
+                }
+                collect_locals(&arm.body, item, symbols);
+            }
+        },
+        Expression::Tuple { elements, id: _ } | Expression::List { elements, id: _ } => {
+            for element in elements {
+                collect_locals(element, item, symbols);
+            }
+        },
+        Expression::Record { fields, id: _ } => {
+            for (_, value) in fields {
+                collect_locals(value, item, symbols);
+            }
+        },
+        Expression::FieldAccess { record, .. } => collect_locals(record, item, symbols),
+    }
+}
+
+/// Each file in the program starting from `root_file` in which the given definition is visible
+fn files_which_can_see(
+    definition: &TopLevelId, root_file: &Arc<String>, compiler: &CompilerHandle,
+) -> Vec<Arc<String>> {
+    let mut files = BTreeSet::new();
+    let mut remaining = vec![root_file.clone()];
+
+    while let Some(file) = remaining.pop() {
+        if files.insert(file.clone()) {
+            remaining.extend(GetImports { file_name: file }.get(compiler).into_iter().map(|(import, _)| import));
+        }
+    }
+
+    files
+        .into_iter()
+        .filter(|file| {
+            let (visible, _) = VisibleDefinitions { file_name: file.clone() }.get(compiler);
+            let values = visible.values.values().filter_map(Origin::top_level_id);
+            values.chain(visible.types.values()).chain(visible.classes.values()).any(|id| id == definition)
+        })
+        .collect()
+}
+
+/// Returns the id of the top-level statement containing the given position,
+/// along with the location of each expression within it.
+fn statement_at_position(
+    file_name: &Arc<String>, byte_index: usize, compiler: &CompilerHandle,
+) -> Option<(TopLevelId, Vec<(ExprId, Location)>)> {
+    let result = Parse { file_name: file_name.clone() }.get(compiler);
+
+    result.top_level_data.iter().find(|(_, data)| contains(&data.location, byte_index)).map(|(id, data)| {
+        let locations = data.expr_locations.iter().map(|(id, location)| (*id, location.clone())).collect();
+        (id.clone(), locations)
+    })
+}
+
+/// Returns the expression with the smallest span containing the given position.
+///
+/// A position just past the end of an expression also counts as being within it since editors
+/// usually place the cursor after the name being asked about, but expressions strictly containing
+/// the position are preferred. When several expressions share the same span - as the nested lambdas
+/// of `fn a b -> ...` do - the outermost one, with the greatest id, is chosen.
+fn innermost_expression(
+    expressions: impl Iterator<Item = (ExprId, Location)>, byte_index: usize,
+) -> Option<(ExprId, Location)> {
+    expressions.filter(|(_, location)| contains(location, byte_index)).min_by_key(|(id, location)| {
+        let length = location.end.byte_index - location.start.byte_index;
+        (location.end.byte_index == byte_index, length, Reverse(*id))
+    })
+}
+
+fn contains(location: &Location, byte_index: usize) -> bool {
+    location.start.byte_index <= byte_index && byte_index <= location.end.byte_index
+}
+
+fn start_of_file(file_name: &Arc<String>) -> Location {
+    Arc::new(LocationData { file_name: file_name.clone(), start: Position::start(), end: Position::start() })
+}
@@ -1,12 +1,19 @@
-use std::{cell::Cell, collections::BTreeMap, sync::Arc};
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use inc_complete::{define_input, define_intermediate, impl_storage, storage::HashMapStorage};
+use inc_complete::{define_input, define_intermediate, impl_storage, storage::HashMapStorage, DbGet};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    backend, definition_collection, errors::{Errors, Location}, name_resolution::{self, ResolutionResult}, parser::{
+    backend::{self, Target}, definition_collection, errors::{Error, Errors, Location}, ide::{self, Reference, Symbol, TextEdit}, name_resolution::{self, Origin, ResolutionResult}, pattern_matching, parser::{
         self, ast::{Ast, TopLevelStatement}, ids::TopLevelId, ParserResult
-    }, type_inference::{self, types::TopLevelDefinitionType, TypeCheckResult}
+    }, type_inference::{self, dependencies::{self, DependencyGraph}, types::{TopLevelDefinitionType, Type, TypeConstructor}, TypeCheckResult}
 };
 
 /// A wrapper over inc-complete's database with our specific storage type to hold
@@ -40,7 +47,23 @@ pub struct Storage {
     top_level_statement: HashMapStorage<GetStatement>,
     get_types: HashMapStorage<GetType>,
     type_checks: HashMapStorage<TypeCheck>,
+    definition_dependencies: HashMapStorage<DefinitionDependencies>,
+    recursive_groups_in_file: HashMapStorage<RecursiveGroups>,
+    recursive_groups: HashMapStorage<RecursiveGroup>,
+    type_check_groups: HashMapStorage<TypeCheckGroup>,
     compiled_files: HashMapStorage<CompileFile>,
+    checked_files: HashMapStorage<CheckFile>,
+    types_at_position: HashMapStorage<TypeAtPosition>,
+    definitions_at_position: HashMapStorage<DefinitionAtPosition>,
+    symbols_at_position: HashMapStorage<SymbolAtPosition>,
+    references: HashMapStorage<FindReferences>,
+    renames: HashMapStorage<Rename>,
+    match_checks: HashMapStorage<CheckMatches>,
+    exported_instances: HashMapStorage<ExportedInstances>,
+    visible_instances: HashMapStorage<VisibleInstances>,
+    type_aliases: HashMapStorage<ExpandTypeAlias>,
+    hole_checks: HashMapStorage<CheckHoles>,
+    compiled_statements: HashMapStorage<CompileStatement>,
 }
 
 impl_storage!(Storage,
@@ -53,12 +76,40 @@ impl_storage!(Storage,
     top_level_statement: GetStatement,
     get_types: GetType,
     type_checks: TypeCheck,
+    definition_dependencies: DefinitionDependencies,
+    recursive_groups_in_file: RecursiveGroups,
+    recursive_groups: RecursiveGroup,
+    type_check_groups: TypeCheckGroup,
     compiled_files: CompileFile,
+    checked_files: CheckFile,
+    types_at_position: TypeAtPosition,
+    definitions_at_position: DefinitionAtPosition,
+    symbols_at_position: SymbolAtPosition,
+    references: FindReferences,
+    renames: Rename,
+    match_checks: CheckMatches,
+    exported_instances: ExportedInstances,
+    visible_instances: VisibleInstances,
+    type_aliases: ExpandTypeAlias,
+    hole_checks: CheckHoles,
+    compiled_statements: CompileStatement,
 );
 
 std::thread_local! {
     // This is a helper to show us how many queries deep we are for our print outs
-    static QUERY_NESTING: Cell<usize> = Cell::new(0);
+    static QUERY_NESTING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// When set, `println` doesn't print anything. This is used when stdout is reserved for
+/// machine-readable output.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
 }
 
 pub fn enter_query() {
@@ -74,6 +125,10 @@ pub fn exit_query() {
 }
 
 pub fn println(msg: String) {
+    if is_quiet() {
+        return;
+    }
+
     let level = QUERY_NESTING.with(|cell| cell.get());
     let spaces = "  ".repeat(level);
 
@@ -101,7 +156,9 @@ pub fn set_source_file(file_name: Arc<String>, text: String, db: &mut Compiler)
     SourceFile { file_name }.set(db, text);
 }
 
-pub fn get_source_file<'c>(file_name: Arc<String>, db: &'c CompilerHandle) -> String {
+/// Retrieve the text of a source file. This can be called either within a query or
+/// from outside of one, e.g. to display a snippet of the source file for an error.
+pub fn get_source_file(file_name: Arc<String>, db: &impl DbGet<SourceFile>) -> String {
     SourceFile { file_name }.get(db)
 }
 
@@ -122,7 +179,7 @@ pub struct Parse {
 define_intermediate!(1, Parse -> ParserResult, Storage, parser::parse_impl);
 
 /// Parse the program (unless we have already done so), ignoring some extra metadata in the full ParserResult
-pub fn parse<'c>(file_name: Arc<String>, db: &'c CompilerHandle) -> (Ast, Errors) {
+pub fn parse(file_name: Arc<String>, db: &CompilerHandle) -> (Ast, Errors) {
     let result = Parse { file_name }.get(db);
     (result.ast, result.errors)
 }
@@ -140,7 +197,18 @@ define_intermediate!(2, VisibleDefinitions -> (Definitions, Errors), Storage, de
 /// collecting these can error, we need a stable iteration order, otherwise the order
 /// we issue errors would be nondeterministic. This is why we use a BTreeMap over a
 /// HashMap, since hashmap iteration in rust has a nondeterministic ordering.
-pub type Definitions = BTreeMap<Arc<String>, TopLevelId>;
+///
+/// Types, classes, and values are in separate namespaces, so a type may share its name with one
+/// of its constructors, as in `type Box a = Box a`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Definitions {
+    /// Names usable in expressions: top-level definitions and constructors
+    pub values: BTreeMap<Arc<String>, Origin>,
+    /// Names usable in types, each mapping to the `type` statement defining it
+    pub types: BTreeMap<Arc<String>, TopLevelId>,
+    /// Names usable in constraints and instances, each mapping to the `class` statement defining it
+    pub classes: BTreeMap<Arc<String>, TopLevelId>,
+}
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 /// Collect all exported definitions in a file. This separate step is important because we don't
@@ -183,8 +251,8 @@ define_intermediate!(5, Resolve -> ResolutionResult, Storage, name_resolution::r
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 /// To go from queries which resolve entire files like `Parse` to queries that resolve only a
-/// single statement like `Resolve` we need a way to split a large `Ast` result into smaller items
-/// - in this case individual statements. This being cached means we check if the resulting
+/// single statement like `Resolve` we need a way to split a large `Ast` result into smaller items,
+/// in this case individual statements. This being cached means we check if the resulting
 /// `TopLevelStatement` has changed, and if not, we don't need to re-run any computations that
 /// depend on that statement.
 #[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
@@ -225,8 +293,137 @@ pub struct TypeCheck(pub TopLevelId);
 define_intermediate!(8, TypeCheck -> TypeCheckResult, Storage, type_inference::type_check_impl);
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
-/// Compile a single file to a string representing python source code of that file.
-/// This will also return any errors originating in that file.
+/// Collect which definitions the type of each definition in a file depends on. This only changes
+/// when a definition starts or stops referring to another, so editing the body of a definition
+/// otherwise won't cause any recursive groups to be recomputed.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefinitionDependencies { pub file_name: Arc<String> }
+define_intermediate!(16, DefinitionDependencies -> DependencyGraph, Storage, dependencies::definition_dependencies_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Find the group of mutually recursive definitions each definition in a file belongs to. These
+/// may span several files since files are allowed to import each other. Every group is found in a
+/// single pass over the dependency graph rather than searching it again for each definition.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecursiveGroups { pub file_name: Arc<String> }
+define_intermediate!(25, RecursiveGroups -> BTreeMap<TopLevelId, Vec<TopLevelId>>, Storage, dependencies::recursive_groups_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Find the group of mutually recursive definitions a definition belongs to. This only looks up
+/// the definition in `RecursiveGroups`, but being cached separately means editing a file only
+/// re-checks the definitions whose groups actually changed.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecursiveGroup(pub TopLevelId);
+define_intermediate!(17, RecursiveGroup -> Vec<TopLevelId>, Storage, dependencies::recursive_group_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Type check each definition in a recursive group together. The id given must be the first
+/// definition in its group so that the group is only checked once rather than once per
+/// definition in it. `TypeCheck` uses this for any group with more than one definition.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeCheckGroup(pub TopLevelId);
+define_intermediate!(18, TypeCheckGroup -> BTreeMap<TopLevelId, TypeCheckResult>, Storage, type_inference::type_check_group_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Check that each `match` in a top-level statement is exhaustive and has no redundant arms.
+/// This needs the types from `TypeCheck` but is kept separate from it so that editing a `match`
+/// without changing any types doesn't need to re-run anything depending on `TypeCheck`.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckMatches(pub TopLevelId);
+define_intermediate!(19, CheckMatches -> Errors, Storage, pattern_matching::check_matches_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Collect the instances defined in a file. Like `ExportedDefinitions`, this is separate from
+/// `VisibleInstances` so that files importing this one only depend on the instances in it.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedInstances { pub file_name: Arc<String> }
+define_intermediate!(20, ExportedInstances -> (Instances, Errors), Storage, definition_collection::exported_instances_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Collect the instances usable within a file: those defined in it and in the files it imports.
+/// Type inference uses these to find the instance of a class to use for a type.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VisibleInstances { pub file_name: Arc<String> }
+define_intermediate!(21, VisibleInstances -> (Instances, Errors), Storage, definition_collection::visible_instances_impl);
+
+/// Each instance by the `class` statement of its class and the outermost type constructor of its
+/// type. Instances can't overlap, so there is at most one instance of a class per type constructor.
+pub type Instances = BTreeMap<(TopLevelId, TypeConstructor), TopLevelId>;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Expand a type alias into the type it stands for, generic over the alias's parameters. Types
+/// written in the source use this to replace each alias they use, so editing an alias only causes
+/// its users to be rechecked if what it expands to changes. Also returns an error if the alias
+/// refers to itself.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpandTypeAlias(pub TopLevelId);
+define_intermediate!(22, ExpandTypeAlias -> (TopLevelDefinitionType, Errors), Storage, type_inference::expand_type_alias_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Report the type of each hole in a top-level statement along with the names in scope which
+/// would fit in it. Like `CheckMatches`, this only needs the results of `TypeCheck` so it is kept
+/// separate from it. Otherwise checking the type of each name in scope from within `TypeCheck`
+/// could make a definition's type depend on itself.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckHoles(pub TopLevelId);
+define_intermediate!(23, CheckHoles -> Errors, Storage, type_inference::holes::check_holes_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Compile a single file to a string representing the source code of that file in the target
+/// language. This will also return any errors originating in that file. The target is part of the
+/// key so that the code cached for each target is kept when switching between them.
 #[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
-pub struct CompileFile { pub file_name: Arc<String> }
+pub struct CompileFile { pub file_name: Arc<String>, pub target: Target }
 define_intermediate!(9, CompileFile -> (String, Errors), Storage, backend::compile_file_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Compile a single top-level statement to the target language. `CompileFile` joins the code for
+/// each statement in a file, so editing one definition only regenerates the code for it. Since
+/// codegen is one of the slower passes, this is where per-statement granularity pays off most.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompileStatement(pub TopLevelId, pub Target);
+define_intermediate!(24, CompileStatement -> String, Storage, backend::compile_statement_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Type check every statement in a single file, returning all errors originating in that file.
+/// This is what `CompileFile` uses to collect errors, but it is also useful on its own when we
+/// only want to check a program for errors without generating any code.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckFile { pub file_name: Arc<String> }
+define_intermediate!(10, CheckFile -> Errors, Storage, type_inference::check_file_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Find the innermost expression at a byte offset in a file along with its type. This is used
+/// by the language server to show the type of whatever is under the cursor. These queries are
+/// cheap enough that caching each position separately is not a concern, and doing so means
+/// repeatedly hovering over the same expression doesn't even need to re-query `TypeCheck`.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeAtPosition { pub file_name: Arc<String>, pub byte_index: usize }
+define_intermediate!(11, TypeAtPosition -> Option<(Location, Type)>, Storage, ide::type_at_position_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Find where the name at a byte offset in a file was defined. Used for jump-to-definition.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefinitionAtPosition { pub file_name: Arc<String>, pub byte_index: usize }
+define_intermediate!(12, DefinitionAtPosition -> Option<Location>, Storage, ide::definition_at_position_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Find the symbol whose name is at a byte offset in a file. This is the starting point for
+/// finding references to a symbol or renaming it.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolAtPosition { pub file_name: Arc<String>, pub byte_index: usize }
+define_intermediate!(13, SymbolAtPosition -> Option<Symbol>, Storage, ide::symbol_at_position_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Find each use of a symbol within the program starting from `root_file`. We need a root file
+/// since files don't know which other files import them - only which files they import.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FindReferences { pub root_file: Arc<String>, pub symbol: Symbol }
+define_intermediate!(14, FindReferences -> Vec<Reference>, Storage, ide::find_references_impl);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// Compute the edits needed to rename a symbol within the program starting from `root_file`,
+/// or an error if the symbol can't be renamed to the new name.
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rename { pub root_file: Arc<String>, pub symbol: Symbol, pub new_name: Arc<String> }
+define_intermediate!(15, Rename -> Result<Vec<TextEdit>, Error>, Storage, ide::rename_impl);
@@ -0,0 +1,541 @@
+//! A tree-walking interpreter which runs a program directly from its Ast, without compiling it to
+//! python first. This lets us see the output of a program without any external toolchain installed.
+//!
+//! Like the later passes of the compiler, the interpreter doesn't look up names itself. Instead it
+//! uses the `Origin` of each variable from `Resolve` to find what each variable refers to, even if
+//! that is a definition in another file. Top-level definitions are evaluated the first time they're
+//! used and cached afterward, so the order definitions are written in doesn't matter.
+//!
+//! Instances are passed to definitions with constraints as dictionaries: records mapping each method
+//! name to its value. A definition with constraints is evaluated again for each use since the
+//! dictionaries it is given may differ each time. Operators are methods of the built-in classes, so
+//! they're looked up in a dictionary as well. The dictionary of a built-in instance maps the name of
+//! each operator of the class to the operator itself.
+//!
+//! The interpreter assumes the program is free of errors. Always check it for errors before
+//! running it!
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::{
+    builtins::{builtin_instance, Builtin},
+    errors::Location,
+    incremental::{Compiler, GetStatement, GetType, Parse, Resolve, TypeCheck},
+    lexer::escape,
+    name_resolution::Origin,
+    parser::{
+        ast::{Expression, Pattern, TopLevelStatement},
+        ids::{ExprId, TopLevelId},
+    },
+    type_inference::{ConstructorSignature, Evidence},
+};
+
+/// Run the program starting from the given file, writing the output of each `print` to `output`.
+/// Each file's `print`s are executed in order when the file is first imported, so the prints of
+/// imported files are executed before those of the file importing them.
+pub fn run_program(file_name: Arc<String>, compiler: &Compiler, output: impl Write) -> Result<(), String> {
+    let mut interpreter = Interpreter::new(compiler, output);
+    interpreter.run_file(file_name)
+}
+
+/// Evaluate a single definition or print statement, evaluating only the definitions it uses.
+/// Returns `None` for import statements and type definitions, which have no value. Unlike `run_program`, this never
+/// executes any other `print`s.
+///
+/// Definitions with constraints can't be evaluated on their own since we don't know which instances they'd use.
+pub fn evaluate(item: &TopLevelId, compiler: &Compiler) -> Result<Option<Value>, String> {
+    let mut interpreter = Interpreter::new(compiler, std::io::sink());
+    match GetStatement(item.clone()).get(compiler) {
+        TopLevelStatement::Import { .. }
+        | TopLevelStatement::TypeDefinition(_)
+        | TopLevelStatement::TypeAlias(_)
+        | TopLevelStatement::Class(_)
+        | TopLevelStatement::Instance(_) => Ok(None),
+        TopLevelStatement::Definition(_) if !GetType(item.clone()).get(compiler).constraints.is_empty() => {
+            Err(format!(
+                "{}: Cannot evaluate a definition with constraints without knowing their instances",
+                interpreter.location(item)
+            ))
+        },
+        TopLevelStatement::Definition(_) => interpreter.definition_value(item).map(Some),
+        TopLevelStatement::Print(expression, _) => interpreter.eval(&expression, item, &None).map(Some),
+    }
+}
+
+#[derive(Clone)]
+pub enum Value {
+    Int(i64),
+    Bool(bool),
+    String(Arc<String>),
+    Function(Rc<Function>),
+    Constructor { name: Arc<String>, fields: Vec<Value> },
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+    Record(BTreeMap<Arc<String>, Value>),
+}
+
+pub enum Function {
+    Closure {
+        /// The statement the lambda is in. We need this to look up the origins of variables in its body.
+        item: TopLevelId,
+        parameter: Pattern,
+        body: Arc<Expression>,
+        environment: Environment,
+    },
+    /// A built-in operator or function, along with any arguments it has been applied to so far
+    Builtin { builtin: Builtin, arguments: Vec<Value> },
+    /// A constructor with at least one field, along with the fields it has been given so far
+    Constructor { name: Arc<String>, arity: usize, fields: Vec<Value> },
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Value::Int(x) => write!(f, "{x}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::String(s) => write!(f, "{s}"),
+            Value::Function(_) => write!(f, "<function>"),
+            // Strings within a constructor are quoted so that `Pair "a b" "c"` isn't confused with `Pair "a" "b c"`
+            Value::Constructor { name, fields } => {
+                write!(f, "{name}")?;
+                for field in fields {
+                    if matches!(field, Value::Constructor { fields, .. } if !fields.is_empty()) {
+                        write!(f, " ({field})")?;
+                    } else {
+                        write!(f, " ")?;
+                        write_nested(f, field)?;
+                    }
+                }
+                Ok(())
+            },
+            Value::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_nested(f, element)?;
+                }
+                write!(f, ")")
+            },
+            Value::List(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_nested(f, element)?;
+                }
+                write!(f, "]")
+            },
+            Value::Record(fields) if fields.is_empty() => write!(f, "{{}}"),
+            Value::Record(fields) => {
+                write!(f, "{{ ")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{name} = ")?;
+                    write_nested(f, value)?;
+                }
+                write!(f, " }}")
+            },
+        }
+    }
+}
+
+/// Write a value contained within another value, quoting it if it is a string
+fn write_nested(f: &mut std::fmt::Formatter, value: &Value) -> std::fmt::Result {
+    match value {
+        Value::String(s) => write!(f, "\"{}\"", escape(s)),
+        other => write!(f, "{other}"),
+    }
+}
+
+/// The value of each parameter, pattern variable, and let binding in scope. Since closures need to capture their environment,
+/// we use an immutable linked list so that capturing it is just a cheap reference count increment.
+pub type Environment = Option<Rc<Binding>>;
+
+pub struct Binding {
+    local: Local,
+    value: Value,
+    rest: Environment,
+}
+
+/// Anything bound in an `Environment`
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Local {
+    /// A parameter, pattern variable, or let binding, by the id of its name
+    Variable(ExprId),
+    /// The dictionary given for the constraint at this index of the enclosing definition or instance
+    Dictionary(usize),
+}
+
+fn lookup(environment: &Environment, local: Local) -> Option<&Value> {
+    let mut current = environment.as_ref();
+    while let Some(binding) = current {
+        if binding.local == local {
+            return Some(&binding.value);
+        }
+        current = binding.rest.as_ref();
+    }
+    None
+}
+
+/// An environment containing only the given dictionaries, for the constraints of a definition
+/// or the context of an instance
+fn bind_dictionaries(dictionaries: Vec<Value>) -> Environment {
+    dictionaries
+        .into_iter()
+        .enumerate()
+        .fold(None, |rest, (index, value)| Some(Rc::new(Binding { local: Local::Dictionary(index), value, rest })))
+}
+
+/// If the value matches the pattern, returns the environment extended with each variable in the pattern
+fn match_pattern(pattern: &Pattern, value: &Value, environment: Environment) -> Option<Environment> {
+    match (pattern, value) {
+        (Pattern::Wildcard(_), _) => Some(environment),
+        (Pattern::Variable(variable), value) => Some(Some(Rc::new(Binding {
+            local: Local::Variable(variable.id),
+            value: value.clone(),
+            rest: environment,
+        }))),
+        (Pattern::IntegerLiteral(x, _), Value::Int(y)) => (x == y).then_some(environment),
+        (Pattern::BoolLiteral(x, _), Value::Bool(y)) => (x == y).then_some(environment),
+        (Pattern::Constructor { name, arguments, id: _ }, Value::Constructor { name: value_name, fields }) => {
+            if name.name != *value_name {
+                return None;
+            }
+            arguments
+                .iter()
+                .zip(fields)
+                .try_fold(environment, |environment, (argument, field)| match_pattern(argument, field, environment))
+        },
+        (Pattern::Tuple { elements, id: _ }, Value::Tuple(values)) => elements
+            .iter()
+            .zip(values)
+            .try_fold(environment, |environment, (element, value)| match_pattern(element, value, environment)),
+        _ => unreachable!("Type checking ensures patterns match values of the same type"),
+    }
+}
+
+struct Interpreter<'c, W> {
+    compiler: &'c Compiler,
+    output: W,
+
+    /// The value of each top-level definition evaluated so far.
+    /// `None` means the definition is currently being evaluated.
+    definitions: BTreeMap<TopLevelId, Option<Value>>,
+
+    /// Cache of the origins of each variable in each statement we've evaluated. This saves
+    /// us from cloning the entire `ResolutionResult` every time a variable is looked up.
+    origins: BTreeMap<TopLevelId, BTreeMap<ExprId, Origin>>,
+
+    /// Cache of the evidence for each variable with constraints in each statement we've evaluated
+    evidence: BTreeMap<TopLevelId, BTreeMap<ExprId, Vec<Evidence>>>,
+
+    files_run: BTreeSet<Arc<String>>,
+}
+
+impl<'c, W: Write> Interpreter<'c, W> {
+    fn new(compiler: &'c Compiler, output: W) -> Self {
+        let (definitions, origins, evidence) = (BTreeMap::new(), BTreeMap::new(), BTreeMap::new());
+        Self { compiler, output, definitions, origins, evidence, files_run: BTreeSet::new() }
+    }
+
+    /// Execute each statement in the given file unless it has already been run
+    fn run_file(&mut self, file_name: Arc<String>) -> Result<(), String> {
+        if !self.files_run.insert(file_name.clone()) {
+            return Ok(());
+        }
+
+        let ast = Parse { file_name }.get(self.compiler).ast;
+        for statement in ast.statements.iter() {
+            match statement {
+                TopLevelStatement::Import { file_name, .. } => self.run_file(file_name.name.clone())?,
+                // Definitions are evaluated lazily when they are first used
+                TopLevelStatement::Definition(_)
+                | TopLevelStatement::TypeDefinition(_)
+                | TopLevelStatement::TypeAlias(_)
+                | TopLevelStatement::Class(_)
+                | TopLevelStatement::Instance(_) => (),
+                TopLevelStatement::Print(expression, id) => {
+                    let value = self.eval(expression, id, &None)?;
+                    writeln!(self.output, "{value}").map_err(|error| format!("Failed to write output: {error}"))?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieve the value of a top-level definition, evaluating it if this is its first use
+    fn definition_value(&mut self, id: &TopLevelId) -> Result<Value, String> {
+        match self.definitions.get(id) {
+            Some(Some(value)) => return Ok(value.clone()),
+            Some(None) => return Err(format!("{}: This definition depends on its own value", self.location(id))),
+            None => (),
+        }
+
+        let TopLevelStatement::Definition(definition) = GetStatement(id.clone()).get(self.compiler) else {
+            unreachable!("Origin {id} should always refer to a definition")
+        };
+
+        self.definitions.insert(id.clone(), None);
+        let value = self.eval(&definition.body, id, &None)?;
+        self.definitions.insert(id.clone(), Some(value.clone()));
+        Ok(value)
+    }
+
+    /// Evaluate a definition with constraints given the dictionary for each of them. Since these may
+    /// differ for each use, the value isn't cached, although it is still marked as being evaluated
+    /// while its body is evaluated in case it depends on its own value.
+    fn constrained_definition_value(&mut self, id: &TopLevelId, dictionaries: Vec<Value>) -> Result<Value, String> {
+        if self.definitions.contains_key(id) {
+            return Err(format!("{}: This definition depends on its own value", self.location(id)));
+        }
+
+        let TopLevelStatement::Definition(definition) = GetStatement(id.clone()).get(self.compiler) else {
+            unreachable!("Origin {id} should always refer to a definition")
+        };
+
+        self.definitions.insert(id.clone(), None);
+        let value = self.eval(&definition.body, id, &bind_dictionaries(dictionaries));
+        self.definitions.remove(id);
+        value
+    }
+
+    /// The dictionary for a constraint: a record of the value of each method of the instance given by the evidence
+    fn dictionary(&mut self, evidence: &Evidence, environment: &Environment) -> Result<Value, String> {
+        match evidence {
+            Evidence::Parameter(index) => {
+                Ok(lookup(environment, Local::Dictionary(*index)).expect("Dictionaries should be in scope").clone())
+            },
+            Evidence::Instance(id, _) if let Some((class, _)) = builtin_instance(id) => {
+                let methods = class.methods().map(|builtin| {
+                    let name = Arc::new(builtin.method_name().to_string());
+                    (name, Value::Function(Rc::new(Function::Builtin { builtin, arguments: Vec::new() })))
+                });
+                Ok(Value::Record(methods.collect()))
+            },
+            Evidence::Instance(id, arguments) => {
+                let arguments = arguments.iter().map(|argument| self.dictionary(argument, environment));
+                let environment = bind_dictionaries(arguments.collect::<Result<_, _>>()?);
+
+                let TopLevelStatement::Instance(instance) = GetStatement(id.clone()).get(self.compiler) else {
+                    unreachable!("Evidence should refer to an instance")
+                };
+
+                let mut methods = BTreeMap::new();
+                for (name, body) in instance.methods.iter() {
+                    methods.insert(name.name.clone(), self.eval(body, id, &environment)?);
+                }
+                Ok(Value::Record(methods))
+            },
+        }
+    }
+
+    /// The value of the method with the given name in the dictionary given by the evidence
+    fn method_value(&mut self, evidence: &[Evidence], name: &str, environment: &Environment) -> Result<Value, String> {
+        let [evidence] = evidence else { unreachable!("Each use of a method has evidence for its class") };
+        match self.dictionary(evidence, environment)? {
+            Value::Record(methods) => {
+                let method = methods.into_iter().find(|(method, _)| method.as_str() == name);
+                Ok(method.expect("Instances define each method").1)
+            },
+            _ => unreachable!("Dictionaries are records"),
+        }
+    }
+
+    fn eval(
+        &mut self, expression: &Arc<Expression>, item: &TopLevelId, environment: &Environment,
+    ) -> Result<Value, String> {
+        match expression.as_ref() {
+            Expression::IntegerLiteral(x, _) => Ok(Value::Int(*x)),
+            Expression::BoolLiteral(b, _) => Ok(Value::Bool(*b)),
+            Expression::StringLiteral(s, _) => Ok(Value::String(s.clone())),
+            Expression::Hole { name: _, id } => Err(format!("{}: Reached a hole", self.expr_location(item, *id))),
+            Expression::Variable(identifier) => match self.origin(item, identifier.id) {
+                Some(Origin::Parameter(parameter) | Origin::LetBinding(parameter)) => {
+                    let local = Local::Variable(parameter);
+                    Ok(lookup(environment, local).expect("Local variables should be in scope").clone())
+                },
+                Some(Origin::TopLevelDefinition(id)) => {
+                    let evidence = self.evidence(item, identifier.id);
+                    if evidence.is_empty() {
+                        self.definition_value(&id)
+                    } else {
+                        let dictionaries = evidence.iter().map(|evidence| self.dictionary(evidence, environment));
+                        let dictionaries = dictionaries.collect::<Result<_, _>>()?;
+                        self.constrained_definition_value(&id, dictionaries)
+                    }
+                },
+                Some(Origin::Method(_, name)) => {
+                    let evidence = self.evidence(item, identifier.id);
+                    self.method_value(&evidence, &name, environment)
+                },
+                Some(Origin::Constructor(type_id, name)) => Ok(self.constructor_value(&type_id, name)),
+                Some(Origin::TypeDefinition(_) | Origin::TypeAlias(_) | Origin::Class(_)) => {
+                    unreachable!("Type and class names are never used as values")
+                },
+                // Built-ins are the only names without an origin in a program without errors.
+                // Operators are methods of their built-in class, so they're found in its dictionary.
+                None => {
+                    let builtin =
+                        Builtin::from_name(&identifier.name).expect("Names without an origin should be built-ins");
+                    match builtin.class() {
+                        Some(_) => {
+                            let evidence = self.evidence(item, identifier.id);
+                            self.method_value(&evidence, builtin.method_name(), environment)
+                        },
+                        None => Ok(Value::Function(Rc::new(Function::Builtin { builtin, arguments: Vec::new() }))),
+                    }
+                },
+            },
+            Expression::FunctionCall { function, argument, id } => {
+                let function = self.eval(function, item, environment)?;
+                let argument = self.eval(argument, item, environment)?;
+                self.apply(function, argument, item, *id)
+            },
+            Expression::Lambda { parameter, body, id: _ } => Ok(Value::Function(Rc::new(Function::Closure {
+                item: item.clone(),
+                parameter: parameter.clone(),
+                body: body.clone(),
+                environment: environment.clone(),
+            }))),
+            Expression::If { condition, then_branch, else_branch, id: _ } => {
+                match self.eval(condition, item, environment)? {
+                    Value::Bool(true) => self.eval(then_branch, item, environment),
+                    Value::Bool(false) => self.eval(else_branch, item, environment),
+                    _ => unreachable!("Type checking ensures `if` conditions are booleans"),
+                }
+            },
+            Expression::Let { name, value, body, id: _ } => {
+                let value = self.eval(value, item, environment)?;
+                let local = Local::Variable(name.id);
+                let environment = Some(Rc::new(Binding { local, value, rest: environment.clone() }));
+                self.eval(body, item, &environment)
+            },
+            Expression::Match { scrutinee, arms, id: _ } => {
+                let value = self.eval(scrutinee, item, environment)?;
+                for arm in arms {
+                    if let Some(environment) = match_pattern(&arm.pattern, &value, environment.clone()) {
+                        return self.eval(&arm.body, item, &environment);
+                    }
+                }
+                unreachable!("Exhaustiveness checking ensures some arm of each match matches")
+            },
+            Expression::Tuple { elements, id: _ } => {
+                let elements = elements.iter().map(|element| self.eval(element, item, environment));
+                Ok(Value::Tuple(elements.collect::<Result<_, _>>()?))
+            },
+            Expression::List { elements, id: _ } => {
+                let elements = elements.iter().map(|element| self.eval(element, item, environment));
+                Ok(Value::List(elements.collect::<Result<_, _>>()?))
+            },
+            Expression::Record { fields, id: _ } => {
+                let mut values = BTreeMap::new();
+                for (name, value) in fields {
+                    values.insert(name.name.clone(), self.eval(value, item, environment)?);
+                }
+                Ok(Value::Record(values))
+            },
+            Expression::FieldAccess { record, field, id: _ } => match self.eval(record, item, environment)? {
+                Value::Record(mut fields) => {
+                    Ok(fields.remove(&field.name).expect("Type checking ensures fields exist"))
+                },
+                _ => unreachable!("Type checking ensures only records have fields"),
+            },
+        }
+    }
+
+    /// Apply a builtin to all of its arguments. `map` and `fold` need to call the function they're
+    /// given so they're evaluated here, while `Builtin::evaluate` handles the others.
+    fn evaluate_builtin(
+        &mut self, builtin: Builtin, arguments: &[Value], item: &TopLevelId, call: ExprId,
+    ) -> Result<Value, String> {
+        match (builtin, arguments) {
+            (Builtin::Map, [function, Value::List(elements)]) => {
+                let elements = elements.iter().map(|element| self.apply(function.clone(), element.clone(), item, call));
+                Ok(Value::List(elements.collect::<Result<_, _>>()?))
+            },
+            (Builtin::Fold, [function, initial, Value::List(elements)]) => {
+                elements.iter().try_fold(initial.clone(), |accumulator, element| {
+                    let partially_applied = self.apply(function.clone(), accumulator, item, call)?;
+                    self.apply(partially_applied, element.clone(), item, call)
+                })
+            },
+            _ => builtin.evaluate(arguments).map_err(|error| format!("{}: {error}", self.expr_location(item, call))),
+        }
+    }
+
+    /// The value of a constructor. Constructors with fields are functions taking each field in turn.
+    fn constructor_value(&self, type_id: &TopLevelId, name: Arc<String>) -> Value {
+        let arity = ConstructorSignature::new(type_id, &name, self.compiler).fields.len();
+        if arity == 0 {
+            Value::Constructor { name, fields: Vec::new() }
+        } else {
+            Value::Function(Rc::new(Function::Constructor { name, arity, fields: Vec::new() }))
+        }
+    }
+
+    /// Apply a function to an argument. `item` and `call` identify the call expression for errors.
+    fn apply(&mut self, function: Value, argument: Value, item: &TopLevelId, call: ExprId) -> Result<Value, String> {
+        let Value::Function(function) = function else {
+            unreachable!("Type checking ensures only functions are called")
+        };
+
+        match function.as_ref() {
+            Function::Closure { item, parameter, body, environment } => {
+                let environment = match_pattern(parameter, &argument, environment.clone())
+                    .expect("Match checking ensures lambda parameters match every argument");
+                self.eval(body, item, &environment)
+            },
+            Function::Builtin { builtin, arguments } => {
+                let mut arguments = arguments.clone();
+                arguments.push(argument);
+
+                if arguments.len() == builtin.arity() {
+                    self.evaluate_builtin(*builtin, &arguments, item, call)
+                } else {
+                    Ok(Value::Function(Rc::new(Function::Builtin { builtin: *builtin, arguments })))
+                }
+            },
+            Function::Constructor { name, arity, fields } => {
+                let mut fields = fields.clone();
+                fields.push(argument);
+
+                if fields.len() == *arity {
+                    Ok(Value::Constructor { name: name.clone(), fields })
+                } else {
+                    Ok(Value::Function(Rc::new(Function::Constructor { name: name.clone(), arity: *arity, fields })))
+                }
+            },
+        }
+    }
+
+    fn origin(&mut self, item: &TopLevelId, variable: ExprId) -> Option<Origin> {
+        let compiler = self.compiler;
+        let origins = self.origins.entry(item.clone()).or_insert_with(|| Resolve(item.clone()).get(compiler).origins);
+        origins.get(&variable).cloned()
+    }
+
+    /// The evidence for each constraint of the variable, which is empty if it has none
+    fn evidence(&mut self, item: &TopLevelId, variable: ExprId) -> Vec<Evidence> {
+        let compiler = self.compiler;
+        let evidence =
+            self.evidence.entry(item.clone()).or_insert_with(|| TypeCheck(item.clone()).get(compiler).evidence);
+        evidence.get(&variable).cloned().unwrap_or_default()
+    }
+
+    fn location(&self, id: &TopLevelId) -> Location {
+        Parse { file_name: id.file_path.clone() }.get(self.compiler).top_level_data[id].location.clone()
+    }
+
+    fn expr_location(&self, item: &TopLevelId, id: ExprId) -> Location {
+        Parse { file_name: item.file_path.clone() }.get(self.compiler).top_level_data[item].expr_locations[&id].clone()
+    }
+}
@@ -0,0 +1,275 @@
+//! A minimal JSON value type used for machine-readable output and for communicating with
+//! editors in the language server. We only need a tiny subset of what a full JSON library
+//! provides so it is simple enough to write by hand.
+use std::{fmt::Write, iter::Peekable, str::Chars};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Fields are kept in insertion order so output is stable and easy to read
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Json {
+        Json::Object(fields.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+    }
+
+    /// Retrieve the given field of an object. Returns `None` if this is not an object
+    /// or the field is not present.
+    pub fn get(&self, field: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(name, _)| name == field).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns this number if it is a non-negative integer
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(value) if *value >= 0.0 && value.fract() == 0.0 => Some(*value as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = JsonParser { chars: text.chars().peekable() };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("Unexpected trailing character `{c}`")),
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("Expected `{expected}` but found `{c}`")),
+            None => Err(format!("Expected `{expected}` but found the end of input")),
+        }
+    }
+
+    fn expect_word(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        word.chars().try_for_each(|c| self.expect(c))?;
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('n') => self.expect_word("null", Json::Null),
+            Some('t') => self.expect_word("true", Json::Bool(true)),
+            Some('f') => self.expect_word("false", Json::Bool(false)),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_object(),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) => Err(format!("Unexpected character `{c}`")),
+            None => Err("Unexpected end of input".to_string()),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let mut number = String::new();
+        while let Some(c) = self.chars.next_if(|c| matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9')) {
+            number.push(c);
+        }
+        number.parse().map(Json::Number).map_err(|_| format!("Invalid number `{number}`"))
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    Some('b') => string.push('\u{8}'),
+                    Some('f') => string.push('\u{c}'),
+                    Some('u') => string.push(self.parse_unicode_escape()?),
+                    Some(c @ ('"' | '\\' | '/')) => string.push(c),
+                    other => return Err(format!("Invalid escape sequence `\\{}`", other.unwrap_or(' '))),
+                },
+                Some(c) => string.push(c),
+                None => return Err("Unterminated string".to_string()),
+            }
+        }
+    }
+
+    /// Parse the `XXXX` in a `\uXXXX` escape, along with the second half of a
+    /// surrogate pair if this is the first half of one.
+    fn parse_unicode_escape(&mut self) -> Result<char, String> {
+        let parse_hex = |this: &mut Self| {
+            let digits: String = this.chars.by_ref().take(4).collect();
+            u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid unicode escape `\\u{digits}`"))
+        };
+
+        let first = parse_hex(self)?;
+        let code_point = if (0xD800..0xDC00).contains(&first) {
+            self.expect('\\')?;
+            self.expect('u')?;
+            let second = parse_hex(self)?;
+            0x10000 + ((first - 0xD800) << 10) + (second.wrapping_sub(0xDC00) & 0x3FF)
+        } else {
+            first
+        };
+        Ok(char::from_u32(code_point).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+
+        if self.chars.next_if_eq(&']').is_none() {
+            loop {
+                elements.push(self.parse_value()?);
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&',').is_none() {
+                    self.expect(']')?;
+                    break;
+                }
+            }
+        }
+        Ok(Json::Array(elements))
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+
+        if self.chars.next_if_eq(&'}').is_none() {
+            loop {
+                self.skip_whitespace();
+                let name = self.parse_string()?;
+                self.skip_whitespace();
+                self.expect(':')?;
+                fields.push((name, self.parse_value()?));
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&',').is_none() {
+                    self.expect('}')?;
+                    break;
+                }
+            }
+        }
+        Ok(Json::Object(fields))
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(value: u32) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(value: Vec<Json>) -> Self {
+        Json::Array(value)
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            Json::Number(value) => write!(f, "{value}"),
+            Json::String(value) => write_string(value, f),
+            Json::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(name, f)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn write_string(string: &str, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    f.write_char('"')?;
+    for c in string.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
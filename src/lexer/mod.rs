@@ -2,19 +2,40 @@ use std::{sync::Arc, str::CharIndices};
 use tokens::Token;
 
 use crate::{
-    errors::{Location, LocationData, Position},
+    errors::{Error, Errors, Location, LocationData, Position},
     incremental::{CompilerHandle, get_source_file},
 };
 
 pub mod tokens;
 
-/// Lex an entire file, returning a vector of tokens in the file
-pub fn lex_file(file_name: Arc<String>, db: &CompilerHandle) -> Vec<(Token, Location)> {
+/// Lex an entire file, returning a vector of tokens in the file along with any errors found.
+/// Like the parser, the lexer never fails outright. A malformed token is still returned so
+/// that the parser can continue from it.
+pub fn lex_file(file_name: Arc<String>, db: &CompilerHandle) -> (Vec<(Token, Location)>, Errors) {
     let source_file_text = get_source_file(file_name.clone(), db);
-    let lexer = Lexer::new(&source_file_text, file_name);
+    let mut lexer = Lexer::new(&source_file_text, file_name);
     // Calls `self.next()` until it returns `None`, collecting
     // all tokens into a `Vec<Token>`
-    lexer.collect()
+    let tokens = lexer.by_ref().collect();
+    (tokens, lexer.errors)
+}
+
+/// True if the given string is lexed as a single name. Keywords are not valid names.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut tokens = Lexer::new(name, Arc::new(String::new())).map(|(token, _)| token);
+    matches!((tokens.next(), tokens.next()), (Some(Token::Name(lexed)), None) if lexed == name)
+}
+
+/// True if the given name starts with an uppercase letter. Only capitalized names may name types
+/// and constructors, and only uncapitalized names may be variables in patterns.
+pub fn is_capitalized(name: &str) -> bool {
+    name.starts_with(char::is_uppercase)
+}
+
+/// True if the first token of the given text may start a top-level statement
+pub fn starts_top_level_statement(text: &str) -> bool {
+    let mut tokens = Lexer::new(text, Arc::new(String::new()));
+    tokens.next().is_some_and(|(token, _)| token.can_start_top_level_statement())
 }
 
 struct Lexer<'src> {
@@ -33,6 +54,8 @@ struct Lexer<'src> {
     /// be more than 1 byte apart.
     current_byte_index: usize,
     next_byte_index: usize,
+
+    errors: Errors,
 }
 
 impl<'src> Lexer<'src> {
@@ -46,6 +69,7 @@ impl<'src> Lexer<'src> {
             next_byte_index: 0,
             file_name,
             current_position: Position::start(),
+            errors: Vec::new(),
         };
         lexer.advance();
         lexer.advance();
@@ -58,16 +82,17 @@ impl<'src> Lexer<'src> {
     ///
     /// If there is no remaining input, the next character is set to '\0' instead.
     fn advance(&mut self) {
-        (self.current_byte_index, self.current_char) = (self.next_byte_index, self.next_char);
-        (self.next_byte_index, self.next_char) = self.source_file.next().unwrap_or((self.source_file_len, '\0'));
-
-        self.current_position.byte_index = self.current_byte_index;
-        self.current_position.column_number += 1;
-
+        // Moving past a newline puts us at the start of the next line
         if self.current_char == '\n' {
             self.current_position.line_number += 1;
-            self.current_position.column_number = 0;
+            self.current_position.column_number = 1;
+        } else {
+            self.current_position.column_number += 1;
         }
+
+        (self.current_byte_index, self.current_char) = (self.next_byte_index, self.next_char);
+        (self.next_byte_index, self.next_char) = self.source_file.next().unwrap_or((self.source_file_len, '\0'));
+        self.current_position.byte_index = self.current_byte_index;
     }
 
     fn location(&self, start: Position, end: Position) -> Location {
@@ -87,30 +112,52 @@ impl<'src> Lexer<'src> {
             Some((token, this.location_from(start)))
         };
 
-        match self.current_char {
-            '=' => advance_with(self, Token::Equals),
-            ':' => advance_with(self, Token::Colon),
-            '-' if self.next_char == '>' => {
-                self.advance();
-                self.advance();
-                Some((Token::RightArrow, self.location_from(start)))
-            },
-            '-' => advance_with(self, Token::Minus),
-            '+' => advance_with(self, Token::Plus),
-            '(' => advance_with(self, Token::ParenLeft),
-            ')' => advance_with(self, Token::ParenRight),
-            '/' if self.next_char == '/' => {
+        let advance2_with = |this: &mut Self, token| {
+            this.advance();
+            advance_with(this, token)
+        };
+
+        match (self.current_char, self.next_char) {
+            ('=', '=') => advance2_with(self, Token::EqualEquals),
+            ('=', '>') => advance2_with(self, Token::FatArrow),
+            ('=', _) => advance_with(self, Token::Equals),
+            (':', _) => advance_with(self, Token::Colon),
+            (',', _) => advance_with(self, Token::Comma),
+            ('.', _) => advance_with(self, Token::Dot),
+            ('-', '>') => advance2_with(self, Token::RightArrow),
+            ('-', _) => advance_with(self, Token::Minus),
+            ('+', '+') => advance2_with(self, Token::PlusPlus),
+            ('+', _) => advance_with(self, Token::Plus),
+            ('*', _) => advance_with(self, Token::Star),
+            ('%', _) => advance_with(self, Token::Percent),
+            ('!', '=') => advance2_with(self, Token::NotEquals),
+            ('<', '=') => advance2_with(self, Token::LessEquals),
+            ('<', _) => advance_with(self, Token::Less),
+            ('>', '=') => advance2_with(self, Token::GreaterEquals),
+            ('>', _) => advance_with(self, Token::Greater),
+            ('|', _) => advance_with(self, Token::Pipe),
+            ('_', _) => advance_with(self, Token::Underscore),
+            ('(', _) => advance_with(self, Token::ParenLeft),
+            (')', _) => advance_with(self, Token::ParenRight),
+            ('{', _) => advance_with(self, Token::BraceLeft),
+            ('}', _) => advance_with(self, Token::BraceRight),
+            ('[', _) => advance_with(self, Token::BracketLeft),
+            (']', _) => advance_with(self, Token::BracketRight),
+            ('/', '/') => {
                 while self.current_char != '\0' && self.current_char != '\n' {
                     self.advance();
                 }
                 self.next_token()
             },
-            c if c.is_whitespace() => self.lex_whitespace(),
-            c if c.is_ascii_digit() => self.lex_integer(),
-            c if c.is_alphanumeric() => self.lex_word(),
-            '\0' => None, // End of input
+            ('/', _) => advance_with(self, Token::Slash),
+            ('?', c) if c.is_alphanumeric() => self.lex_hole(),
+            ('"', _) => self.lex_string(),
+            (c, _) if c.is_whitespace() => self.lex_whitespace(),
+            (c, _) if c.is_ascii_digit() => self.lex_integer(),
+            (c, _) if c.is_alphanumeric() => self.lex_word(),
+            ('\0', _) => None, // End of input
             // Unexpected token. We can't error so give it to the parser to error there
-            unexpected => advance_with(self, Token::Unexpected(unexpected)),
+            (unexpected, _) => advance_with(self, Token::Unexpected(unexpected)),
         }
     }
 
@@ -133,11 +180,26 @@ impl<'src> Lexer<'src> {
         }
 
         let token = match word.as_str() {
+            "Bool" => Token::Bool,
+            "class" => Token::Class,
             "def" => Token::Def,
+            "else" => Token::Else,
+            "false" => Token::False,
             "fn" => Token::Fn,
+            "forall" => Token::Forall,
+            "if" => Token::If,
             "import" => Token::Import,
+            "in" => Token::In,
+            "instance" => Token::Instance,
             "Int" => Token::Int,
+            "let" => Token::Let,
+            "match" => Token::Match,
             "print" => Token::Print,
+            "String" => Token::String,
+            "then" => Token::Then,
+            "true" => Token::True,
+            "type" => Token::Type,
+            "with" => Token::With,
             _other => Token::Name(word),
         };
 
@@ -145,6 +207,20 @@ impl<'src> Lexer<'src> {
         Some((token, location))
     }
 
+    /// Lex a named hole such as `?result`. The name may be anything a name could be.
+    fn lex_hole(&mut self) -> Option<(Token, Location)> {
+        let start = self.current_position;
+        self.advance(); // Skip the `?`
+
+        let mut name = String::new();
+        while self.current_char.is_alphanumeric() || self.current_char == '_' {
+            name.push(self.current_char);
+            self.advance();
+        }
+
+        Some((Token::Hole(name), self.location_from(start)))
+    }
+
     /// Lex a positive, 64-bit integer
     fn lex_integer(&mut self) -> Option<(Token, Location)> {
         let mut integer = 0;
@@ -160,6 +236,84 @@ impl<'src> Lexer<'src> {
         let location = self.location_from(start);
         Some((Token::Integer(integer), location))
     }
+
+    /// Lex a string literal, translating any escape sequences within it.
+    ///
+    /// String literals may not span multiple lines, so a string which isn't closed by the end of
+    /// its line is an error. The string is still returned with the contents up to that point.
+    fn lex_string(&mut self) -> Option<(Token, Location)> {
+        let start = self.current_position;
+        let mut contents = String::new();
+        self.advance(); // Skip the opening `"`
+
+        loop {
+            match self.current_char {
+                '"' => {
+                    self.advance();
+                    break;
+                },
+                '\\' => {
+                    let escape_start = self.current_position;
+                    self.advance();
+
+                    // Leave a backslash at the end of a line to the unterminated string error below
+                    if matches!(self.current_char, '\n' | '\0') {
+                        continue;
+                    }
+
+                    let escape = self.current_char;
+                    self.advance();
+                    match unescape(escape) {
+                        Some(c) => contents.push(c),
+                        None => {
+                            let location = self.location_from(escape_start);
+                            self.errors.push(Error::InvalidEscapeSequence { escape, location });
+                        },
+                    }
+                },
+                '\n' | '\0' => {
+                    self.errors.push(Error::UnterminatedString { location: self.location_from(start) });
+                    break;
+                },
+                c => {
+                    contents.push(c);
+                    self.advance();
+                },
+            }
+        }
+
+        let location = self.location_from(start);
+        Some((Token::StringLiteral(contents), location))
+    }
+}
+
+/// The character an escape sequence `\c` stands for, or `None` if `c` can't be escaped
+fn unescape(c: char) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '\\' => Some('\\'),
+        '"' => Some('"'),
+        _ => None,
+    }
+}
+
+/// Escape a string so that it can be written back out as a string literal, without the surrounding quotes.
+/// This is the inverse of the escape sequences the lexer translates.
+pub fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 impl<'src> Iterator for Lexer<'src> {
@@ -4,64 +4,183 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Token {
+    /// `Bool`
+    Bool,
+    /// `{`
+    BraceLeft,
+    /// `}`
+    BraceRight,
+    /// `[`
+    BracketLeft,
+    /// `]`
+    BracketRight,
+    /// `class`
+    Class,
     /// `:`
     Colon,
+    /// `,`
+    Comma,
     /// `def`
     Def,
+    /// `.`
+    Dot,
+    /// `else`
+    Else,
     /// `=`
     Equals,
+    /// `==`
+    EqualEquals,
+    /// `false`
+    False,
+    /// `=>`
+    FatArrow,
     /// `fn`
     Fn,
+    /// `forall`
+    Forall,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEquals,
+    /// `?{0}`, a named hole
+    Hole(String),
+    /// `if`
+    If,
     /// `import`
     Import,
+    /// `in`
+    In,
+    /// `instance`
+    Instance,
     /// `Int`
     Int,
     /// An integer literal - these must be positive i64 values
     Integer(i64),
+    /// `<`
+    Less,
+    /// `let`
+    Let,
+    /// `match`
+    Match,
+    /// `<=`
+    LessEquals,
     /// `-`
     Minus,
     /// `{0}` (the given string)
     Name(String),
+    /// `!=`
+    NotEquals,
     /// `(`
     ParenLeft,
     /// `)`
     ParenRight,
+    /// `%`
+    Percent,
+    /// `|`
+    Pipe,
     /// `+`
     Plus,
+    /// `++`
+    PlusPlus,
     /// `print`
     Print,
     /// `->`
     RightArrow,
+    /// `/`
+    Slash,
+    /// `*`
+    Star,
+    /// `String`
+    String,
+    /// A string literal with any escape sequences already translated
+    StringLiteral(String),
+    /// `then`
+    Then,
+    /// `true`
+    True,
+    /// `type`
+    Type,
+    /// `_`
+    Underscore,
     /// This character is not in the language - it is an error.
     /// We treat it as a token though since the lexer shouldn't error. It will get to the
     /// parser and the parser can error instead and decide how to recover.
     Unexpected(char),
+    /// `with`
+    With,
 }
 
 impl Token {
     pub fn can_start_top_level_statement(&self) -> bool {
-        matches!(self, Token::Def | Token::Import | Token::Print)
+        matches!(self, Token::Def | Token::Import | Token::Print | Token::Type | Token::Class | Token::Instance)
+    }
+
+    pub fn can_start_basic_type(&self) -> bool {
+        matches!(self, Token::Int | Token::Bool | Token::String | Token::Name(_) | Token::ParenLeft | Token::BraceLeft)
+    }
+
+    pub fn can_start_pattern(&self) -> bool {
+        matches!(
+            self,
+            Token::Underscore | Token::Name(_) | Token::Integer(_) | Token::True | Token::False | Token::ParenLeft
+        )
     }
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
+            Token::Bool => write!(f, "Bool"),
+            Token::BraceLeft => write!(f, "{{"),
+            Token::BraceRight => write!(f, "}}"),
+            Token::BracketLeft => write!(f, "["),
+            Token::BracketRight => write!(f, "]"),
+            Token::Class => write!(f, "class"),
             Token::Colon => write!(f, ":"),
+            Token::Comma => write!(f, ","),
             Token::Def => write!(f, "def"),
+            Token::Dot => write!(f, "."),
+            Token::Else => write!(f, "else"),
             Token::Equals => write!(f, "="),
+            Token::EqualEquals => write!(f, "=="),
+            Token::False => write!(f, "false"),
+            Token::FatArrow => write!(f, "=>"),
             Token::Fn => write!(f, "fn"),
+            Token::Forall => write!(f, "forall"),
+            Token::Greater => write!(f, ">"),
+            Token::GreaterEquals => write!(f, ">="),
+            Token::Hole(name) => write!(f, "?{name}"),
+            Token::If => write!(f, "if"),
             Token::Import => write!(f, "import"),
+            Token::In => write!(f, "in"),
+            Token::Instance => write!(f, "instance"),
             Token::Int => write!(f, "Int"),
             Token::Integer(x) => write!(f, "{x}"),
+            Token::Less => write!(f, "<"),
+            Token::Let => write!(f, "let"),
+            Token::Match => write!(f, "match"),
+            Token::LessEquals => write!(f, "<="),
             Token::Minus => write!(f, "-"),
             Token::Name(name) => write!(f, "{name}"),
+            Token::NotEquals => write!(f, "!="),
             Token::ParenLeft => write!(f, "("),
             Token::ParenRight => write!(f, ")"),
+            Token::Percent => write!(f, "%"),
+            Token::Pipe => write!(f, "|"),
             Token::Plus => write!(f, "+"),
+            Token::PlusPlus => write!(f, "++"),
             Token::Print => write!(f, "print"),
             Token::RightArrow => write!(f, "->"),
+            Token::Slash => write!(f, "/"),
+            Token::Star => write!(f, "*"),
+            Token::String => write!(f, "String"),
+            Token::StringLiteral(string) => write!(f, "\"{}\"", super::escape(string)),
+            Token::Then => write!(f, "then"),
+            Token::True => write!(f, "true"),
+            Token::Type => write!(f, "type"),
+            Token::Underscore => write!(f, "_"),
             Token::Unexpected(c) => write!(f, "{c}"),
+            Token::With => write!(f, "with"),
         }
     }
 }
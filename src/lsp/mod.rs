@@ -0,0 +1,437 @@
+//! A language server speaking the Language Server Protocol (LSP) over stdin and stdout.
+//!
+//! This is where the demand-driven architecture of the compiler really pays off. Instead of
+//! starting a fresh compiler for each request, the server keeps a single long-lived `Compiler`.
+//! Each time a file is edited we update the `SourceFile` input for it and re-run `CheckFile` on
+//! each file in the program. Since every query is cached, this only re-does the work affected by
+//! the edit - usually re-parsing one file and re-checking a handful of statements in it.
+//!
+//! Files open in the editor are read from the editor's buffers rather than from disk, since they
+//! may contain unsaved changes. Any files they import which are not open are still read from disk.
+//!
+//! Besides diagnostics, the server supports hovering over an expression to see its type, jumping
+//! to the definition of a name, finding all references to a name, and renaming it. Each of these
+//! is answered by incremental queries from `ide`. Since each open document is the root of a program,
+//! references and renames are found within each of these programs and merged together.
+//!
+//! The server only supports full document synchronization: each change notification contains
+//! the entire new text of the document. The server is generic over its input and output streams
+//! so it can be driven by a scripted client rather than a real editor.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{BufRead, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    errors::{Error, Errors, Location, Severity},
+    find_changed_files::collect_all_changed_files_with,
+    incremental::{
+        self, get_source_file, set_source_file, CheckFile, Compiler, DefinitionAtPosition, FindReferences, Rename,
+        SymbolAtPosition, TypeAtPosition,
+    },
+    json::Json,
+    read_file,
+    type_inference::types::TypeBindings,
+};
+
+/// Run the language server until the client sends an `exit` notification or closes the input stream.
+pub fn run(mut input: impl BufRead, output: impl Write) -> Result<(), String> {
+    // Printing our query trace would corrupt the messages we send to the client
+    incremental::set_quiet(true);
+    let mut server = Server::new(output);
+
+    while let Some(message) = read_message(&mut input)? {
+        match Json::parse(&message) {
+            Ok(message) => {
+                if !server.handle(message)? {
+                    break;
+                }
+            },
+            Err(error) => eprintln!("lsp: Failed to parse message: {error}"),
+        }
+    }
+    Ok(())
+}
+
+struct Server<W> {
+    compiler: Compiler,
+    output: W,
+
+    /// The current text of each file open in the editor. These take priority over the
+    /// contents of the file on disk. Each open file is also treated as the root of a program.
+    open_documents: BTreeMap<Arc<String>, String>,
+
+    /// Files we have published diagnostics for, so that we can clear them once the
+    /// file is no longer part of any program.
+    published: BTreeSet<Arc<String>>,
+}
+
+impl<W: Write> Server<W> {
+    fn new(output: W) -> Self {
+        Self { compiler: Compiler::default(), output, open_documents: BTreeMap::new(), published: BTreeSet::new() }
+    }
+
+    /// Handle a single message from the client. Returns false if the server should exit.
+    fn handle(&mut self, message: Json) -> Result<bool, String> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+
+        match (message.get("id").cloned(), method) {
+            (Some(id), "initialize") => self.respond(id, capabilities())?,
+            (Some(id), "shutdown") => self.respond(id, Json::Null)?,
+            (None, "exit") => return Ok(false),
+            (None, "textDocument/didOpen") => {
+                let document = params.get("textDocument");
+                let file = document.and_then(|document| document.get("uri")).and_then(Json::as_str).map(uri_to_path);
+                let text = document.and_then(|document| document.get("text")).and_then(Json::as_str);
+
+                if let (Some(file), Some(text)) = (file, text) {
+                    self.open_documents.insert(file, text.to_string());
+                    self.publish_diagnostics()?;
+                }
+            },
+            (None, "textDocument/didChange") => {
+                let file = document_path(&params);
+                // With full document synchronization the last change contains the entire new text
+                let changes = params.get("contentChanges").and_then(Json::as_array).unwrap_or_default();
+                let text = changes.last().and_then(|change| change.get("text")).and_then(Json::as_str);
+
+                if let (Some(file), Some(text)) = (file, text) {
+                    self.open_documents.insert(file, text.to_string());
+                    self.publish_diagnostics()?;
+                }
+            },
+            (None, "textDocument/didClose") => {
+                if let Some(file) = document_path(&params) {
+                    self.open_documents.remove(&file);
+                    self.publish_diagnostics()?;
+                }
+            },
+            (Some(id), "textDocument/hover") => {
+                let result = self.hover(&params).unwrap_or(Json::Null);
+                self.respond(id, result)?;
+            },
+            (Some(id), "textDocument/definition") => {
+                let result = self.definition(&params).unwrap_or(Json::Null);
+                self.respond(id, result)?;
+            },
+            (Some(id), "textDocument/references") => {
+                let result = self.references(&params).unwrap_or(Json::Null);
+                self.respond(id, result)?;
+            },
+            (Some(id), "textDocument/rename") => match self.rename(&params) {
+                Ok(result) => self.respond(id, result.unwrap_or(Json::Null))?,
+                // -32803 = RequestFailed
+                Err(error) => self.respond_error(id, -32803, error.message())?,
+            },
+            // This is a response to a request we sent. We don't send any requests so ignore it.
+            (Some(_), "") => (),
+            (Some(id), _) => self.respond_error(id, -32601, format!("Unsupported method `{method}`"))?,
+            // Ignore any other notifications
+            (None, _) => (),
+        }
+        Ok(true)
+    }
+
+    /// Re-check each program rooted at an open document and publish the diagnostics for each file.
+    /// Since the compiler is incremental, this only re-checks what has changed since the last call.
+    fn publish_diagnostics(&mut self) -> Result<(), String> {
+        let open_documents = self.open_documents.clone();
+        let read_source = |file: &Arc<String>| match open_documents.get(file) {
+            Some(text) => Ok(text.clone()),
+            None => read_file(Path::new(file.as_str())),
+        };
+
+        let mut files = BTreeSet::new();
+        let mut errors = Errors::new();
+
+        for (file, text) in open_documents.iter() {
+            set_source_file(file.clone(), text.clone(), &mut self.compiler);
+            let (new_files, new_errors) =
+                collect_all_changed_files_with(file.clone(), &read_source, &mut self.compiler);
+            files.extend(new_files);
+
+            // Programs rooted at different open documents may share imports, don't report errors twice
+            for error in new_errors {
+                if !errors.contains(&error) {
+                    errors.push(error);
+                }
+            }
+        }
+
+        let compiler = &self.compiler;
+        let check_errors: Errors =
+            files.clone().into_par_iter().flat_map(|file| CheckFile { file_name: file }.get(compiler)).collect();
+        errors.extend(check_errors);
+
+        let mut errors_by_file: BTreeMap<Arc<String>, Vec<&Error>> = BTreeMap::new();
+        for error in errors.iter() {
+            errors_by_file.entry(error.location().file_name.clone()).or_default().push(error);
+        }
+
+        // Clear diagnostics of any files which are no longer part of a program as well
+        let stale_files = std::mem::take(&mut self.published);
+        for file in files.union(&stale_files) {
+            let errors = errors_by_file.get(file).map(Vec::as_slice).unwrap_or_default();
+            let diagnostics = errors.iter().map(|error| self.diagnostic(error)).collect();
+
+            let params = Json::object([("uri", path_to_uri(file).into()), ("diagnostics", Json::Array(diagnostics))]);
+            self.notify("textDocument/publishDiagnostics", params)?;
+        }
+
+        self.published = files;
+        Ok(())
+    }
+
+    fn hover(&self, params: &Json) -> Option<Json> {
+        let (file_name, byte_index) = self.document_position(params)?;
+        let (location, typ) = TypeAtPosition { file_name, byte_index }.get(&self.compiler)?;
+
+        let contents = Json::object([
+            ("kind", "plaintext".into()),
+            ("value", typ.display(&TypeBindings::new()).to_string().into()),
+        ]);
+        Some(Json::object([("contents", contents), ("range", self.range(&location))]))
+    }
+
+    fn definition(&self, params: &Json) -> Option<Json> {
+        let (file_name, byte_index) = self.document_position(params)?;
+        let location = DefinitionAtPosition { file_name, byte_index }.get(&self.compiler)?;
+        Some(self.location(&location))
+    }
+
+    fn references(&self, params: &Json) -> Option<Json> {
+        let (file_name, byte_index) = self.document_position(params)?;
+        let symbol = SymbolAtPosition { file_name: file_name.clone(), byte_index }.get(&self.compiler)?;
+
+        let mut locations = BTreeSet::new();
+        for root_file in self.open_documents.keys().cloned() {
+            let references = FindReferences { root_file, symbol: symbol.clone() }.get(&self.compiler);
+            locations.extend(references.into_iter().map(|reference| reference.location));
+        }
+
+        let context = params.get("context");
+        let include_declaration =
+            context.and_then(|context| context.get("includeDeclaration")) == Some(&Json::Bool(true));
+        if include_declaration {
+            locations.extend(DefinitionAtPosition { file_name, byte_index }.get(&self.compiler));
+        }
+
+        Some(Json::Array(locations.iter().map(|location| self.location(location)).collect()))
+    }
+
+    /// Returns a `WorkspaceEdit` with the edits for each file, or `Ok(None)` if there
+    /// is nothing to rename at the given position.
+    fn rename(&self, params: &Json) -> Result<Option<Json>, Error> {
+        let Some((file_name, byte_index)) = self.document_position(params) else { return Ok(None) };
+        let Some(new_name) = params.get("newName").and_then(Json::as_str) else { return Ok(None) };
+        let Some(symbol) = SymbolAtPosition { file_name, byte_index }.get(&self.compiler) else { return Ok(None) };
+
+        let new_name = Arc::new(new_name.to_string());
+        let mut edits = BTreeSet::new();
+        for root_file in self.open_documents.keys().cloned() {
+            let rename = Rename { root_file, symbol: symbol.clone(), new_name: new_name.clone() };
+            edits.extend(rename.get(&self.compiler)?);
+        }
+
+        let mut edits_by_file: BTreeMap<_, Vec<Json>> = BTreeMap::new();
+        for edit in edits {
+            let text_edit =
+                Json::object([("range", self.range(&edit.location)), ("newText", edit.new_text.as_str().into())]);
+            edits_by_file.entry(path_to_uri(&edit.location.file_name)).or_default().push(text_edit);
+        }
+
+        let changes = edits_by_file.into_iter().map(|(uri, edits)| (uri, Json::Array(edits))).collect();
+        Ok(Some(Json::object([("changes", Json::Object(changes))])))
+    }
+
+    /// The file and byte index referred to by the `textDocument` and `position` of a request.
+    /// Returns `None` if the file is not part of any program we've checked since we won't have
+    /// its source text.
+    fn document_position(&self, params: &Json) -> Option<(Arc<String>, usize)> {
+        let file = document_path(params)?;
+        if !self.open_documents.contains_key(&file) && !self.published.contains(&file) {
+            return None;
+        }
+
+        let text = get_source_file(file.clone(), &self.compiler);
+        let byte_index = byte_index(&text, params.get("position")?)?;
+        Some((file, byte_index))
+    }
+
+    fn diagnostic(&self, error: &Error) -> Json {
+        let related = error.labels().into_iter().filter(|label| !label.primary).map(|label| {
+            Json::object([("location", self.location(label.location)), ("message", label.message.into())])
+        });
+
+        let severity = match error.severity() {
+            Severity::Error => 1usize,
+            Severity::Warning => 2,
+        };
+
+        Json::object([
+            ("range", self.range(error.location())),
+            ("severity", severity.into()),
+            ("code", error.code().into()),
+            ("source", "modern_compiler_architecture".into()),
+            ("message", error.message().into()),
+            ("relatedInformation", Json::Array(related.collect())),
+        ])
+    }
+
+    fn location(&self, location: &Location) -> Json {
+        Json::object([("uri", path_to_uri(&location.file_name).into()), ("range", self.range(location))])
+    }
+
+    fn range(&self, location: &Location) -> Json {
+        let text = get_source_file(location.file_name.clone(), &self.compiler);
+        Json::object([
+            ("start", lsp_position(&text, location.start.byte_index)),
+            ("end", lsp_position(&text, location.end.byte_index)),
+        ])
+    }
+
+    fn respond(&mut self, id: Json, result: Json) -> Result<(), String> {
+        self.send(Json::object([("jsonrpc", "2.0".into()), ("id", id), ("result", result)]))
+    }
+
+    fn respond_error(&mut self, id: Json, code: i32, message: String) -> Result<(), String> {
+        let error = Json::object([("code", Json::Number(code.into())), ("message", message.into())]);
+        self.send(Json::object([("jsonrpc", "2.0".into()), ("id", id), ("error", error)]))
+    }
+
+    fn notify(&mut self, method: &str, params: Json) -> Result<(), String> {
+        self.send(Json::object([("jsonrpc", "2.0".into()), ("method", method.into()), ("params", params)]))
+    }
+
+    fn send(&mut self, message: Json) -> Result<(), String> {
+        let body = message.to_string();
+        write!(self.output, "Content-Length: {}\r\n\r\n{body}", body.len())
+            .and_then(|()| self.output.flush())
+            .map_err(|error| format!("Failed to write to client: {error}"))
+    }
+}
+
+fn capabilities() -> Json {
+    // 1 = Full document synchronization
+    let capabilities = Json::object([
+        ("textDocumentSync", 1usize.into()),
+        ("hoverProvider", true.into()),
+        ("definitionProvider", true.into()),
+        ("referencesProvider", true.into()),
+        ("renameProvider", true.into()),
+    ]);
+    let server_info = Json::object([("name", "modern_compiler_architecture".into())]);
+    Json::object([("capabilities", capabilities), ("serverInfo", server_info)])
+}
+
+/// Read the body of a single message, or `None` if the input stream has ended.
+/// Each message is preceded by a `Content-Length` header giving the length of its body in bytes.
+fn read_message(input: &mut impl BufRead) -> Result<Option<String>, String> {
+    let mut content_length = None;
+
+    loop {
+        let mut line = String::new();
+        let bytes_read = input.read_line(&mut line).map_err(|error| format!("Failed to read from client: {error}"))?;
+        if bytes_read == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() && content_length.is_some() {
+            break;
+        } else if let Some(length) = line.strip_prefix("Content-Length:") {
+            let length = length.trim().parse().map_err(|_| format!("Invalid Content-Length `{length}`"))?;
+            content_length = Some(length);
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap()];
+    input.read_exact(&mut body).map_err(|error| format!("Failed to read from client: {error}"))?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// The path of the file in `params.textDocument.uri`
+fn document_path(params: &Json) -> Option<Arc<String>> {
+    let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+    Some(uri_to_path(uri))
+}
+
+/// Convert a `file://` uri to a file path
+fn uri_to_path(uri: &str) -> Arc<String> {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+
+    // Decode any percent-encoded bytes like `%20`
+    while let Some((&byte, remaining)) = rest.split_first() {
+        let decoded = remaining.get(..2).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match decoded {
+            Some(decoded) if byte == b'%' => {
+                bytes.push(decoded);
+                rest = &remaining[2..];
+            },
+            _ => {
+                bytes.push(byte);
+                rest = remaining;
+            },
+        }
+    }
+    Arc::new(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Convert a file path to a `file://` uri
+fn path_to_uri(path: &str) -> String {
+    let mut uri = "file://".to_string();
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri += &format!("%{byte:02X}");
+        }
+    }
+    uri
+}
+
+/// Convert a byte index into the given text to an LSP position. LSP positions are a 0-based line
+/// and a 0-based character offset within that line, counted in UTF-16 code units.
+fn lsp_position(text: &str, byte_index: usize) -> Json {
+    let mut byte_index = byte_index.min(text.len());
+    while !text.is_char_boundary(byte_index) {
+        byte_index -= 1;
+    }
+
+    let line_start = text[..byte_index].rfind('\n').map_or(0, |index| index + 1);
+    let line = text[..line_start].matches('\n').count();
+    let character: usize = text[line_start..byte_index].chars().map(char::len_utf16).sum();
+    Json::object([("line", line.into()), ("character", character.into())])
+}
+
+/// Convert an LSP position back into a byte index into the given text.
+/// Positions past the end of a line are clamped to the end of that line.
+fn byte_index(text: &str, position: &Json) -> Option<usize> {
+    let line = position.get("line")?.as_usize()?;
+    let character = position.get("character")?.as_usize()?;
+
+    let line_start = match line {
+        0 => 0,
+        _ => text.match_indices('\n').nth(line - 1)?.0 + 1,
+    };
+    let line_text = text[line_start..].split('\n').next().unwrap_or_default();
+
+    let mut utf16_offset = 0;
+    for (index, c) in line_text.char_indices() {
+        if utf16_offset >= character {
+            return Some(line_start + index);
+        }
+        utf16_offset += c.len_utf16();
+    }
+    Some(line_start + line_text.len())
+}
+
+#[cfg(test)]
+mod tests;
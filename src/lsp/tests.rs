@@ -0,0 +1,160 @@
+//! Tests which drive the server with a scripted client, sending each message through in-memory
+//! input and output streams just as an editor would over stdin and stdout.
+use super::{read_message, run};
+use crate::json::Json;
+
+const URI: &str = "file:///lsp_tests/main.ex";
+
+const SOURCE: &str = "\
+def double = fn x -> x + x
+def four = double 2
+def quadruple = fn y -> double (double y)
+";
+
+/// A session with a client which opens `SOURCE` then sends each of the given requests, numbered
+/// from 1, before shutting the server down. Returns every message the server sent back.
+fn session(requests: Vec<(&str, Json)>) -> Vec<Json> {
+    let text_document = Json::object([("uri", URI.into()), ("text", SOURCE.into())]);
+    let mut messages = vec![
+        request(0, "initialize", Json::object([])),
+        notification("textDocument/didOpen", Json::object([("textDocument", text_document)])),
+    ];
+
+    let request_count = requests.len();
+    for (id, (method, params)) in requests.into_iter().enumerate() {
+        messages.push(request(id + 1, method, params));
+    }
+    messages.push(request(request_count + 1, "shutdown", Json::Null));
+    messages.push(notification("exit", Json::Null));
+
+    let mut input = Vec::new();
+    for message in messages {
+        let body = message.to_string();
+        input.extend(format!("Content-Length: {}\r\n\r\n{body}", body.len()).into_bytes());
+    }
+
+    let mut output = Vec::new();
+    run(input.as_slice(), &mut output).unwrap();
+
+    let mut output = output.as_slice();
+    let mut responses = Vec::new();
+    while let Some(message) = read_message(&mut output).unwrap() {
+        responses.push(Json::parse(&message).unwrap());
+    }
+    responses
+}
+
+fn request(id: usize, method: &str, params: Json) -> Json {
+    Json::object([("jsonrpc", "2.0".into()), ("id", id.into()), ("method", method.into()), ("params", params)])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object([("jsonrpc", "2.0".into()), ("method", method.into()), ("params", params)])
+}
+
+/// The params of a request about the given 0-based line and character of `SOURCE`
+fn position(line: usize, character: usize) -> Json {
+    let position = Json::object([("line", line.into()), ("character", character.into())]);
+    Json::object([("textDocument", Json::object([("uri", URI.into())])), ("position", position)])
+}
+
+fn rename(line: usize, character: usize, new_name: &str) -> Json {
+    let Json::Object(mut fields) = position(line, character) else { unreachable!() };
+    fields.push(("newName".to_string(), new_name.into()));
+    Json::Object(fields)
+}
+
+/// The message of the error the server responded to a rename with
+fn rename_error(line: usize, character: usize, new_name: &str) -> String {
+    let messages = session(vec![("textDocument/rename", rename(line, character, new_name))]);
+    let error = response(&messages, 1).get("error").and_then(|error| error.get("message"));
+    error.and_then(Json::as_str).expect("Rename should have failed").to_string()
+}
+
+/// The response to the request with the given id
+fn response(messages: &[Json], id: usize) -> &Json {
+    let id = Json::from(id);
+    let response = messages.iter().find(|message| message.get("id") == Some(&id));
+    response.unwrap_or_else(|| panic!("No response to request {id}"))
+}
+
+fn range(start: (usize, usize), end: (usize, usize)) -> Json {
+    let position =
+        |(line, character): (usize, usize)| Json::object([("line", line.into()), ("character", character.into())]);
+    Json::object([("start", position(start)), ("end", position(end))])
+}
+
+#[test]
+fn initialize_advertises_capabilities() {
+    let messages = session(Vec::new());
+    let capabilities = response(&messages, 0).get("result").and_then(|result| result.get("capabilities"));
+
+    assert_eq!(capabilities.and_then(|capabilities| capabilities.get("hoverProvider")), Some(&Json::Bool(true)));
+    assert_eq!(response(&messages, 1).get("result"), Some(&Json::Null));
+}
+
+#[test]
+fn opening_a_document_publishes_its_diagnostics() {
+    let messages = session(Vec::new());
+    let published = messages
+        .iter()
+        .find(|message| message.get("method").and_then(Json::as_str) == Some("textDocument/publishDiagnostics"));
+
+    let params = published.and_then(|message| message.get("params")).unwrap();
+    assert_eq!(params.get("uri").and_then(Json::as_str), Some(URI));
+    assert_eq!(params.get("diagnostics"), Some(&Json::Array(Vec::new())));
+}
+
+#[test]
+fn hover_shows_the_type_of_a_name() {
+    let messages = session(vec![("textDocument/hover", position(1, 12))]);
+    let result = response(&messages, 1).get("result").unwrap();
+
+    let contents = result.get("contents").and_then(|contents| contents.get("value"));
+    assert_eq!(contents.and_then(Json::as_str), Some("Int -> Int"));
+    assert_eq!(result.get("range"), Some(&range((1, 11), (1, 17))));
+}
+
+#[test]
+fn definition_jumps_to_where_a_name_is_defined() {
+    let messages = session(vec![("textDocument/definition", position(1, 12))]);
+    let result = response(&messages, 1).get("result").unwrap();
+
+    assert_eq!(result.get("uri").and_then(Json::as_str), Some(URI));
+    assert_eq!(result.get("range"), Some(&range((0, 4), (0, 10))));
+}
+
+#[test]
+fn rename_edits_the_definition_and_each_use() {
+    let messages = session(vec![("textDocument/rename", rename(0, 4, "twice"))]);
+    let result = response(&messages, 1).get("result").unwrap();
+
+    let edits = result.get("changes").and_then(|changes| changes.get(URI)).and_then(Json::as_array).unwrap();
+    let ranges: Vec<_> = edits.iter().filter_map(|edit| edit.get("range")).collect();
+    assert_eq!(
+        ranges,
+        [&range((0, 4), (0, 10)), &range((1, 11), (1, 17)), &range((2, 24), (2, 30)), &range((2, 32), (2, 38))]
+    );
+    assert!(edits.iter().all(|edit| edit.get("newText").and_then(Json::as_str) == Some("twice")));
+}
+
+#[test]
+fn rename_rejects_invalid_names() {
+    assert_eq!(rename_error(0, 4, "2x"), "`2x` is not a valid name");
+    assert_eq!(rename_error(0, 4, "if"), "`if` is not a valid name");
+
+    // Parameters can't be capitalized
+    assert_eq!(rename_error(0, 16, "X"), "`X` is not a valid name");
+}
+
+#[test]
+fn rename_rejects_names_which_are_already_defined() {
+    assert_eq!(rename_error(0, 4, "four"), "Cannot rename to `four`, which is already defined");
+}
+
+#[test]
+fn rename_rejects_names_which_would_be_shadowed() {
+    // `double` is used within `quadruple` where `y` is a parameter
+    let expected = "Cannot rename to `y`, this would change which definition this name refers to";
+    assert_eq!(rename_error(0, 4, "y"), expected);
+}
@@ -15,21 +15,36 @@
 //! - Parsing `src/parser/mod.rs`:
 //! - Name Resolution `src/name_resolution/mod.rs`:
 //! - Type Inference `src/type_inference/mod.rs`:
+//! - Pattern Match Checking `src/pattern_matching/mod.rs`: Checks each `match` is exhaustive and has no redundant arms
+//! - Code Generation `src/backend/mod.rs`: Compiles each statement to python or javascript
+//! - Interpretation `src/interpreter/mod.rs`: Runs a program directly instead of compiling it to python
 //!
 //! Non-passes:
+//! - `src/builtins.rs`: The table of built-in operators, functions, and types each pass looks up instead of hard-coding
+//! - `src/lsp/mod.rs`: A language server which keeps a single `Compiler` alive across edits
+//! - `src/repl.rs`: An interactive REPL where each line entered becomes a new in-memory source file
+//! - `src/watch.rs`: Rebuilds the program whenever one of its files changes
+//! - `src/ide/mod.rs`: Queries about a position in a file, like its type or where a name is defined
+//! - `src/cli.rs`: Parsing of command-line arguments
+//! - `src/diagnostics.rs`: Rendering of errors with snippets of the source code they refer to
 //! - `src/errors.rs`: Defines each error used in the program as well as the `Location` struct
+//! - `src/json.rs`: A minimal JSON value type for machine-readable output
 //! - `src/incremental.rs`: Some plumbing for the inc-complete library which also defines
 //!   which functions we're caching the result of.
-use incremental::{set_source_file, CompileFile, Compiler};
+use backend::Target;
+use cli::{Command, MessageFormat, Options};
+use diagnostics::DiagnosticRenderer;
+use incremental::{set_source_file, CheckFile, CompileFile, Compiler};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     fs::File,
     io::{Read, Write},
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
-use crate::errors::Errors;
+use crate::errors::{contains_errors, Errors};
 
 // All the compiler passes:
 // (listed out of order because `cargo fmt` alphabetizes them)
@@ -39,94 +54,258 @@ mod lexer;
 mod name_resolution;
 mod parser;
 mod type_inference;
+mod pattern_matching;
 mod backend;
+mod interpreter;
+
+// Frontends other than the command-line:
+mod ide;
+mod lsp;
+mod repl;
+mod watch;
 
 // Util modules:
+mod builtins;
+mod cli;
+mod diagnostics;
 mod errors;
 mod incremental;
+mod json;
 
-const INPUT_FILE: &str = "input.ex";
 const METADATA_FILE: &str = "incremental_metadata.ron";
 
 // Deserialize the compiler from our metadata file.
 // If we fail, just default to a fresh compiler with no cached compilations.
-fn make_compiler() -> Compiler {
-    match read_file(METADATA_FILE) {
+fn make_compiler(options: &Options) -> Compiler {
+    match read_file(&options.cache_dir.join(METADATA_FILE)) {
         Ok(text) => ron::from_str(&text).unwrap_or_default(),
         Err(_) => Compiler::default(),
     }
 }
 
 fn main() {
-    let mut compiler = make_compiler();
+    let options = cli::parse_args(std::env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("error: {error}\n\n{}", cli::USAGE);
+        std::process::exit(1);
+    });
+
+    let errors = match options.command {
+        Command::Build | Command::Check | Command::Run => compile(&options),
+        Command::Clean => return clean(&options),
+        Command::Lsp => {
+            if let Err(error) = lsp::run(std::io::stdin().lock(), std::io::stdout().lock()) {
+                eprintln!("error: {error}");
+                std::process::exit(1);
+            }
+            return;
+        },
+        Command::Watch => return watch::run(&options),
+        Command::Repl => return repl::run(&options),
+        Command::Help => return println!("{}", cli::USAGE),
+    };
+
+    if contains_errors(&errors) {
+        std::process::exit(1);
+    }
+}
+
+/// Run the compiler on the input file and each file imported from it. Depending on the command,
+/// this will either generate python or javascript code for each file, only check each file for
+/// errors, or check each file then interpret the program if there were no errors.
+fn compile(options: &Options) -> Errors {
+    let mut compiler = make_compiler(options);
+    let human_output = options.message_format == MessageFormat::Human;
 
-    let source = read_file(INPUT_FILE).unwrap_or_else(|error| {
+    // Only diagnostics may be printed to stdout when outputting json. When running the program
+    // we also hide our own output so that it doesn't get mixed up with the program's output.
+    let show_passes = human_output && options.command != Command::Run;
+    incremental::set_quiet(!show_passes);
+
+    let file_name = options.input_file.clone();
+    let source = read_file(Path::new(file_name.as_str())).unwrap_or_else(|error| {
         eprintln!("error: {error}");
         std::process::exit(1);
     });
 
-    let file_name = Arc::new(INPUT_FILE.to_string());
     set_source_file(file_name.clone(), source, &mut compiler);
 
-    println!("Passes Run:");
+    if show_passes {
+        println!("Passes Run:");
+    }
 
     // First, run through our input file and any imports recursively to find any
     // files which have changed. These are the inputs to our incremental compilation
     // and we can't dynamically update our inputs within another query. Instead, we
     // can query to collect them all and update them here at top-level.
-    let (files, mut errors) = find_changed_files::collect_all_changed_files(file_name, &mut compiler);
-    errors.extend(compile_all(files, &mut compiler));
+    let (files, mut errors) = find_changed_files::collect_all_changed_files(file_name.clone(), &mut compiler);
 
-    println!("Compiler finished.\n");
+    if options.command == Command::Build {
+        let compiled = compile_all(files, options, &compiler).unwrap_or_else(|error| {
+            eprintln!("error: {error}");
+            std::process::exit(1);
+        });
+        errors.extend(compiled);
+    } else {
+        errors.extend(check_all(files, &compiler));
+    }
 
-    if !errors.is_empty() {
-        println!("errors:");
+    if show_passes {
+        println!("Compiler finished.\n");
     }
-    for error in errors {
-        println!("  {}", error.message());
+
+    print_errors(&errors, options.message_format, &compiler);
+
+    if let Err(error) = write_metadata(&compiler, &options.cache_dir) {
+        eprintln!("\n{error}");
+    }
+
+    if options.command == Command::Run && !contains_errors(&errors) {
+        let result = interpreter::run_program(file_name, &compiler, std::io::stdout().lock());
+        if let Err(error) = result {
+            eprintln!("error: {error}");
+            std::process::exit(1);
+        }
     }
+    errors
+}
 
-    if let Err(error) = write_metadata(compiler) {
-        println!("\n{error}");
+fn print_errors(errors: &Errors, format: MessageFormat, compiler: &Compiler) {
+    match format {
+        MessageFormat::Human => {
+            let renderer = DiagnosticRenderer::for_stdout(compiler);
+            for error in errors {
+                println!("{}", renderer.render(error));
+            }
+        },
+        MessageFormat::Json => {
+            for error in errors {
+                println!("{}", diagnostics::to_json(error));
+            }
+        },
     }
 }
 
-/// Compile all the files in the set to python files. In a real compiler we may want
+/// Compile all the files in the set to python or javascript files. In a real compiler we may want
 /// to compile each as an independent llvm or cranelift module then link them all
 /// together at the end.
-fn compile_all(files: BTreeSet<Arc<String>>, compiler: &mut Compiler) -> Errors {
-    files.into_par_iter().flat_map(|file| {
-        let output_file = file.replace(".ex", ".py");
-        let (text, errors) = CompileFile { file_name: file }.get(compiler);
+///
+/// Fails without compiling anything if two files would be written to the same output file.
+fn compile_all(files: BTreeSet<Arc<String>>, options: &Options, compiler: &Compiler) -> Result<Errors, String> {
+    check_output_collisions(&files, options)?;
+
+    if let Err(error) = std::fs::create_dir_all(&options.output_dir) {
+        eprintln!("error: Failed to create directory `{}`:\n{error}", options.output_dir.display());
+    }
+
+    let runtime_file = runtime_file(options.target, options);
+    if let Err(msg) = write_file(&runtime_file, options.target.runtime()) {
+        eprintln!("error: {msg}");
+    }
+
+    let errors = files.into_par_iter().flat_map(|file| {
+        let output_file = output_file(&file, options.target, options);
+        let (text, errors) = CompileFile { file_name: file, target: options.target }.get(compiler);
 
         if let Err(msg) = write_file(&output_file, &text) {
             eprintln!("error: {msg}");
         }
         errors
-    }).collect()
+    }).collect();
+    Ok(errors)
 }
 
-fn write_file(file_name: &str, text: &str) -> Result<(), String> {
-    let mut metadata_file =
-        File::create(file_name).map_err(|error| format!("Failed to create file `{file_name}`:\n{error}"))?;
+/// Since each output file is named after only the stem of its source file, files with the same
+/// name in different directories, like `a/foo.ex` and `b/foo.ex`, would overwrite each other.
+/// A file could also overwrite the runtime module if it were named after it.
+fn check_output_collisions(files: &BTreeSet<Arc<String>>, options: &Options) -> Result<(), String> {
+    let mut outputs = BTreeMap::new();
+    for file in files {
+        let output_file = output_file(file, options.target, options);
+        if output_file == runtime_file(options.target, options) {
+            let output_file = output_file.display();
+            return Err(format!("`{file}` would be compiled to the runtime module `{output_file}`, try renaming it"));
+        }
+        if let Some(first) = outputs.insert(output_file.clone(), file) {
+            let output_file = output_file.display();
+            return Err(format!("`{first}` and `{file}` would both be compiled to `{output_file}`, try renaming one"));
+        }
+    }
+    Ok(())
+}
+
+/// Type check all the files in the set without generating any code for them.
+fn check_all(files: BTreeSet<Arc<String>>, compiler: &Compiler) -> Errors {
+    files.into_par_iter().flat_map(|file| CheckFile { file_name: file }.get(compiler)).collect()
+}
+
+/// Delete the incremental metadata file along with the file generated for each target for each
+/// file reachable from the input file, and the runtime module of each target.
+fn clean(options: &Options) {
+    // We need to know which files make up the program to know which output files to remove
+    if let Ok(source) = read_file(Path::new(options.input_file.as_str())) {
+        let mut compiler = make_compiler(options);
+        set_source_file(options.input_file.clone(), source, &mut compiler);
+
+        let (files, _errors) = find_changed_files::collect_all_changed_files(options.input_file.clone(), &mut compiler);
+        // Files generated for any target are removed, not just those for the current one
+        for file in files {
+            for target in Target::ALL {
+                remove_file(&output_file(&file, target, options));
+            }
+        }
+    }
+
+    for target in Target::ALL {
+        remove_file(&runtime_file(target, options));
+    }
+
+    remove_file(&options.cache_dir.join(METADATA_FILE));
+}
+
+/// The path of the file generated for the given source file when compiling to the given target
+fn output_file(file_name: &str, target: Target, options: &Options) -> PathBuf {
+    let extension = target.extension();
+    options.output_dir.join(format!("{}.{extension}", backend::module_name(file_name)))
+}
+
+/// The path the runtime module imported by each generated file is written to for the given target
+fn runtime_file(target: Target, options: &Options) -> PathBuf {
+    options.output_dir.join(format!("{}.{}", backend::RUNTIME_MODULE, target.extension()))
+}
+
+fn remove_file(path: &Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => println!("Removed {}", path.display()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+        Err(error) => eprintln!("error: Failed to remove `{}`:\n{error}", path.display()),
+    }
+}
+
+fn write_file(path: &Path, text: &str) -> Result<(), String> {
+    let file_name = path.display();
+    let mut file = File::create(path).map_err(|error| format!("Failed to create file `{file_name}`:\n{error}"))?;
 
     let text = text.as_bytes();
-    metadata_file.write_all(text).map_err(|error| format!("Failed to write to file `{file_name}`:\n{error}"))
+    file.write_all(text).map_err(|error| format!("Failed to write to file `{file_name}`:\n{error}"))
 }
 
 /// This could be changed so that we only write if the metadata actually
 /// changed but to simplify things we just always write.
-fn write_metadata(compiler: Compiler) -> Result<(), String> {
+fn write_metadata(compiler: &Compiler, cache_dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(cache_dir)
+        .map_err(|error| format!("Failed to create directory `{}`:\n{error}", cache_dir.display()))?;
+
     // Using `to_writer` here would avoid the intermediate step of creating the string
-    let serialized = ron::to_string(&compiler).map_err(|error| format!("Failed to serialize database:\n{error}"))?;
-    write_file(METADATA_FILE, &serialized)
+    let serialized = ron::to_string(compiler).map_err(|error| format!("Failed to serialize database:\n{error}"))?;
+    write_file(&cache_dir.join(METADATA_FILE), &serialized)
 }
 
-fn read_file(file_name: &str) -> Result<String, String> {
-    let mut file = File::open(file_name).map_err(|error| format!("Failed to open `{INPUT_FILE}`:\n{error}"))?;
+fn read_file(path: &Path) -> Result<String, String> {
+    let file_name = path.display();
+    let mut file = File::open(path).map_err(|error| format!("Failed to open `{file_name}`:\n{error}"))?;
 
     let mut text = String::new();
-    file.read_to_string(&mut text).map_err(|error| format!("Failed to read from file `{INPUT_FILE}`:\n{error}"))?;
+    file.read_to_string(&mut text).map_err(|error| format!("Failed to read from file `{file_name}`:\n{error}"))?;
 
     Ok(text)
 }
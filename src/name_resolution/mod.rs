@@ -3,10 +3,11 @@ use std::{collections::BTreeMap, sync::Arc};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    builtins::{Builtin, BuiltinClass, LIST_TYPE},
     errors::{Error, Errors},
-    incremental::{self, CompilerHandle, GetStatement, Resolve, VisibleDefinitions},
+    incremental::{self, CompilerHandle, Definitions, GetStatement, Resolve, VisibleDefinitions},
     parser::{
-        ast::{Expression, TopLevelStatement},
+        ast::{Constraint, Expression, Identifier, InstanceDefinition, Pattern, TopLevelStatement, Type},
         ids::{ExprId, TopLevelId},
     },
 };
@@ -23,8 +24,10 @@ struct Resolver<'local, 'inner> {
     item: TopLevelId,
     links: BTreeMap<ExprId, Origin>,
     errors: Errors,
-    names_in_global_scope: BTreeMap<Arc<String>, TopLevelId>,
-    parameters_in_scope: BTreeMap<Arc<String>, ExprId>,
+    names_in_global_scope: Definitions,
+    /// Each lambda parameter, pattern variable, and let binding in scope. These are kept in the same map
+    /// so that whichever was bound most recently shadows the other.
+    locals_in_scope: BTreeMap<Arc<String>, Origin>,
     compiler: &'local CompilerHandle<'inner>,
 }
 
@@ -35,7 +38,45 @@ pub enum Origin {
     TopLevelDefinition(TopLevelId),
     /// This name is the parameter of this lambda expression.
     /// Remember that all lambdas define only a single parameter.
+    /// Variables bound by a pattern in a `match` are also parameters, identified by the pattern variable.
     Parameter(ExprId),
+    /// This name is defined by the `let` binding with this name
+    LetBinding(ExprId),
+    /// This name is a constructor of the type defined by this `type` statement
+    Constructor(TopLevelId, Arc<String>),
+    /// This name is the type defined by this `type` statement
+    TypeDefinition(TopLevelId),
+    /// This name is the type alias defined by this `type` statement
+    TypeAlias(TopLevelId),
+    /// This name is a method of the class defined by this `class` statement
+    Method(TopLevelId, Arc<String>),
+    /// This name is the class defined by this `class` statement
+    Class(TopLevelId),
+}
+
+impl Origin {
+    /// The top-level statement defining this name, or `None` for local names
+    pub fn top_level_id(&self) -> Option<&TopLevelId> {
+        match self {
+            Origin::TopLevelDefinition(id)
+            | Origin::Constructor(id, _)
+            | Origin::TypeDefinition(id)
+            | Origin::TypeAlias(id)
+            | Origin::Method(id, _)
+            | Origin::Class(id) => Some(id),
+            Origin::Parameter(_) | Origin::LetBinding(_) => None,
+        }
+    }
+}
+
+/// The class a class name in a constraint or instance refers to, or `None` if it failed to resolve.
+/// Like built-in functions, built-in classes have no origin, so these are found by name instead.
+pub fn class_id(name: &Identifier, origins: &BTreeMap<ExprId, Origin>) -> Option<TopLevelId> {
+    match origins.get(&name.id) {
+        Some(Origin::Class(id)) => Some(id.clone()),
+        Some(_) => None,
+        None => BuiltinClass::from_name(&name.name).map(BuiltinClass::id),
+    }
 }
 
 pub fn resolve_impl(context: &Resolve, compiler: &CompilerHandle) -> ResolutionResult {
@@ -51,9 +92,27 @@ pub fn resolve_impl(context: &Resolve, compiler: &CompilerHandle) -> ResolutionR
     let mut resolver = Resolver::new(compiler, context.0.clone(), names_in_scope);
 
     match statement {
-        TopLevelStatement::Import { .. } => (),
-        TopLevelStatement::Definition(definition) => resolver.resolve_expr(&definition.body),
+        TopLevelStatement::Import { names, .. } => resolver.resolve_imported_names(names.iter().flatten()),
+        TopLevelStatement::Definition(definition) => {
+            if let Some(typ) = &definition.typ {
+                resolver.resolve_type(typ, definition.forall.as_deref());
+                resolver.resolve_constraints(&definition.constraints, &typ.generics());
+            }
+            resolver.resolve_expr(&definition.body);
+        },
         TopLevelStatement::Print(expression, _) => resolver.resolve_expr(&expression),
+        TopLevelStatement::TypeDefinition(definition) => {
+            for field in definition.constructors.iter().flat_map(|constructor| &constructor.fields) {
+                resolver.resolve_type(field, Some(&definition.generics));
+            }
+        },
+        TopLevelStatement::TypeAlias(alias) => resolver.resolve_type(&alias.typ, Some(&alias.generics)),
+        TopLevelStatement::Class(class) => {
+            for (_, typ) in class.methods.iter() {
+                resolver.resolve_type(typ, None);
+            }
+        },
+        TopLevelStatement::Instance(instance) => resolver.resolve_instance(&instance),
     }
 
     incremental::exit_query();
@@ -62,8 +121,7 @@ pub fn resolve_impl(context: &Resolve, compiler: &CompilerHandle) -> ResolutionR
 
 impl<'local, 'inner> Resolver<'local, 'inner> {
     fn new(
-        compiler: &'local CompilerHandle<'inner>, item: TopLevelId,
-        names_in_scope: BTreeMap<Arc<String>, TopLevelId>,
+        compiler: &'local CompilerHandle<'inner>, item: TopLevelId, names_in_scope: Definitions,
     ) -> Self {
         Self {
             compiler,
@@ -71,7 +129,7 @@ impl<'local, 'inner> Resolver<'local, 'inner> {
             names_in_global_scope: names_in_scope,
             links: Default::default(),
             errors: Vec::new(),
-            parameters_in_scope: Default::default(),
+            locals_in_scope: Default::default(),
         }
     }
 
@@ -80,21 +138,18 @@ impl<'local, 'inner> Resolver<'local, 'inner> {
     }
 
     fn lookup(&self, name: &Arc<String>) -> Option<Origin> {
-        // Check local parameters first. They shadow global definitions
-        if let Some(expr) = self.parameters_in_scope.get(name) {
-            return Some(Origin::Parameter(*expr));
-        }
-        if let Some(statement) = self.names_in_global_scope.get(name) {
-            return Some(Origin::TopLevelDefinition(statement.clone()));
+        // Check local parameters and let bindings first. They shadow global definitions
+        if let Some(origin) = self.locals_in_scope.get(name) {
+            return Some(origin.clone());
         }
-        None
+        self.names_in_global_scope.values.get(name).cloned()
     }
 
     fn link(&mut self, name: &Arc<String>, expr: ExprId) {
-        if name.as_ref() == "+" || name.as_ref() == "-" {
-            // Ignore built-ins
-        } else if let Some(origin) = self.lookup(name) {
+        if let Some(origin) = self.lookup(name) {
             self.links.insert(expr, origin);
+        } else if Builtin::from_name(name).is_some() {
+            // Built-ins are never defined in the program, so they have no origin
         } else {
             let location = expr.location(&self.item, self.compiler);
             self.errors.push(Error::NameNotInScope { name: name.clone(), location });
@@ -103,25 +158,330 @@ impl<'local, 'inner> Resolver<'local, 'inner> {
 
     fn resolve_expr(&mut self, expression: &Expression) {
         match expression {
-            Expression::IntegerLiteral(..) => (),
+            Expression::IntegerLiteral(..)
+            | Expression::BoolLiteral(..)
+            | Expression::StringLiteral(..)
+            | Expression::Hole { .. } => (),
             Expression::Variable(identifier) => self.link(&identifier.name, identifier.id),
             Expression::FunctionCall { function, argument, id: _ } => {
-                self.resolve_expr(&function);
-                self.resolve_expr(&argument);
+                self.resolve_expr(function);
+                self.resolve_expr(argument);
+            },
+            Expression::Lambda { parameter, body, id: _ } => self.resolve_in_pattern_scope(parameter, body),
+            Expression::If { condition, then_branch, else_branch, id: _ } => {
+                self.resolve_expr(condition);
+                self.resolve_expr(then_branch);
+                self.resolve_expr(else_branch);
+            },
+            Expression::Let { name, value, body, id: _ } => {
+                // Let bindings aren't recursive, so the name is only in scope within the body
+                self.resolve_expr(value);
+                self.resolve_in_scope(&name.name, Origin::LetBinding(name.id), body);
+            },
+            Expression::Match { scrutinee, arms, id: _ } => {
+                self.resolve_expr(scrutinee);
+                for arm in arms {
+                    self.resolve_in_pattern_scope(&arm.pattern, &arm.body);
+                }
+            },
+            Expression::Tuple { elements, id: _ } | Expression::List { elements, id: _ } => {
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            },
+            Expression::Record { fields, id: _ } => {
+                self.check_duplicate_fields(fields.iter().map(|(name, _)| name));
+                for (_, value) in fields {
+                    self.resolve_expr(value);
+                }
+            },
+            // Which field is accessed depends on the type of the record, so it's left to type inference
+            Expression::FieldAccess { record, .. } => self.resolve_expr(record),
+        }
+    }
+
+    /// Link the class of an instance along with each of its methods, and resolve the body of each method.
+    /// Each method of the class must be defined exactly once.
+    fn resolve_instance(&mut self, instance: &InstanceDefinition) {
+        self.resolve_type(&instance.typ, None);
+        self.check_instance_type(instance);
+        self.resolve_constraints(&instance.constraints, &instance.typ.generics());
+
+        let class = self.resolve_class(&instance.class);
+        let builtin_class = class.as_ref().and_then(BuiltinClass::from_id);
+        let class_methods = match (&class, builtin_class) {
+            (_, Some(builtin)) => builtin.methods().map(|method| Arc::new(method.method_name().to_string())).collect(),
+            (Some(id), None) => match GetStatement(id.clone()).get(self.compiler) {
+                TopLevelStatement::Class(class) => class.methods.into_iter().map(|(name, _)| name.name).collect(),
+                _ => unreachable!("Class origins should refer to a class"),
+            },
+            (None, None) => Vec::new(),
+        };
+
+        let mut defined: Vec<&Identifier> = Vec::new();
+        for (name, body) in instance.methods.iter() {
+            if let Some(existing) = defined.iter().find(|existing| existing.name == name.name) {
+                let first_location = existing.id.location(&self.item, self.compiler);
+                let second_location = name.id.location(&self.item, self.compiler);
+                let name = name.name.clone();
+                self.errors.push(Error::NameAlreadyInScope { name, first_location, second_location });
+            } else {
+                defined.push(name);
+            }
+
+            if let Some(class_id) = &class {
+                if class_methods.contains(&name.name) {
+                    // Like the built-in classes themselves, their methods have no origin
+                    if builtin_class.is_none() {
+                        self.links.insert(name.id, Origin::Method(class_id.clone(), name.name.clone()));
+                    }
+                } else {
+                    let location = name.id.location(&self.item, self.compiler);
+                    let (name, class) = (name.name.clone(), instance.class.name.clone());
+                    self.errors.push(Error::NotAMethod { name, class, location });
+                }
+            }
+            self.resolve_expr(body);
+        }
+
+        for method in class_methods {
+            if !defined.iter().any(|defined| defined.name == method) {
+                let location = instance.class.id.location(&self.item, self.compiler);
+                let class = instance.class.name.clone();
+                self.errors.push(Error::MissingMethod { name: method, class, location });
+            }
+        }
+    }
+
+    /// Instances are chosen by the outermost type constructor of the type they're used at, so an
+    /// instance must be for a type applied only to distinct generics, like `Option a` or `(a, b)`.
+    /// Type aliases are not allowed either since they may stand for any type.
+    fn check_instance_type(&mut self, instance: &InstanceDefinition) {
+        let arguments = match &instance.typ {
+            Type::Int | Type::Bool | Type::String => Some(Vec::new()),
+            Type::Named { name, .. } if matches!(self.links.get(&name.id), Some(Origin::TypeAlias(_))) => None,
+            Type::Named { arguments, .. } | Type::Tuple(arguments) => Some(arguments.iter().collect()),
+            Type::Function { parameter, return_type } => Some(vec![parameter.as_ref(), return_type.as_ref()]),
+            Type::Generic(_) | Type::Record { .. } => None,
+        };
+
+        let mut generics = Vec::new();
+        let mut valid = arguments.is_some();
+        for argument in arguments.into_iter().flatten() {
+            match argument {
+                Type::Generic(generic) if !generics.contains(&&generic.name) => generics.push(&generic.name),
+                _ => valid = false,
+            }
+        }
+
+        if !valid {
+            let location = instance.class.id.location(&self.item, self.compiler);
+            self.errors.push(Error::InvalidInstanceType { location });
+        }
+    }
+
+    /// Link the class of each constraint. The generic of each must be one of `generics`, the
+    /// generics of the type the constraints are on.
+    fn resolve_constraints(&mut self, constraints: &[Constraint], generics: &[Identifier]) {
+        for constraint in constraints {
+            self.resolve_class(&constraint.class);
+            self.resolve_generic(&constraint.generic, Some(generics));
+        }
+    }
+
+    /// Link the name of a class, returning the `class` statement defining it. Built-in classes are
+    /// only used when no class of the same name is in scope, and are left unlinked like other built-ins.
+    fn resolve_class(&mut self, name: &Identifier) -> Option<TopLevelId> {
+        if let Some(id) = self.names_in_global_scope.classes.get(&name.name) {
+            self.links.insert(name.id, Origin::Class(id.clone()));
+            Some(id.clone())
+        } else if let Some(class) = BuiltinClass::from_name(&name.name) {
+            Some(class.id())
+        } else {
+            let location = name.id.location(&self.item, self.compiler);
+            self.errors.push(Error::NameNotInScope { name: name.name.clone(), location });
+            None
+        }
+    }
+
+    /// A record may only give each field once
+    fn check_duplicate_fields<'a>(&mut self, fields: impl Iterator<Item = &'a Identifier>) {
+        let mut seen: Vec<&Identifier> = Vec::new();
+        for field in fields {
+            if let Some(existing) = seen.iter().find(|existing| existing.name == field.name) {
+                let first_location = existing.id.location(&self.item, self.compiler);
+                let second_location = field.id.location(&self.item, self.compiler);
+                let name = field.name.clone();
+                self.errors.push(Error::DuplicateField { name, first_location, second_location });
+            } else {
+                seen.push(field);
+            }
+        }
+    }
+
+    /// Resolve an expression with the given local name in scope
+    fn resolve_in_scope(&mut self, name: &Arc<String>, origin: Origin, expression: &Expression) {
+        let old_origin = self.locals_in_scope.insert(name.clone(), origin);
+        self.resolve_expr(expression);
+        self.remove_from_scope(name, old_origin);
+    }
+
+    /// Remember to either remove the name from scope, or if we shadowed
+    /// an existing name, then re-insert that one.
+    fn remove_from_scope(&mut self, name: &Arc<String>, old_origin: Option<Origin>) {
+        if let Some(old_origin) = old_origin {
+            self.locals_in_scope.insert(name.clone(), old_origin);
+        } else {
+            self.locals_in_scope.remove(name);
+        }
+    }
+
+    /// Resolve the pattern of a match arm or lambda parameter. Each variable in the pattern is in
+    /// scope within `body`.
+    fn resolve_in_pattern_scope(&mut self, pattern: &Pattern, body: &Expression) {
+        let mut variables = Vec::new();
+        self.resolve_pattern(pattern, &mut variables);
+
+        let old_origins: Vec<_> = variables
+            .iter()
+            .map(|variable| self.locals_in_scope.insert(variable.name.clone(), Origin::Parameter(variable.id)))
+            .collect();
+
+        self.resolve_expr(body);
+
+        // Restore these in reverse in case the same name was bound twice
+        for (variable, old_origin) in variables.iter().zip(old_origins).rev() {
+            self.remove_from_scope(&variable.name, old_origin);
+        }
+    }
+
+    /// Link each constructor in a pattern and collect the variables it binds. A variable may
+    /// only be bound once per pattern since it wouldn't be clear which value it refers to.
+    fn resolve_pattern<'a>(&mut self, pattern: &'a Pattern, variables: &mut Vec<&'a Identifier>) {
+        match pattern {
+            Pattern::Wildcard(_) | Pattern::IntegerLiteral(..) | Pattern::BoolLiteral(..) => (),
+            Pattern::Variable(variable) => {
+                if let Some(existing) = variables.iter().find(|existing| existing.name == variable.name) {
+                    let first_location = existing.id.location(&self.item, self.compiler);
+                    let second_location = variable.id.location(&self.item, self.compiler);
+                    let name = variable.name.clone();
+                    self.errors.push(Error::NameAlreadyInScope { name, first_location, second_location });
+                } else {
+                    variables.push(variable);
+                }
             },
-            Expression::Lambda { parameter_name, body, id: _ } => {
-                // Resolve body with the parameter name in scope
-                let old_name = self.parameters_in_scope.insert(parameter_name.name.clone(), parameter_name.id);
-                self.resolve_expr(&body);
-
-                // Then remember to either remove the parameter name from scope, or if we shadowed
-                // an existing name, then re-insert that one.
-                if let Some(old_name) = old_name {
-                    self.parameters_in_scope.insert(parameter_name.name.clone(), old_name);
+            Pattern::Constructor { name, arguments, id: _ } => {
+                match self.names_in_global_scope.values.get(&name.name) {
+                    Some(origin @ Origin::Constructor(..)) => {
+                        self.links.insert(name.id, origin.clone());
+                    },
+                    _ => {
+                        let location = name.id.location(&self.item, self.compiler);
+                        self.errors.push(Error::NameNotInScope { name: name.name.clone(), location });
+                    },
+                }
+
+                for argument in arguments {
+                    self.resolve_pattern(argument, variables);
+                }
+            },
+            Pattern::Tuple { elements, id: _ } => {
+                for element in elements {
+                    self.resolve_pattern(element, variables);
+                }
+            },
+        }
+    }
+
+    /// The origin of a type name defined by the given `type` statement, along with its number of generics
+    fn type_origin(&self, id: TopLevelId) -> (Origin, usize) {
+        match GetStatement(id.clone()).get(self.compiler) {
+            TopLevelStatement::TypeDefinition(definition) => (Origin::TypeDefinition(id), definition.generics.len()),
+            TopLevelStatement::TypeAlias(alias) => (Origin::TypeAlias(id), alias.generics.len()),
+            _ => unreachable!("Type names are only defined by `type` statements"),
+        }
+    }
+
+    /// Link each name listed by an import so they're included when finding references or renaming.
+    /// A name which is both a type and a value is linked to the value. Names the imported file doesn't
+    /// define are reported by `VisibleDefinitions` instead.
+    fn resolve_imported_names<'a>(&mut self, names: impl Iterator<Item = &'a Identifier>) {
+        for name in names {
+            let scope = &self.names_in_global_scope;
+            let origin = if let Some(origin) = scope.values.get(&name.name) {
+                origin.clone()
+            } else if let Some(id) = scope.types.get(&name.name) {
+                self.type_origin(id.clone()).0
+            } else if let Some(id) = scope.classes.get(&name.name) {
+                Origin::Class(id.clone())
+            } else {
+                continue;
+            };
+            self.links.insert(name.id, origin);
+        }
+    }
+
+    /// Link each type name used in a type. If `generics` is given, these are the only generics
+    /// allowed in the type, as is the case for the fields of a type definition or an annotation
+    /// with an explicit `forall`. Otherwise any generic may be used, as in other type annotations.
+    fn resolve_type(&mut self, typ: &Type, generics: Option<&[Identifier]>) {
+        match typ {
+            Type::Int | Type::Bool | Type::String => (),
+            Type::Generic(generic) => self.resolve_generic(generic, generics),
+            Type::Named { name, arguments } => {
+                if let Some(id) = self.names_in_global_scope.types.get(&name.name).cloned() {
+                    let (origin, expected) = self.type_origin(id);
+                    self.check_type_argument_count(name, expected, arguments.len());
+                    self.links.insert(name.id, origin);
+                } else if name.name.as_str() == LIST_TYPE {
+                    // Like built-in functions, the built-in list type has no origin
+                    self.check_type_argument_count(name, 1, arguments.len());
                 } else {
-                    self.parameters_in_scope.remove(&parameter_name.name);
+                    let location = name.id.location(&self.item, self.compiler);
+                    self.errors.push(Error::NameNotInScope { name: name.name.clone(), location });
+                }
+
+                for argument in arguments {
+                    self.resolve_type(argument, generics);
+                }
+            },
+            Type::Function { parameter, return_type } => {
+                self.resolve_type(parameter, generics);
+                self.resolve_type(return_type, generics);
+            },
+            Type::Tuple(elements) => {
+                for element in elements {
+                    self.resolve_type(element, generics);
+                }
+            },
+            Type::Record { fields, rest } => {
+                self.check_duplicate_fields(fields.iter().map(|(name, _)| name));
+                for (_, typ) in fields {
+                    self.resolve_type(typ, generics);
+                }
+                if let Some(rest) = rest {
+                    self.resolve_generic(rest, generics);
                 }
             },
         }
     }
+
+    fn resolve_generic(&mut self, generic: &Identifier, generics: Option<&[Identifier]>) {
+        if let Some(generics) = generics
+            && !generics.iter().any(|declared| declared.name == generic.name)
+        {
+            let location = generic.id.location(&self.item, self.compiler);
+            self.errors.push(Error::NameNotInScope { name: generic.name.clone(), location });
+        }
+    }
+
+    /// Each type must be given exactly one argument per generic of its definition. We don't
+    /// support partially applied types like `Option` on its own.
+    fn check_type_argument_count(&mut self, name: &Identifier, expected: usize, actual: usize) {
+        if expected != actual {
+            let location = name.id.location(&self.item, self.compiler);
+            let name = name.name.clone();
+            self.errors.push(Error::TypeArgumentCount { name, expected, actual, location });
+        }
+    }
 }
@@ -13,9 +13,15 @@ pub struct Program {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TopLevelStatement {
-    Import { file_name: Identifier, id: TopLevelId },
+    /// `import foo` imports every name `foo` defines while `import foo (a, B)` only imports the names listed.
+    /// Instances are imported either way since they have no name to list.
+    Import { file_name: Identifier, names: Option<Vec<Identifier>>, id: TopLevelId },
     Definition(Definition),
     Print(Arc<Expression>, TopLevelId),
+    TypeDefinition(TypeDefinition),
+    TypeAlias(TypeAlias),
+    Class(ClassDefinition),
+    Instance(InstanceDefinition),
 }
 
 impl TopLevelStatement {
@@ -24,6 +30,10 @@ impl TopLevelStatement {
             TopLevelStatement::Import { id, .. } => id,
             TopLevelStatement::Definition(definition) => &definition.id,
             TopLevelStatement::Print(_, id) => id,
+            TopLevelStatement::TypeDefinition(definition) => &definition.id,
+            TopLevelStatement::TypeAlias(alias) => &alias.id,
+            TopLevelStatement::Class(class) => &class.id,
+            TopLevelStatement::Instance(instance) => &instance.id,
         }
     }
 }
@@ -37,33 +47,192 @@ pub struct Identifier {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Definition {
     pub name: Identifier,
+    /// The generics of the type annotation if they are quantified explicitly, e.g. `a` in
+    /// `def f: forall a. a -> a`. Otherwise each generic used in the annotation is quantified implicitly.
+    pub forall: Option<Vec<Identifier>>,
+    /// The constraints on the generics of the type annotation, e.g. `Show a` in `def f: Show a => a -> String`
+    pub constraints: Vec<Constraint>,
     pub typ: Option<Type>,
     pub body: Arc<Expression>,
     pub id: TopLevelId,
 }
 
+/// `type Option a = None | Some a`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeDefinition {
+    pub name: Identifier,
+    pub generics: Vec<Identifier>,
+    pub constructors: Vec<Constructor>,
+    pub id: TopLevelId,
+}
+
+/// `type Pair a = (a, a)`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeAlias {
+    pub name: Identifier,
+    pub generics: Vec<Identifier>,
+    pub typ: Type,
+    pub id: TopLevelId,
+}
+
+/// `class Show a { show: a -> String }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassDefinition {
+    pub name: Identifier,
+    /// The type the class is over. Each method's type is expected to mention it.
+    pub generic: Identifier,
+    pub methods: Vec<(Identifier, Type)>,
+    pub id: TopLevelId,
+}
+
+/// `instance Show a => Show (List a) { show = fn xs -> ... }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceDefinition {
+    /// The constraints the instance needs to hold on the generics of its type, e.g. `Show a`
+    pub constraints: Vec<Constraint>,
+    pub class: Identifier,
+    pub typ: Type,
+    pub methods: Vec<(Identifier, Arc<Expression>)>,
+    pub id: TopLevelId,
+}
+
+/// A class applied to a generic, e.g. `Show a`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Constraint {
+    pub class: Identifier,
+    pub generic: Identifier,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Constructor {
+    pub name: Identifier,
+    pub fields: Vec<Type>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Expression {
     IntegerLiteral(i64, ExprId),
+    BoolLiteral(bool, ExprId),
+    StringLiteral(Arc<String>, ExprId),
     Variable(Identifier),
     FunctionCall { function: Arc<Expression>, argument: Arc<Expression>, id: ExprId },
-    Lambda { parameter_name: Identifier, body: Arc<Expression>, id: ExprId },
+    Lambda { parameter: Pattern, body: Arc<Expression>, id: ExprId },
+    If { condition: Arc<Expression>, then_branch: Arc<Expression>, else_branch: Arc<Expression>, id: ExprId },
+    Let { name: Identifier, value: Arc<Expression>, body: Arc<Expression>, id: ExprId },
+    Match { scrutinee: Arc<Expression>, arms: Vec<MatchArm>, id: ExprId },
+    Tuple { elements: Vec<Arc<Expression>>, id: ExprId },
+    List { elements: Vec<Arc<Expression>>, id: ExprId },
+    Record { fields: Vec<(Identifier, Arc<Expression>)>, id: ExprId },
+    FieldAccess { record: Arc<Expression>, field: Identifier, id: ExprId },
+    Hole { name: Option<Arc<String>>, id: ExprId },
 }
 
 impl Expression {
     pub fn id(&self) -> ExprId {
         match self {
             Expression::IntegerLiteral(_, id) => *id,
+            Expression::BoolLiteral(_, id) => *id,
+            Expression::StringLiteral(_, id) => *id,
             Expression::Variable(identifier) => identifier.id,
             Expression::FunctionCall { id, .. } => *id,
             Expression::Lambda { id, .. } => *id,
+            Expression::If { id, .. } => *id,
+            Expression::Let { id, .. } => *id,
+            Expression::Match { id, .. } => *id,
+            Expression::Tuple { id, .. } => *id,
+            Expression::List { id, .. } => *id,
+            Expression::Record { id, .. } => *id,
+            Expression::FieldAccess { id, .. } => *id,
+            Expression::Hole { id, .. } => *id,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Arc<Expression>,
+}
+
+/// A pattern in a match arm or lambda parameter. Uses of a variable bound by a pattern resolve
+/// to `Origin::Parameter`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Pattern {
+    Wildcard(ExprId),
+    Variable(Identifier),
+    IntegerLiteral(i64, ExprId),
+    BoolLiteral(bool, ExprId),
+    Constructor { name: Identifier, arguments: Vec<Pattern>, id: ExprId },
+    Tuple { elements: Vec<Pattern>, id: ExprId },
+}
+
+impl Pattern {
+    /// Each variable bound by this pattern, from left to right
+    pub fn variables(&self) -> Vec<&Identifier> {
+        match self {
+            Pattern::Variable(identifier) => vec![identifier],
+            Pattern::Constructor { arguments: patterns, .. } | Pattern::Tuple { elements: patterns, .. } => {
+                patterns.iter().flat_map(Pattern::variables).collect()
+            },
+            Pattern::Wildcard(_) | Pattern::IntegerLiteral(..) | Pattern::BoolLiteral(..) => Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> ExprId {
+        match self {
+            Pattern::Wildcard(id) => *id,
+            Pattern::Variable(identifier) => identifier.id,
+            Pattern::IntegerLiteral(_, id) => *id,
+            Pattern::BoolLiteral(_, id) => *id,
+            Pattern::Constructor { id, .. } => *id,
+            Pattern::Tuple { id, .. } => *id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Type {
     Int,
+    Bool,
+    String,
     Generic(Identifier),
+    Named { name: Identifier, arguments: Vec<Type> },
+    Tuple(Vec<Type>),
+    Record { fields: Vec<(Identifier, Type)>, rest: Option<Identifier> },
     Function { parameter: Arc<Type>, return_type: Arc<Type> },
 }
+
+impl Type {
+    /// Each distinct generic used in this type, in the order they first appear
+    pub fn generics(&self) -> Vec<Identifier> {
+        let mut generics = Vec::new();
+        self.collect_generics(&mut generics);
+        generics
+    }
+
+    fn collect_generics(&self, generics: &mut Vec<Identifier>) {
+        let add = |generics: &mut Vec<Identifier>, generic: &Identifier| {
+            if !generics.iter().any(|existing| existing.name == generic.name) {
+                generics.push(generic.clone());
+            }
+        };
+
+        match self {
+            Type::Int | Type::Bool | Type::String => (),
+            Type::Generic(generic) => add(generics, generic),
+            Type::Named { arguments: types, .. } | Type::Tuple(types) => {
+                types.iter().for_each(|typ| typ.collect_generics(generics));
+            },
+            Type::Record { fields, rest } => {
+                fields.iter().for_each(|(_, typ)| typ.collect_generics(generics));
+                if let Some(rest) = rest {
+                    add(generics, rest);
+                }
+            },
+            Type::Function { parameter, return_type } => {
+                parameter.collect_generics(generics);
+                return_type.collect_generics(generics);
+            },
+        }
+    }
+}
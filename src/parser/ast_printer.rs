@@ -1,4 +1,9 @@
-use super::ast::{Definition, Expression, Identifier, Program, TopLevelStatement, Type};
+use crate::lexer::escape;
+
+use super::ast::{
+    ClassDefinition, Constraint, Definition, Expression, Identifier, InstanceDefinition, Pattern, Program,
+    TopLevelStatement, Type, TypeAlias, TypeDefinition,
+};
 
 impl std::fmt::Display for Program {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -15,8 +20,13 @@ impl std::fmt::Display for Program {
 impl std::fmt::Display for TopLevelStatement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            TopLevelStatement::Import { file_name, id: _ } => {
-                write!(f, "import {file_name}")
+            TopLevelStatement::Import { file_name, names, id: _ } => {
+                write!(f, "import {file_name}")?;
+                if let Some(names) = names {
+                    let names: Vec<_> = names.iter().map(ToString::to_string).collect();
+                    write!(f, " ({})", names.join(", "))?;
+                }
+                Ok(())
             },
             TopLevelStatement::Definition(definition) => {
                 write!(f, "{definition}")
@@ -24,6 +34,18 @@ impl std::fmt::Display for TopLevelStatement {
             TopLevelStatement::Print(expression, _id) => {
                 write!(f, "print {expression}")
             },
+            TopLevelStatement::TypeDefinition(definition) => {
+                write!(f, "{definition}")
+            },
+            TopLevelStatement::TypeAlias(alias) => {
+                write!(f, "{alias}")
+            },
+            TopLevelStatement::Class(class) => {
+                write!(f, "{class}")
+            },
+            TopLevelStatement::Instance(instance) => {
+                write!(f, "{instance}")
+            },
         }
     }
 }
@@ -38,32 +60,232 @@ impl std::fmt::Display for Definition {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "def {}", self.name)?;
         if let Some(typ) = self.typ.as_ref() {
-            write!(f, ": {typ}")?;
+            write!(f, ": ")?;
+            if let Some(generics) = &self.forall {
+                write!(f, "forall")?;
+                for generic in generics {
+                    write!(f, " {generic}")?;
+                }
+                write!(f, ". ")?;
+            }
+            write_constraints(f, &self.constraints)?;
+            write!(f, "{typ}")?;
         }
 
         write!(f, " = {}", self.body)
     }
 }
 
+impl std::fmt::Display for TypeDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "type {}", self.name)?;
+        for generic in self.generics.iter() {
+            write!(f, " {generic}")?;
+        }
+
+        for (i, constructor) in self.constructors.iter().enumerate() {
+            let separator = if i == 0 { "=" } else { "|" };
+            write!(f, " {separator} {}", constructor.name)?;
+
+            for field in constructor.fields.iter() {
+                if is_atomic_type(field) { write!(f, " {field}")? } else { write!(f, " ({field})")? }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for TypeAlias {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "type {}", self.name)?;
+        for generic in self.generics.iter() {
+            write!(f, " {generic}")?;
+        }
+
+        // A named type on its own would be parsed as a constructor instead
+        match &self.typ {
+            Type::Named { .. } => write!(f, " = ({})", self.typ),
+            typ => write!(f, " = {typ}"),
+        }
+    }
+}
+
+impl std::fmt::Display for ClassDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "class {} {} {{", self.name, self.generic)?;
+        for (i, (name, typ)) in self.methods.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(f, "{separator} {name}: {typ}")?;
+        }
+        if self.methods.is_empty() { write!(f, "}}") } else { write!(f, " }}") }
+    }
+}
+
+impl std::fmt::Display for InstanceDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "instance ")?;
+        write_constraints(f, &self.constraints)?;
+
+        if is_atomic_type(&self.typ) {
+            write!(f, "{} {} {{", self.class, self.typ)?;
+        } else {
+            write!(f, "{} ({}) {{", self.class, self.typ)?;
+        }
+
+        for (i, (name, body)) in self.methods.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(f, "{separator} {name} = {body}")?;
+        }
+        if self.methods.is_empty() { write!(f, "}}") } else { write!(f, " }}") }
+    }
+}
+
+impl std::fmt::Display for Constraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {}", self.class, self.generic)
+    }
+}
+
+/// Write the constraints before the `=>` of a type annotation or instance, if there are any
+fn write_constraints(f: &mut std::fmt::Formatter, constraints: &[Constraint]) -> std::fmt::Result {
+    match constraints {
+        [] => Ok(()),
+        [constraint] => write!(f, "{constraint} => "),
+        constraints => {
+            write!(f, "(")?;
+            write_comma_separated(f, constraints)?;
+            write!(f, ") => ")
+        },
+    }
+}
+
+/// Types which never need parentheses when used as an argument to another type
+fn is_atomic_type(typ: &Type) -> bool {
+    match typ {
+        Type::Int | Type::Bool | Type::String | Type::Generic(_) | Type::Tuple(_) | Type::Record { .. } => true,
+        Type::Named { arguments, .. } => arguments.is_empty(),
+        Type::Function { .. } => false,
+    }
+}
+
 impl std::fmt::Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // Expressions ending in another expression would otherwise extend over the rest of the input
+        let extends_right = |expr: &Expression| {
+            matches!(
+                expr,
+                Expression::Lambda { .. } | Expression::If { .. } | Expression::Let { .. } | Expression::Match { .. }
+            )
+        };
         let should_parenthesize =
-            |expr: &Expression| matches!(expr, Expression::FunctionCall { .. } | Expression::Lambda { .. });
+            |expr: &Expression| matches!(expr, Expression::FunctionCall { .. }) || extends_right(expr);
 
         match self {
             Expression::IntegerLiteral(x, _id) => write!(f, "{x}"),
+            Expression::BoolLiteral(b, _id) => write!(f, "{b}"),
+            Expression::StringLiteral(s, _id) => write!(f, "\"{}\"", escape(s)),
             Expression::Variable(identifier) => write!(f, "{identifier}"),
             Expression::FunctionCall { function, argument, id: _ } => {
-                if matches!(function.as_ref(), Expression::Lambda { .. }) {
+                if extends_right(function) {
                     write!(f, "({function})")?;
                 } else {
                     write!(f, "{function}")?;
                 }
 
-                if should_parenthesize(&argument) { write!(f, " ({argument})") } else { write!(f, " {argument}") }
+                if should_parenthesize(argument) { write!(f, " ({argument})") } else { write!(f, " {argument}") }
+            },
+            Expression::Lambda { parameter, body, id: _ } => {
+                if is_atomic_pattern(parameter) {
+                    write!(f, "fn {parameter} -> {body}")
+                } else {
+                    write!(f, "fn ({parameter}) -> {body}")
+                }
+            },
+            Expression::If { condition, then_branch, else_branch, id: _ } => {
+                write!(f, "if {condition} then {then_branch} else {else_branch}")
             },
-            Expression::Lambda { parameter_name, body, id: _ } => {
-                write!(f, "fn {parameter_name} -> {body}")
+            Expression::Let { name, value, body, id: _ } => {
+                write!(f, "let {name} = {value} in {body}")
+            },
+            Expression::Match { scrutinee, arms, id: _ } => {
+                write!(f, "match {scrutinee} with")?;
+                for arm in arms {
+                    // Arms other than the last would otherwise extend over the arms after them
+                    if extends_right(&arm.body) {
+                        write!(f, " | {} -> ({})", arm.pattern, arm.body)?;
+                    } else {
+                        write!(f, " | {} -> {}", arm.pattern, arm.body)?;
+                    }
+                }
+                Ok(())
+            },
+            Expression::Tuple { elements, id: _ } => {
+                write!(f, "(")?;
+                write_comma_separated(f, elements)?;
+                write!(f, ")")
+            },
+            Expression::List { elements, id: _ } => {
+                write!(f, "[")?;
+                write_comma_separated(f, elements)?;
+                write!(f, "]")
+            },
+            Expression::Record { fields, id: _ } if fields.is_empty() => write!(f, "{{}}"),
+            Expression::Record { fields, id: _ } => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(f, "{separator} {name} = {value}")?;
+                }
+                write!(f, " }}")
+            },
+            Expression::FieldAccess { record, field, id: _ } => {
+                if should_parenthesize(record) {
+                    write!(f, "({record}).{field}")
+                } else {
+                    write!(f, "{record}.{field}")
+                }
+            },
+            Expression::Hole { name: Some(name), id: _ } => write!(f, "?{name}"),
+            Expression::Hole { name: None, id: _ } => write!(f, "_"),
+        }
+    }
+}
+
+fn write_comma_separated(
+    f: &mut std::fmt::Formatter, items: impl IntoIterator<Item = impl std::fmt::Display>,
+) -> std::fmt::Result {
+    for (i, item) in items.into_iter().enumerate() {
+        if i != 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+    Ok(())
+}
+
+/// Patterns which never need parentheses when used as an argument or lambda parameter
+fn is_atomic_pattern(pattern: &Pattern) -> bool {
+    !matches!(pattern, Pattern::Constructor { arguments, .. } if !arguments.is_empty())
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Pattern::Wildcard(_) => write!(f, "_"),
+            Pattern::Variable(identifier) => write!(f, "{identifier}"),
+            Pattern::IntegerLiteral(x, _) => write!(f, "{x}"),
+            Pattern::BoolLiteral(b, _) => write!(f, "{b}"),
+            Pattern::Constructor { name, arguments, id: _ } => {
+                write!(f, "{name}")?;
+                for argument in arguments {
+                    if is_atomic_pattern(argument) { write!(f, " {argument}")? } else { write!(f, " ({argument})")? }
+                }
+                Ok(())
+            },
+            Pattern::Tuple { elements, id: _ } => {
+                write!(f, "(")?;
+                write_comma_separated(f, elements)?;
+                write!(f, ")")
             },
         }
     }
@@ -73,7 +295,33 @@ impl std::fmt::Display for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Type::Int => write!(f, "Int"),
+            Type::Bool => write!(f, "Bool"),
+            Type::String => write!(f, "String"),
             Type::Generic(identifier) => write!(f, "{identifier}"),
+            Type::Named { name, arguments } => {
+                write!(f, "{name}")?;
+                for argument in arguments {
+                    if is_atomic_type(argument) { write!(f, " {argument}")? } else { write!(f, " ({argument})")? }
+                }
+                Ok(())
+            },
+            Type::Tuple(elements) => {
+                write!(f, "(")?;
+                write_comma_separated(f, elements)?;
+                write!(f, ")")
+            },
+            Type::Record { fields, rest } => {
+                write!(f, "{{")?;
+                for (i, (name, typ)) in fields.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(f, "{separator} {name}: {typ}")?;
+                }
+                match rest {
+                    Some(rest) => write!(f, " | {rest} }}"),
+                    None if fields.is_empty() => write!(f, "}}"),
+                    None => write!(f, " }}"),
+                }
+            },
             Type::Function { parameter, return_type } => {
                 if matches!(parameter.as_ref(), Type::Function { .. }) {
                     write!(f, "({parameter}) -> {return_type}")
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     errors::Location,
     incremental::{CompilerHandle, Parse},
-    parser::ast::Expression,
+    parser::ast::{Expression, Type},
 };
 
 /// A `TopLevelId` is a 64-bit hash uniquely identifying a particular
@@ -49,6 +49,28 @@ impl TopLevelId {
         hash(file_path, (definition_name, collision))
     }
 
+    /// Type definitions are hashed like definitions, although we also hash a tag so that a type
+    /// and a definition with the same name in the same file still get different Ids.
+    pub fn new_type_definition(file_path: Arc<String>, type_name: &str, collision: u32) -> TopLevelId {
+        hash(file_path, ("type", type_name, collision))
+    }
+
+    /// Classes are hashed like type definitions, with a different tag
+    pub fn new_class(file_path: Arc<String>, class_name: &str, collision: u32) -> TopLevelId {
+        hash(file_path, ("class", class_name, collision))
+    }
+
+    /// Instances have no name of their own, so we hash the class and type they are an instance for
+    pub fn new_instance(file_path: Arc<String>, class_name: &str, typ: &Type, collision: u32) -> TopLevelId {
+        hash(file_path, ("instance", class_name, typ, collision))
+    }
+
+    /// Built-in classes and instances aren't defined by any statement, but are still given an id so
+    /// that type inference can treat them like any other. These are hashed from their name alone.
+    pub fn new_builtin(file_path: Arc<String>, name: &str) -> TopLevelId {
+        hash(file_path, ("builtin", name))
+    }
+
     /// Print statements only have their expression contents so we just hash that.
     /// This means any time what we print is changed we recompile the print statement, but
     /// unlike definitions, this is usually desired.
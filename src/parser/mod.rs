@@ -38,22 +38,34 @@
 //!   errors there in the future. For types this means if a type fails to parse you can
 //!   also filter out type errors with that error type since error types should always
 //!   correctly type check (and should be hidden from users).
-use std::{collections::BTreeMap, sync::Arc};
+use std::{collections::BTreeMap, path::Path, sync::Arc};
 
-use ast::{Ast, Definition, Expression, Identifier, Program, TopLevelStatement, Type};
+use ast::{
+    Ast, ClassDefinition, Constraint, Constructor, Definition, Expression, Identifier, InstanceDefinition, MatchArm,
+    Pattern, Program, TopLevelStatement, Type, TypeAlias, TypeDefinition,
+};
 use ids::{ExprId, TopLevelId};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     errors::{Error, Errors, Location, LocationData, Position},
     incremental::{self, CompilerHandle, Parse},
-    lexer::{self, tokens::Token},
+    lexer::{self, is_capitalized, tokens::Token},
 };
 
 pub mod ast;
 mod ast_printer;
 pub mod ids;
 
+/// Each binary operator grouped by precedence, from lowest to highest.
+/// Operators in the same group are left-associative.
+const PRECEDENCE_LEVELS: &[&[Token]] = &[
+    &[Token::EqualEquals, Token::NotEquals, Token::Less, Token::LessEquals, Token::Greater, Token::GreaterEquals],
+    &[Token::PlusPlus],
+    &[Token::Plus, Token::Minus],
+    &[Token::Star, Token::Slash, Token::Percent],
+];
+
 struct Parser {
     tokens: Vec<(Token, Location)>,
     current_token_index: usize,
@@ -92,12 +104,13 @@ pub fn parse_impl(params: &Parse, db: &CompilerHandle) -> ParserResult {
     incremental::enter_query();
     incremental::println(format!("Parsing {}", params.file_name));
 
-    let tokens = lexer::lex_file(params.file_name.clone(), db);
+    let (tokens, mut errors) = lexer::lex_file(params.file_name.clone(), db);
     let mut parser = Parser::new(params.file_name.clone(), tokens);
     let ast = parser.parse();
+    errors.append(&mut parser.errors);
 
     incremental::exit_query();
-    ParserResult { ast: Arc::new(ast), errors: parser.errors, top_level_data: Arc::new(parser.top_level_data) }
+    ParserResult { ast: Arc::new(ast), errors, top_level_data: Arc::new(parser.top_level_data) }
 }
 
 impl Parser {
@@ -136,6 +149,16 @@ impl Parser {
         }
     }
 
+    /// Returns the location of the last token we advanced past. This is used for
+    /// the end of a Location spanning multiple tokens. If we haven't advanced past
+    /// any tokens yet, the current location is returned instead.
+    fn previous_location(&self) -> Location {
+        match self.current_token_index.checked_sub(1).and_then(|index| self.tokens.get(index)) {
+            Some((_, location)) => location.clone(),
+            None => self.current_location(),
+        }
+    }
+
     /// Returns the current token, or None if we've reached the end of input,
     /// along with the current location. If we've reached the end of input,
     /// the last token's location is used.
@@ -195,7 +218,7 @@ impl Parser {
     fn parse(&mut self) -> Program {
         let statements = self.parse_top_level_statements();
 
-        if !self.current_token().is_none() {
+        if self.current_token().is_some() {
             // We have unparsed input
             let (found, location) = self.current_token_and_location();
             let found = found.cloned();
@@ -251,7 +274,7 @@ impl Parser {
     /// Parse a top level statement - expects the input to already
     /// be on a token such that `token.can_start_top_level_statement()` is true.
     ///
-    /// top_level_statement: definition | import | print
+    /// top_level_statement: definition | import | print | type_definition | class_definition | instance_definition
     fn parse_top_level_statement(&mut self) -> Result<TopLevelStatement, Error> {
         self.next_expr_id = 0;
         let token = self.current_token().expect(
@@ -264,6 +287,9 @@ impl Parser {
             Token::Def => self.parse_definition(),
             Token::Import => self.parse_import(),
             Token::Print => self.parse_print(),
+            Token::Type => self.parse_type_definition(),
+            Token::Class => self.parse_class_definition(),
+            Token::Instance => self.parse_instance_definition(),
             _ => unreachable!(
                 "parse_top_level_statement should only be called on a token which may start a top_level_statement"
             ),
@@ -277,16 +303,33 @@ impl Parser {
         self.top_level_data.insert(id.clone(), meta);
     }
 
-    /// definition: "def" name (":" type)? "=" expr
+    /// definition: "def" name (":" ("forall" name* ".")? (constraints "=>")? type)? "=" expr
     fn parse_definition(&mut self) -> Result<TopLevelStatement, Error> {
         let start = self.current_location();
         self.expect(Token::Def)?;
 
         let name = self.parse_name()?;
 
+        let mut forall = None;
+        let mut constraints = Vec::new();
         let mut typ = None;
         if self.accept(Token::Colon) {
-            typ = Some(self.parse_type()?);
+            if self.accept(Token::Forall) {
+                let mut generics = Vec::new();
+                while !self.accept(Token::Dot) {
+                    generics.push(self.parse_name()?);
+                }
+                forall = Some(generics);
+            }
+
+            let mut annotation = self.parse_type()?;
+
+            // Constraints are written like types, so we only know we parsed them once we reach the `=>`
+            if self.accept(Token::FatArrow) {
+                constraints = self.constraints_from_type(annotation)?;
+                annotation = self.parse_type()?;
+            }
+            typ = Some(annotation);
         }
 
         self.expect(Token::Equals)?;
@@ -294,29 +337,46 @@ impl Parser {
 
         // TODO: Handle collisions
         let id = TopLevelId::new_definition(self.file_name.clone(), &name.name, 0);
-        let location = start.to(&self.current_location());
+        let location = start.to(&self.previous_location());
         self.store_top_level_metadata(id.clone(), location);
 
-        Ok(TopLevelStatement::Definition(Definition { name, typ, body, id }))
+        Ok(TopLevelStatement::Definition(Definition { name, forall, constraints, typ, body, id }))
     }
 
-    /// import: "import" name
+    /// import: "import" name ("(" (name ("," name)*)? ")")?
     fn parse_import(&mut self) -> Result<TopLevelStatement, Error> {
         let start = self.current_location();
         self.expect(Token::Import)?;
         let mut file_name = self.parse_name()?;
 
+        let mut names = None;
+        if self.accept(Token::ParenLeft) {
+            let names = names.insert(Vec::new());
+            if !self.accept(Token::ParenRight) {
+                loop {
+                    names.push(self.parse_name()?);
+                    if !self.accept(Token::Comma) {
+                        break;
+                    }
+                }
+                self.expect(Token::ParenRight)?;
+            }
+        }
+
         // Hack: Adding the .ex suffix here lets us share this suffix in the Arc
         // much more easily without having to cache it and add code to translate between
-        // the module name and the file name everywhere else.
-        file_name.name = Arc::new(format!("{}.ex", file_name.name));
+        // the module name and the file name everywhere else. Imports are relative to the
+        // directory of the importing file so we also prepend that directory here.
+        let directory = Path::new(self.file_name.as_str()).parent().unwrap_or(Path::new(""));
+        let path = directory.join(format!("{}.ex", file_name.name));
+        file_name.name = Arc::new(path.to_string_lossy().into_owned());
 
         // TODO: Handle collisions
         let id = TopLevelId::new_import(self.file_name.clone(), &file_name.name, 0);
-        let location = start.to(&self.current_location());
+        let location = start.to(&self.previous_location());
         self.store_top_level_metadata(id.clone(), location);
 
-        Ok(TopLevelStatement::Import { file_name, id })
+        Ok(TopLevelStatement::Import { file_name, names, id })
     }
 
     /// print: "print" expr
@@ -324,7 +384,7 @@ impl Parser {
         let start = self.current_location();
         self.expect(Token::Print)?;
         let expr = self.parse_expr()?;
-        let location = start.to(&self.current_location());
+        let location = start.to(&self.previous_location());
 
         // TODO: Handle collisions
         let id = TopLevelId::new_print(self.file_name.clone(), &expr, 0);
@@ -333,87 +393,416 @@ impl Parser {
         Ok(TopLevelStatement::Print(Arc::new(expr), id))
     }
 
-    /// expr: lambda | infix_expr
+    /// type_definition: "type" type_name name* "=" "|"? constructor ("|" constructor)*
+    ///                | "type" type_name name* "=" type
+    ///
+    /// The second form defines a type alias. A single named type such as `List a` can't be told apart
+    /// from a constructor so it is always parsed as one, and must be parenthesized to be aliased instead.
+    fn parse_type_definition(&mut self) -> Result<TopLevelStatement, Error> {
+        let start = self.current_location();
+        self.expect(Token::Type)?;
+        let name = self.parse_type_name()?;
+
+        let mut generics = Vec::new();
+        while let Ok(generic) = self.parse_name() {
+            generics.push(generic);
+        }
+
+        self.expect(Token::Equals)?;
+        let leading_pipe = self.accept(Token::Pipe);
+        let starts_with_constructor = matches!(self.current_token(), Some(Token::Name(name)) if is_capitalized(name));
+
+        let mut constructors = Vec::new();
+        let alias = if !leading_pipe && !starts_with_constructor {
+            Some(self.parse_type()?)
+        } else {
+            let first = self.parse_constructor()?;
+
+            // A function type starting with a named type, e.g. `Option a -> a`, is only known to be one here
+            if !leading_pipe && self.accept(Token::RightArrow) {
+                let parameter = Arc::new(Type::Named { name: first.name, arguments: first.fields });
+                let return_type = Arc::new(self.parse_type()?);
+                Some(Type::Function { parameter, return_type })
+            } else {
+                constructors.push(first);
+                while self.accept(Token::Pipe) {
+                    constructors.push(self.parse_constructor()?);
+                }
+                None
+            }
+        };
+
+        // TODO: Handle collisions
+        let id = TopLevelId::new_type_definition(self.file_name.clone(), &name.name, 0);
+        let location = start.to(&self.previous_location());
+        self.store_top_level_metadata(id.clone(), location);
+
+        match alias {
+            Some(typ) => Ok(TopLevelStatement::TypeAlias(TypeAlias { name, generics, typ, id })),
+            None => Ok(TopLevelStatement::TypeDefinition(TypeDefinition { name, generics, constructors, id })),
+        }
+    }
+
+    /// constructor: type_name basic_type*
+    fn parse_constructor(&mut self) -> Result<Constructor, Error> {
+        let name = self.parse_type_name()?;
+        let mut fields = Vec::new();
+
+        while self.current_token().is_some_and(Token::can_start_basic_type) {
+            fields.push(self.parse_basic_type()?);
+        }
+
+        Ok(Constructor { name, fields })
+    }
+
+    /// class_definition: "class" type_name name "{" (name ":" type ("," name ":" type)*)? "}"
+    fn parse_class_definition(&mut self) -> Result<TopLevelStatement, Error> {
+        let start = self.current_location();
+        self.expect(Token::Class)?;
+        let name = self.parse_type_name()?;
+        let generic = self.parse_name()?;
+        self.expect(Token::BraceLeft)?;
+
+        let mut methods = Vec::new();
+        if !self.accept(Token::BraceRight) {
+            loop {
+                let name = self.parse_name()?;
+                self.expect(Token::Colon)?;
+                methods.push((name, self.parse_type()?));
+
+                if !self.accept(Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::BraceRight)?;
+        }
+
+        // TODO: Handle collisions
+        let id = TopLevelId::new_class(self.file_name.clone(), &name.name, 0);
+        let location = start.to(&self.previous_location());
+        self.store_top_level_metadata(id.clone(), location);
+
+        Ok(TopLevelStatement::Class(ClassDefinition { name, generic, methods, id }))
+    }
+
+    /// instance_definition: "instance" (constraints "=>")? type_name basic_type "{" instance_methods? "}"
+    /// instance_methods: name "=" expr ("," name "=" expr)*
+    fn parse_instance_definition(&mut self) -> Result<TopLevelStatement, Error> {
+        let start = self.current_location();
+        self.expect(Token::Instance)?;
+
+        let mut constraints = Vec::new();
+        if self.current_token() == Some(&Token::ParenLeft) {
+            let context = self.parse_basic_type()?;
+            self.expect(Token::FatArrow)?;
+            constraints = self.constraints_from_type(context)?;
+        }
+
+        let mut class = self.parse_type_name()?;
+        let mut typ = self.parse_basic_type()?;
+
+        // A single constraint needs no parentheses, so the `Show a` of `instance Show a => Show (List a)`
+        // looks just like the class and type of an instance until we reach the `=>`
+        if constraints.is_empty() && self.accept(Token::FatArrow) {
+            constraints = self.constraints_from_type(Type::Named { name: class, arguments: vec![typ] })?;
+            class = self.parse_type_name()?;
+            typ = self.parse_basic_type()?;
+        }
+
+        self.expect(Token::BraceLeft)?;
+        let mut methods = Vec::new();
+        if !self.accept(Token::BraceRight) {
+            loop {
+                let name = self.parse_name()?;
+                self.expect(Token::Equals)?;
+                methods.push((name, Arc::new(self.parse_expr()?)));
+
+                if !self.accept(Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::BraceRight)?;
+        }
+
+        // TODO: Handle collisions
+        let id = TopLevelId::new_instance(self.file_name.clone(), &class.name, &typ, 0);
+        let location = start.to(&self.previous_location());
+        self.store_top_level_metadata(id.clone(), location);
+
+        Ok(TopLevelStatement::Instance(InstanceDefinition { constraints, class, typ, methods, id }))
+    }
+
+    /// constraints: constraint | "(" constraint ("," constraint)* ")"
+    /// constraint: type_name name
+    ///
+    /// Constraints are parsed as a type first since they can't be told apart from one until the
+    /// `=>` after them. This converts that type into the constraints it stands for.
+    fn constraints_from_type(&self, typ: Type) -> Result<Vec<Constraint>, Error> {
+        let constraint = |typ| match typ {
+            Type::Named { name: class, arguments } => match <[Type; 1]>::try_from(arguments) {
+                Ok([Type::Generic(generic)]) => Ok(Constraint { class, generic }),
+                _ => Err(()),
+            },
+            _ => Err(()),
+        };
+
+        let constraints = match typ {
+            Type::Tuple(elements) => elements.into_iter().map(constraint).collect(),
+            other => constraint(other).map(|constraint| vec![constraint]),
+        };
+
+        constraints.map_err(|()| {
+            let rule = "constraints such as `Show a` or `(Show a, Eq b)`".to_string();
+            Error::ParserExpected { rule, found: Some(Token::FatArrow), location: self.previous_location() }
+        })
+    }
+
+    /// expr: lambda | if | let | match | infix_expr
     fn parse_expr(&mut self) -> Result<Expression, Error> {
-        if self.current_token() == Some(&Token::Fn) { self.parse_lambda() } else { self.parse_infix_expr() }
+        match self.current_token() {
+            Some(Token::Fn) => self.parse_lambda(),
+            Some(Token::If) => self.parse_if(),
+            Some(Token::Let) => self.parse_let(),
+            Some(Token::Match) => self.parse_match(),
+            _ => self.parse_infix_expr(),
+        }
+    }
+
+    /// if: "if" expr "then" expr "else" expr
+    fn parse_if(&mut self) -> Result<Expression, Error> {
+        let start = self.current_location();
+        self.expect(Token::If)?;
+        let condition = Arc::new(self.parse_expr()?);
+        self.expect(Token::Then)?;
+        let then_branch = Arc::new(self.parse_expr()?);
+        self.expect(Token::Else)?;
+        let else_branch = Arc::new(self.parse_expr()?);
+
+        let location = start.to(&self.previous_location());
+        Ok(Expression::If { condition, then_branch, else_branch, id: self.next_expr_id(location) })
+    }
+
+    /// let: "let" name "=" expr "in" expr
+    fn parse_let(&mut self) -> Result<Expression, Error> {
+        let start = self.current_location();
+        self.expect(Token::Let)?;
+        let name = self.parse_name()?;
+        self.expect(Token::Equals)?;
+        let value = Arc::new(self.parse_expr()?);
+        self.expect(Token::In)?;
+        let body = Arc::new(self.parse_expr()?);
+
+        let location = start.to(&self.previous_location());
+        Ok(Expression::Let { name, value, body, id: self.next_expr_id(location) })
+    }
+
+    /// match: "match" expr "with" ("|" pattern "->" expr)+
+    ///
+    /// Since each arm extends as far as possible, a `match` within an arm of another `match`
+    /// needs to be parenthesized unless it is in the last arm.
+    fn parse_match(&mut self) -> Result<Expression, Error> {
+        let start = self.current_location();
+        self.expect(Token::Match)?;
+        let scrutinee = Arc::new(self.parse_expr()?);
+        self.expect(Token::With)?;
+
+        let mut arms = Vec::new();
+        self.expect(Token::Pipe)?;
+        loop {
+            let pattern = self.parse_pattern()?;
+            self.expect(Token::RightArrow)?;
+            let body = Arc::new(self.parse_expr()?);
+            arms.push(MatchArm { pattern, body });
+
+            if !self.accept(Token::Pipe) {
+                break;
+            }
+        }
+
+        let location = start.to(&self.previous_location());
+        Ok(Expression::Match { scrutinee, arms, id: self.next_expr_id(location) })
+    }
+
+    /// pattern: type_name pattern_atom*
+    ///        | pattern_atom
+    fn parse_pattern(&mut self) -> Result<Pattern, Error> {
+        match self.current_token() {
+            Some(Token::Name(name)) if is_capitalized(name) => {
+                let start = self.current_location();
+                let name = self.parse_type_name()?;
+                let mut arguments = Vec::new();
+
+                while self.current_token().is_some_and(Token::can_start_pattern) {
+                    arguments.push(self.parse_pattern_atom()?);
+                }
+
+                let location = start.to(&self.previous_location());
+                Ok(Pattern::Constructor { name, arguments, id: self.next_expr_id(location) })
+            },
+            _ => self.parse_pattern_atom(),
+        }
     }
 
-    /// lambda: "fn" name+ "->" expr
+    /// pattern_atom: "_" | name | integer | "true" | "false" | type_name | "(" pattern ("," pattern)* ")"
+    fn parse_pattern_atom(&mut self) -> Result<Pattern, Error> {
+        match self.current_token_and_location() {
+            (Some(Token::Underscore), location) => {
+                self.advance();
+                Ok(Pattern::Wildcard(self.next_expr_id(location)))
+            },
+            (Some(Token::Name(name)), location) if is_capitalized(name) => {
+                let name = self.parse_type_name()?;
+                Ok(Pattern::Constructor { name, arguments: Vec::new(), id: self.next_expr_id(location) })
+            },
+            (Some(Token::Name(_)), _) => Ok(Pattern::Variable(self.parse_name()?)),
+            (Some(Token::Integer(x)), location) => {
+                let x = *x;
+                self.advance();
+                Ok(Pattern::IntegerLiteral(x, self.next_expr_id(location)))
+            },
+            (Some(Token::True | Token::False), location) => {
+                let value = self.current_token() == Some(&Token::True);
+                self.advance();
+                Ok(Pattern::BoolLiteral(value, self.next_expr_id(location)))
+            },
+            (Some(Token::ParenLeft), start) => {
+                self.advance();
+                let pattern = self.parse_pattern()?;
+                if !self.accept(Token::Comma) {
+                    self.expect(Token::ParenRight)?;
+                    return Ok(pattern);
+                }
+
+                let mut elements = vec![pattern, self.parse_pattern()?];
+                while self.accept(Token::Comma) {
+                    elements.push(self.parse_pattern()?);
+                }
+                self.expect(Token::ParenRight)?;
+
+                let location = start.to(&self.previous_location());
+                Ok(Pattern::Tuple { elements, id: self.next_expr_id(location) })
+            },
+            (other, location) => {
+                let rule = "a pattern".to_string();
+                Err(Error::ParserExpected { rule, found: other.cloned(), location })
+            },
+        }
+    }
+
+    /// lambda: "fn" pattern_atom+ "->" expr
+    ///
+    /// Parameters are usually names but may be any pattern which matches every value, e.g. `fn (a, b) -> a`.
     fn parse_lambda(&mut self) -> Result<Expression, Error> {
         let start = self.current_location();
         self.expect(Token::Fn)?;
-        let mut parameters = vec![self.parse_name()?];
+        let mut parameters = vec![self.parse_pattern_atom()?];
 
         // The remaining parameters are optional so don't error if they're not there
-        while let Ok(arg) = self.parse_name() {
-            parameters.push(arg);
+        while self.current_token().is_some_and(Token::can_start_pattern) {
+            parameters.push(self.parse_pattern_atom()?);
         }
 
         self.expect(Token::RightArrow)?;
         let body = self.parse_expr()?;
-        let location = start.to(&self.current_location());
+        let location = start.to(&self.previous_location());
 
         // Lambdas with more than one parameter are desugared into nested lambdas
         // each with exactly one parameter
         let mut expr = body;
-        for parameter_name in parameters.into_iter().rev() {
+        for parameter in parameters.into_iter().rev() {
             let body = Arc::new(expr);
             let id = self.next_expr_id(location.clone());
-            expr = Expression::Lambda { parameter_name, body, id };
+            expr = Expression::Lambda { parameter, body, id };
         }
 
         Ok(expr)
     }
 
-    /// expr: expr + call
-    ///     | expr - call
-    ///     | call
+    /// infix_expr: infix_expr comparison_operator concatenation
+    ///           | concatenation
+    ///
+    /// concatenation: concatenation "++" sum
+    ///              | sum
+    ///
+    /// sum: sum ("+" | "-") product
+    ///    | product
+    ///
+    /// product: product ("*" | "/" | "%") call
+    ///        | call
+    ///
+    /// comparison_operator: "==" | "!=" | "<" | "<=" | ">" | ">="
     fn parse_infix_expr(&mut self) -> Result<Expression, Error> {
+        self.parse_binary_operators(0)
+    }
+
+    /// Parse a chain of the operators at the given index into `PRECEDENCE_LEVELS`,
+    /// where each operand may contain only operators with a higher precedence.
+    fn parse_binary_operators(&mut self, precedence: usize) -> Result<Expression, Error> {
+        let Some(operators) = PRECEDENCE_LEVELS.get(precedence) else { return self.parse_call() };
         let start = self.current_location();
-        let mut expr = self.parse_call()?;
+        let mut expr = self.parse_binary_operators(precedence + 1)?;
 
-        // `a + b` and `a - b` are represented as function calls: `(+) a b` and `(-) a b`
-        let operator = |this: &mut Self, name: &str, expr| -> Result<_, Error> {
-            let operator_location = this.current_location();
-            this.advance();
-            let id = this.next_expr_id(operator_location);
-            let name = Identifier { name: Arc::new(name.into()), id };
+        // `a + b` is represented as a function call: `(+) a b`. The name of the variable is the
+        // operator itself, which name resolution and later passes look up in the builtin table.
+        while let Some(token) = self.current_token().filter(|token| operators.contains(token)) {
+            let name = Arc::new(token.to_string());
+            let operator_location = self.current_location();
+            self.advance();
+            let id = self.next_expr_id(operator_location);
+            let function = Arc::new(Expression::Variable(Identifier { name, id }));
 
-            let function = Arc::new(Expression::Variable(name));
             let lhs = Arc::new(expr);
-            let rhs = Arc::new(this.parse_call()?);
-            let call_location = start.to(&this.current_location());
+            let rhs = Arc::new(self.parse_binary_operators(precedence + 1)?);
+            let call_location = start.to(&self.previous_location());
 
-            let id = this.next_expr_id(call_location.clone());
+            let id = self.next_expr_id(call_location.clone());
             let call1 = Arc::new(Expression::FunctionCall { function, argument: lhs, id });
-            let id = this.next_expr_id(call_location);
-            Ok(Expression::FunctionCall { function: call1, argument: rhs, id })
-        };
-
-        while matches!(self.current_token(), Some(Token::Plus | Token::Minus)) {
-            expr = operator(self, "+", expr)?;
+            let id = self.next_expr_id(call_location);
+            expr = Expression::FunctionCall { function: call1, argument: rhs, id };
         }
-        
+
         Ok(expr)
     }
 
-    /// call: call atom
-    ///     | atom
+    /// call: call field_access
+    ///     | field_access
     fn parse_call(&mut self) -> Result<Expression, Error> {
         let start = self.current_location();
-        let mut atom = self.parse_atom()?;
+        let atom = self.parse_atom()?;
+        let mut atom = self.parse_field_accesses(atom, start.clone())?;
 
-        while let Ok(argument) = self.parse_atom() {
+        loop {
+            let argument_start = self.current_location();
+            let Ok(argument) = self.parse_atom() else { break };
+            let argument = Arc::new(self.parse_field_accesses(argument, argument_start)?);
             let function = Arc::new(atom);
-            let argument = Arc::new(argument);
-            let location = start.to(&self.current_location());
+            let location = start.to(&self.previous_location());
             atom = Expression::FunctionCall { function, argument, id: self.next_expr_id(location) };
         }
 
         Ok(atom)
     }
 
-    /// atom: name | integer | "(" expr ")"
+    /// field_access: field_access "." name
+    ///             | atom
+    ///
+    /// Parses any field accesses following the already-parsed `atom` starting at `start`.
+    fn parse_field_accesses(&mut self, atom: Expression, start: Location) -> Result<Expression, Error> {
+        let mut expr = atom;
+        while self.accept(Token::Dot) {
+            let field = self.parse_name()?;
+            let location = start.to(&self.previous_location());
+            expr = Expression::FieldAccess { record: Arc::new(expr), field, id: self.next_expr_id(location) };
+        }
+        Ok(expr)
+    }
+
+    /// atom: name | integer | string | "true" | "false" | hole | tuple | record | list | "(" expr ")"
+    ///
+    /// hole: "_" | "?" name
+    ///
+    /// tuple: "(" expr ("," expr)+ ")"
+    /// list: "[" (expr ("," expr)*)? "]"
     fn parse_atom(&mut self) -> Result<Expression, Error> {
         match self.current_token_and_location() {
             (Some(Token::Name(name)), location) => {
@@ -427,11 +816,56 @@ impl Parser {
                 self.advance();
                 Ok(Expression::IntegerLiteral(x, self.next_expr_id(location)))
             },
-            (Some(Token::ParenLeft), _) => {
+            (Some(Token::StringLiteral(string)), location) => {
+                let string = Arc::new(string.clone());
+                self.advance();
+                Ok(Expression::StringLiteral(string, self.next_expr_id(location)))
+            },
+            (Some(Token::True | Token::False), location) => {
+                let value = self.current_token() == Some(&Token::True);
+                self.advance();
+                Ok(Expression::BoolLiteral(value, self.next_expr_id(location)))
+            },
+            (Some(Token::Underscore), location) => {
+                self.advance();
+                Ok(Expression::Hole { name: None, id: self.next_expr_id(location) })
+            },
+            (Some(Token::Hole(name)), location) => {
+                let name = Some(Arc::new(name.clone()));
+                self.advance();
+                Ok(Expression::Hole { name, id: self.next_expr_id(location) })
+            },
+            (Some(Token::ParenLeft), start) => {
                 self.advance();
                 let expr = self.parse_expr()?;
+                if !self.accept(Token::Comma) {
+                    self.expect(Token::ParenRight)?;
+                    return Ok(expr);
+                }
+
+                let mut elements = vec![Arc::new(expr), Arc::new(self.parse_expr()?)];
+                while self.accept(Token::Comma) {
+                    elements.push(Arc::new(self.parse_expr()?));
+                }
                 self.expect(Token::ParenRight)?;
-                Ok(expr)
+
+                let location = start.to(&self.previous_location());
+                Ok(Expression::Tuple { elements, id: self.next_expr_id(location) })
+            },
+            (Some(Token::BraceLeft), _) => self.parse_record(),
+            (Some(Token::BracketLeft), start) => {
+                self.advance();
+                let mut elements = Vec::new();
+                if !self.accept(Token::BracketRight) {
+                    elements.push(Arc::new(self.parse_expr()?));
+                    while self.accept(Token::Comma) {
+                        elements.push(Arc::new(self.parse_expr()?));
+                    }
+                    self.expect(Token::BracketRight)?;
+                }
+
+                let location = start.to(&self.previous_location());
+                Ok(Expression::List { elements, id: self.next_expr_id(location) })
             },
             (other, location) => {
                 let rule = "an expression".to_string();
@@ -440,10 +874,33 @@ impl Parser {
         }
     }
 
-    /// type: basic_type
-    ///     | basic_type "->" type
+    /// record: "{" (name "=" expr ("," name "=" expr)*)? "}"
+    fn parse_record(&mut self) -> Result<Expression, Error> {
+        let start = self.current_location();
+        self.expect(Token::BraceLeft)?;
+
+        let mut fields = Vec::new();
+        if !self.accept(Token::BraceRight) {
+            loop {
+                let name = self.parse_name()?;
+                self.expect(Token::Equals)?;
+                fields.push((name, Arc::new(self.parse_expr()?)));
+
+                if !self.accept(Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::BraceRight)?;
+        }
+
+        let location = start.to(&self.previous_location());
+        Ok(Expression::Record { fields, id: self.next_expr_id(location) })
+    }
+
+    /// type: applied_type
+    ///     | applied_type "->" type
     fn parse_type(&mut self) -> Result<Type, Error> {
-        let typ = self.parse_basic_type()?;
+        let typ = self.parse_applied_type()?;
 
         if self.accept(Token::RightArrow) {
             let parameter = Arc::new(typ);
@@ -454,13 +911,42 @@ impl Parser {
         }
     }
 
-    /// basic_type: "Int" | name | "(" type ")"
+    /// applied_type: type_name basic_type+
+    ///             | basic_type
+    fn parse_applied_type(&mut self) -> Result<Type, Error> {
+        match self.current_token() {
+            Some(Token::Name(name)) if is_capitalized(name) => {
+                let name = self.parse_type_name()?;
+                let mut arguments = Vec::new();
+
+                while self.current_token().is_some_and(Token::can_start_basic_type) {
+                    arguments.push(self.parse_basic_type()?);
+                }
+                Ok(Type::Named { name, arguments })
+            },
+            _ => self.parse_basic_type(),
+        }
+    }
+
+    /// basic_type: "Int" | "Bool" | "String" | type_name | name | "(" type ("," type)* ")" | record_type
     fn parse_basic_type(&mut self) -> Result<Type, Error> {
         match self.current_token() {
             Some(Token::Int) => {
                 self.advance();
                 Ok(Type::Int)
             },
+            Some(Token::Bool) => {
+                self.advance();
+                Ok(Type::Bool)
+            },
+            Some(Token::String) => {
+                self.advance();
+                Ok(Type::String)
+            },
+            Some(Token::Name(name)) if is_capitalized(name) => {
+                let name = self.parse_type_name()?;
+                Ok(Type::Named { name, arguments: Vec::new() })
+            },
             Some(Token::Name(name)) => {
                 let name = Arc::new(name.clone());
                 let location = self.current_location();
@@ -471,9 +957,19 @@ impl Parser {
             Some(Token::ParenLeft) => {
                 self.advance();
                 let typ = self.parse_type()?;
+                if !self.accept(Token::Comma) {
+                    self.expect(Token::ParenRight)?;
+                    return Ok(typ);
+                }
+
+                let mut elements = vec![typ, self.parse_type()?];
+                while self.accept(Token::Comma) {
+                    elements.push(self.parse_type()?);
+                }
                 self.expect(Token::ParenRight)?;
-                Ok(typ)
+                Ok(Type::Tuple(elements))
             },
+            Some(Token::BraceLeft) => self.parse_record_type(),
             other => {
                 let location = self.current_location();
                 let rule = "a type".to_string();
@@ -482,6 +978,49 @@ impl Parser {
         }
     }
 
+    /// record_type: "{" (name ":" type ("," name ":" type)*)? ("|" name)? "}"
+    ///
+    /// A record type ending in `| r` has at least the given fields, with any other fields it has given by `r`.
+    fn parse_record_type(&mut self) -> Result<Type, Error> {
+        self.expect(Token::BraceLeft)?;
+
+        let mut fields = Vec::new();
+        if matches!(self.current_token(), Some(Token::Name(_))) {
+            loop {
+                let name = self.parse_name()?;
+                self.expect(Token::Colon)?;
+                fields.push((name, self.parse_type()?));
+
+                if !self.accept(Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let rest = if self.accept(Token::Pipe) { Some(self.parse_name()?) } else { None };
+        self.expect(Token::BraceRight)?;
+        Ok(Type::Record { fields, rest })
+    }
+
+    /// type_name: [A-Z][a-zA-Z0-9]*
+    ///
+    /// The names of types and constructors must be capitalized. This is what lets us tell
+    /// constructor patterns apart from variable patterns without resolving them first.
+    fn parse_type_name(&mut self) -> Result<Identifier, Error> {
+        match self.current_token_and_location() {
+            (Some(Token::Name(name)), location) if is_capitalized(name) => {
+                let name = Arc::new(name.clone());
+                self.advance();
+                Ok(Identifier { name, id: self.next_expr_id(location) })
+            },
+            (other, location) => {
+                let found = other.cloned();
+                let rule = "a capitalized name".to_string();
+                Err(Error::ParserExpected { rule, found, location })
+            },
+        }
+    }
+
     /// name: [a-zA-Z][a-zA-Z0-9]*
     fn parse_name(&mut self) -> Result<Identifier, Error> {
         match self.current_token_and_location() {
@@ -0,0 +1,339 @@
+//! Checking the arms of each `match` expression. A match is exhaustive if every value of the type
+//! being matched on is handled by some arm, and an arm is redundant if every value it matches is
+//! already handled by an earlier arm. Lambda parameters are checked too since they're like a match
+//! with a single arm, which must then match every value.
+//!
+//! Both questions are answered with the usefulness algorithm from Luc Maranget's
+//! "Warnings for pattern matching": a pattern is useful with respect to the rows of a pattern
+//! matrix if there is some value it matches which no row matches. An arm is redundant if it isn't
+//! useful with respect to the arms before it, and a match is exhaustive if a wildcard isn't useful
+//! with respect to all of its arms. When a wildcard is useful, the algorithm also gives us an
+//! example of a value it matches which we can show in the error.
+//!
+//! This pass needs the type of each value being matched on to know which constructors it has, so it
+//! is only run on statements which type checked without errors.
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    errors::{Error, Errors},
+    incremental::{self, CheckMatches, CompilerHandle, GetStatement, TypeCheck},
+    parser::{
+        ast::{Expression, Pattern, TopLevelStatement},
+        ids::{ExprId, TopLevelId},
+    },
+    type_inference::{types::Type, ConstructorSignature},
+};
+
+/// Check each `match` within a top-level statement, returning any non-exhaustive matches and
+/// redundant match arms found.
+pub fn check_matches_impl(context: &CheckMatches, compiler: &CompilerHandle) -> Errors {
+    incremental::enter_query();
+    let statement = GetStatement(context.0.clone()).get(compiler);
+    incremental::println(format!("Checking matches in {statement}"));
+
+    let result = TypeCheck(context.0.clone()).get(compiler);
+    let mut checker = MatchChecker { item: &context.0, types: &result.expr_types, errors: Vec::new(), compiler };
+
+    // The types of a statement with type errors may not be consistent with its patterns
+    if result.errors.is_empty() {
+        match &statement {
+            TopLevelStatement::Import { .. }
+            | TopLevelStatement::TypeDefinition(_)
+            | TopLevelStatement::TypeAlias(_)
+            | TopLevelStatement::Class(_) => (),
+            TopLevelStatement::Definition(definition) => checker.check_expr(&definition.body),
+            TopLevelStatement::Print(expression, _) => checker.check_expr(expression),
+            TopLevelStatement::Instance(instance) => {
+                for (_, body) in instance.methods.iter() {
+                    checker.check_expr(body);
+                }
+            },
+        }
+    }
+
+    incremental::exit_query();
+    checker.errors
+}
+
+struct MatchChecker<'local, 'inner> {
+    item: &'local TopLevelId,
+    types: &'local BTreeMap<ExprId, Type>,
+    errors: Errors,
+    compiler: &'local CompilerHandle<'inner>,
+}
+
+/// A pattern with the details we don't care about removed. Variables match anything so they're
+/// treated as wildcards.
+#[derive(Clone)]
+enum SimplePattern {
+    Wildcard,
+    Constructor(Constructor, Vec<SimplePattern>),
+}
+
+/// Integer and boolean literals are treated as constructors without any fields, and a tuple as the
+/// only constructor of its type with a field for each element.
+#[derive(Clone, PartialEq, Eq)]
+enum Constructor {
+    Data(Arc<String>),
+    Int(i64),
+    Bool(bool),
+    Tuple,
+}
+
+impl SimplePattern {
+    fn new(pattern: &Pattern) -> SimplePattern {
+        match pattern {
+            Pattern::Wildcard(_) | Pattern::Variable(_) => SimplePattern::Wildcard,
+            Pattern::IntegerLiteral(x, _) => SimplePattern::Constructor(Constructor::Int(*x), Vec::new()),
+            Pattern::BoolLiteral(b, _) => SimplePattern::Constructor(Constructor::Bool(*b), Vec::new()),
+            Pattern::Constructor { name, arguments, id: _ } => {
+                let arguments = arguments.iter().map(SimplePattern::new).collect();
+                SimplePattern::Constructor(Constructor::Data(name.name.clone()), arguments)
+            },
+            Pattern::Tuple { elements, id: _ } => {
+                SimplePattern::Constructor(Constructor::Tuple, elements.iter().map(SimplePattern::new).collect())
+            },
+        }
+    }
+
+    fn constructor(&self) -> Option<&Constructor> {
+        match self {
+            SimplePattern::Wildcard => None,
+            SimplePattern::Constructor(constructor, _) => Some(constructor),
+        }
+    }
+}
+
+impl std::fmt::Display for SimplePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SimplePattern::Wildcard => write!(f, "_"),
+            SimplePattern::Constructor(Constructor::Int(x), _) => write!(f, "{x}"),
+            SimplePattern::Constructor(Constructor::Bool(b), _) => write!(f, "{b}"),
+            SimplePattern::Constructor(Constructor::Tuple, elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, ")")
+            },
+            SimplePattern::Constructor(Constructor::Data(name), arguments) => {
+                write!(f, "{name}")?;
+                for argument in arguments {
+                    // Tuples are already parenthesized
+                    let has_fields = matches!(argument, SimplePattern::Constructor(Constructor::Data(_), fields) if !fields.is_empty());
+                    if has_fields {
+                        write!(f, " ({argument})")?;
+                    } else {
+                        write!(f, " {argument}")?;
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Each row of a pattern matrix is a list of patterns matching a list of values, one per column
+type Row = Vec<SimplePattern>;
+
+impl MatchChecker<'_, '_> {
+    fn check_expr(&mut self, expression: &Expression) {
+        match expression {
+            Expression::IntegerLiteral(..)
+            | Expression::BoolLiteral(..)
+            | Expression::StringLiteral(..)
+            | Expression::Hole { .. }
+            | Expression::Variable(_) => (),
+            Expression::FunctionCall { function, argument, id: _ } => {
+                self.check_expr(function);
+                self.check_expr(argument);
+            },
+            Expression::Lambda { parameter, body, id: _ } => {
+                let typ = self.types[&parameter.id()].clone();
+                let row = [SimplePattern::new(parameter)];
+                if let Some(witness) = self.useful(&[row.to_vec()], &[SimplePattern::Wildcard], &[typ]) {
+                    let location = parameter.id().location(self.item, self.compiler);
+                    self.errors.push(Error::RefutableParameter { missing: witness[0].to_string(), location });
+                }
+                self.check_expr(body);
+            },
+            Expression::If { condition, then_branch, else_branch, id: _ } => {
+                self.check_expr(condition);
+                self.check_expr(then_branch);
+                self.check_expr(else_branch);
+            },
+            Expression::Let { value, body, .. } => {
+                self.check_expr(value);
+                self.check_expr(body);
+            },
+            Expression::Match { scrutinee, arms, id: _ } => {
+                self.check_expr(scrutinee);
+                let typ = self.types[&scrutinee.id()].clone();
+                let mut rows = Vec::new();
+
+                for arm in arms {
+                    let row = vec![SimplePattern::new(&arm.pattern)];
+                    if self.useful(&rows, &row, std::slice::from_ref(&typ)).is_none() {
+                        let location = arm.pattern.id().location(self.item, self.compiler);
+                        self.errors.push(Error::RedundantMatchArm { location });
+                    }
+                    rows.push(row);
+                    self.check_expr(&arm.body);
+                }
+
+                if let Some(witness) = self.useful(&rows, &[SimplePattern::Wildcard], &[typ]) {
+                    let location = scrutinee.id().location(self.item, self.compiler);
+                    self.errors.push(Error::NonExhaustiveMatch { missing: witness[0].to_string(), location });
+                }
+            },
+            Expression::Tuple { elements, id: _ } | Expression::List { elements, id: _ } => {
+                for element in elements {
+                    self.check_expr(element);
+                }
+            },
+            Expression::Record { fields, id: _ } => {
+                for (_, value) in fields {
+                    self.check_expr(value);
+                }
+            },
+            Expression::FieldAccess { record, .. } => self.check_expr(record),
+        }
+    }
+
+    /// If `row` is useful with respect to `rows`, returns the patterns of an example of the values
+    /// it matches which no row in `rows` does. `types` are the types of each column.
+    fn useful(&self, rows: &[Row], row: &[SimplePattern], types: &[Type]) -> Option<Row> {
+        let Some((first, rest)) = row.split_first() else {
+            // An empty row matches the (empty) values, so it is only useful if there are no other rows
+            return rows.is_empty().then(Vec::new);
+        };
+
+        match first {
+            SimplePattern::Constructor(constructor, arguments) => {
+                self.useful_constructor(rows, constructor, arguments, rest, types)
+            },
+            SimplePattern::Wildcard => {
+                let used: Vec<_> = rows.iter().filter_map(|row| row[0].constructor()).collect();
+                let all_constructors = self.constructors_of(&types[0]);
+
+                match all_constructors {
+                    // If every constructor is used by some row, the wildcard is useful if it is
+                    // useful in place of any one of them.
+                    Some(all) if all.iter().all(|constructor| used.contains(&constructor)) => {
+                        all.iter().find_map(|constructor| {
+                            let arity = self.field_types(constructor, &types[0]).len();
+                            let arguments: Row = repeat_wildcard(arity).collect();
+                            self.useful_constructor(rows, constructor, &arguments, rest, types)
+                        })
+                    },
+                    // Otherwise the wildcard matches a constructor no row matches, so only the rows
+                    // starting with a wildcard are relevant for the remaining columns
+                    all_constructors => {
+                        let default_rows: Vec<Row> = rows
+                            .iter()
+                            .filter(|row| matches!(row[0], SimplePattern::Wildcard))
+                            .map(|row| row[1..].to_vec())
+                            .collect();
+
+                        let mut witness = self.useful(&default_rows, rest, &types[1..])?;
+                        witness.insert(0, self.missing_constructor(all_constructors, &used, &types[0]));
+                        Some(witness)
+                    },
+                }
+            },
+        }
+    }
+
+    /// Check whether a row starting with the given constructor is useful, by checking whether its
+    /// arguments followed by the rest of its patterns are useful among the rows starting with that
+    /// same constructor (or a wildcard).
+    fn useful_constructor(
+        &self, rows: &[Row], constructor: &Constructor, arguments: &[SimplePattern], rest: &[SimplePattern],
+        types: &[Type],
+    ) -> Option<Row> {
+        let field_types = self.field_types(constructor, &types[0]);
+        let arity = field_types.len();
+
+        let specialized_rows: Vec<Row> = rows
+            .iter()
+            .filter_map(|row| match &row[0] {
+                SimplePattern::Wildcard => Some(repeat_wildcard(arity).chain(row[1..].iter().cloned()).collect()),
+                SimplePattern::Constructor(other, row_arguments) if other == constructor => {
+                    Some(row_arguments.iter().chain(&row[1..]).cloned().collect())
+                },
+                SimplePattern::Constructor(..) => None,
+            })
+            .collect();
+
+        let row: Row = arguments.iter().chain(rest).cloned().collect();
+        let types: Vec<_> = field_types.into_iter().chain(types[1..].iter().cloned()).collect();
+
+        let mut witness = self.useful(&specialized_rows, &row, &types)?;
+        let rest = witness.split_off(arity);
+        let first = SimplePattern::Constructor(constructor.clone(), witness);
+        Some(std::iter::once(first).chain(rest).collect())
+    }
+
+    /// Each constructor of the given type, or `None` if there are too many to list
+    /// like there are for integers.
+    fn constructors_of(&self, typ: &Type) -> Option<Vec<Constructor>> {
+        match typ {
+            Type::Bool => Some(vec![Constructor::Bool(true), Constructor::Bool(false)]),
+            Type::Data { id, .. } => match GetStatement(id.clone()).get(self.compiler) {
+                TopLevelStatement::TypeDefinition(definition) => {
+                    let names = definition.constructors.iter().map(|constructor| constructor.name.name.clone());
+                    Some(names.map(Constructor::Data).collect())
+                },
+                _ => unreachable!("Data types are only defined by type definitions"),
+            },
+            Type::Tuple(_) => Some(vec![Constructor::Tuple]),
+            _ => None,
+        }
+    }
+
+    /// The type of each field of the given constructor of a type
+    fn field_types(&self, constructor: &Constructor, typ: &Type) -> Vec<Type> {
+        match (constructor, typ) {
+            (Constructor::Data(name), Type::Data { id, arguments, .. }) => {
+                let signature = ConstructorSignature::new(id, name, self.compiler);
+                let substitutions = signature.generics.iter().cloned().zip(arguments.iter().cloned()).collect();
+                let fields = signature.fields.iter();
+                fields.map(|field| field.substitute(&substitutions, &Default::default())).collect()
+            },
+            (Constructor::Tuple, Type::Tuple(elements)) => elements.to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// A pattern for an example of a value matched by none of the `used` constructors
+    fn missing_constructor(
+        &self, all_constructors: Option<Vec<Constructor>>, used: &[&Constructor], typ: &Type,
+    ) -> SimplePattern {
+        // If no constructor is used the example may as well be anything
+        if used.is_empty() {
+            return SimplePattern::Wildcard;
+        }
+
+        match all_constructors {
+            Some(all) => {
+                let missing = all.into_iter().find(|constructor| !used.contains(&constructor));
+                let missing = missing.expect("Some constructor should be unused if the wildcard is useful");
+                let arguments = repeat_wildcard(self.field_types(&missing, typ).len()).collect();
+                SimplePattern::Constructor(missing, arguments)
+            },
+            None => {
+                // Only integers have infinitely many constructors
+                let unused = (0..).find(|x| !used.contains(&&Constructor::Int(*x))).unwrap();
+                SimplePattern::Constructor(Constructor::Int(unused), Vec::new())
+            },
+        }
+    }
+}
+
+fn repeat_wildcard(count: usize) -> impl Iterator<Item = SimplePattern> {
+    std::iter::repeat_n(SimplePattern::Wildcard, count)
+}
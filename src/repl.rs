@@ -0,0 +1,372 @@
+//! An interactive read-eval-print loop.
+//!
+//! Rather than having a separate code path for the REPL, each line entered is turned into a new
+//! synthetic source file which only exists in memory: `repl_1.ex`, `repl_2.ex`, etc. Each of these
+//! files implicitly imports every file loaded with `:load` along with the latest definition of each
+//! name entered so far, so the rest of the compiler treats the REPL just like any other program.
+//! Entries are imported with a list of names, e.g. `import repl_1 (a, b)`, so redefining a name only
+//! stops the new entry and later entries from importing the old definition. Any other names from the
+//! entry with the old definition are still imported, and entries which already used the old
+//! definition keep using it.
+//!
+//! Entries defining instances are always imported, since instances have no name to shadow them with.
+//!
+//! Lines which don't start a top-level statement are treated as expressions. These are wrapped in a
+//! definition `def it = <expression>` so they can be type checked and generalized like any other
+//! definition, but they are never imported into later entries.
+//!
+//! Because each entry is cached like any other file, `:reload` only needs to update the inputs of
+//! loaded files which changed on disk. Re-checking every entry afterward only re-checks the entries
+//! which depend on something which actually changed.
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    io::{BufRead, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    backend,
+    cli::Options,
+    errors::{contains_errors, Errors},
+    find_changed_files::collect_all_changed_files_with,
+    incremental::{
+        self, set_source_file, CheckFile, Compiler, Definitions, ExpandTypeAlias, ExportedDefinitions,
+        ExportedInstances, GetType, Parse,
+    },
+    interpreter, lexer,
+    parser::{ast::TopLevelStatement, ids::TopLevelId},
+    print_errors, read_file,
+    type_inference::{types::TypeBindings, ClassSignature, ConstructorSignature},
+};
+
+const HELP: &str = "\
+Enter a top-level statement or an expression to evaluate it.
+
+Commands:
+  :type <expr>   Print the type of the given expression
+  :load <file>   Load a file in the current directory, making its definitions visible
+  :reload        Re-read each loaded file from disk and re-check each entry
+  :help          Print this message
+  :quit          Exit the REPL";
+
+/// Run the REPL until the input ends or `:quit` is entered
+pub fn run(options: &Options) {
+    // Our query trace would be far too noisy when interleaved with the REPL's output
+    incremental::set_quiet(true);
+
+    let mut repl = Repl::new(options);
+    println!("Type :help for a list of commands");
+
+    let mut stdin = std::io::stdin().lock();
+    loop {
+        print!("> ");
+        std::io::stdout().flush().ok();
+
+        let mut line = String::new();
+        match stdin.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(error) => return eprintln!("error: Failed to read from stdin: {error}"),
+        }
+
+        if !repl.handle_line(line.trim()) {
+            break;
+        }
+    }
+}
+
+struct Repl<'a> {
+    compiler: Compiler,
+    options: &'a Options,
+
+    /// The text of every file known to the REPL, including REPL entries. Files other than entries
+    /// are only read from disk when they are first loaded or when `:reload` is entered.
+    sources: BTreeMap<Arc<String>, String>,
+
+    /// Each REPL entry which had no errors
+    entries: BTreeSet<Arc<String>>,
+
+    /// Files loaded with `:load` or an `import` entered in the REPL
+    loaded: BTreeSet<Arc<String>>,
+
+    /// Maps each name defined in the REPL to the entry that most recently defined it
+    definitions: BTreeMap<Arc<String>, Arc<String>>,
+
+    /// Maps each type defined in the REPL to the entry that most recently defined it. Types are
+    /// kept separately since they may share a name with a definition or constructor.
+    types: BTreeMap<Arc<String>, Arc<String>>,
+
+    /// Maps each class defined in the REPL to the entry that most recently defined it
+    classes: BTreeMap<Arc<String>, Arc<String>>,
+
+    /// Each entry defining at least one instance
+    instance_entries: BTreeSet<Arc<String>>,
+
+    entry_count: usize,
+}
+
+impl<'a> Repl<'a> {
+    fn new(options: &'a Options) -> Self {
+        Self {
+            compiler: Compiler::default(),
+            options,
+            sources: BTreeMap::new(),
+            entries: BTreeSet::new(),
+            loaded: BTreeSet::new(),
+            definitions: BTreeMap::new(),
+            types: BTreeMap::new(),
+            classes: BTreeMap::new(),
+            instance_entries: BTreeSet::new(),
+            entry_count: 0,
+        }
+    }
+
+    /// Handle a single line of input. Returns false if the REPL should exit.
+    fn handle_line(&mut self, line: &str) -> bool {
+        let (command, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let argument = argument.trim();
+
+        match command {
+            "" => (),
+            ":q" | ":quit" => return false,
+            ":h" | ":help" => println!("{HELP}"),
+            ":t" | ":type" => self.print_type(argument),
+            ":l" | ":load" => self.load(argument),
+            ":r" | ":reload" => self.reload(),
+            _ if command.starts_with(':') => println!("Unknown command `{command}`, type :help for a list of commands"),
+            _ if lexer::starts_top_level_statement(line) => self.enter_statements(line),
+            _ => self.evaluate_expression(line),
+        }
+        true
+    }
+
+    fn enter_statements(&mut self, line: &str) {
+        let Some((entry, header_length)) = self.add_entry(line) else { return };
+        let ast = Parse { file_name: entry.clone() }.get(&self.compiler).ast;
+
+        for statement in ast.statements.iter().skip(header_length) {
+            match statement {
+                TopLevelStatement::Import { file_name, .. } => {
+                    self.loaded.insert(file_name.name.clone());
+
+                    // Loading a file also runs any `print`s in it
+                    let result = interpreter::run_program(file_name.name.clone(), &self.compiler, std::io::stdout());
+                    if let Err(error) = result {
+                        println!("error: {error}");
+                    }
+                },
+                TopLevelStatement::Definition(definition) => {
+                    let typ = GetType(definition.id.clone()).get(&self.compiler);
+                    println!("{} : {}", definition.name, typ.display(&TypeBindings::new()));
+                },
+                TopLevelStatement::Print(_, id) => self.run_statement(id),
+                TopLevelStatement::TypeDefinition(definition) => {
+                    for constructor in definition.constructors.iter() {
+                        let name = &constructor.name;
+                        let signature = ConstructorSignature::new(&definition.id, &name.name, &self.compiler);
+                        println!("{name} : {}", signature.typ().display(&TypeBindings::new()));
+                    }
+                },
+                // Show what the alias expands to since it may use other aliases
+                TopLevelStatement::TypeAlias(alias) => {
+                    let expanded = ExpandTypeAlias(alias.id.clone()).get(&self.compiler).0;
+                    let generics = alias.generics.iter().map(|generic| format!(" {generic}")).collect::<String>();
+                    println!("type {}{generics} = {}", alias.name, expanded.typ.display(&TypeBindings::new()));
+                },
+                TopLevelStatement::Class(class) => {
+                    let signature = ClassSignature::new(&class.id, &self.compiler);
+                    for (name, _) in class.methods.iter() {
+                        println!("{name} : {}", signature.method_type(&name.name).display(&TypeBindings::new()));
+                    }
+                },
+                TopLevelStatement::Instance(_) => (),
+            }
+        }
+
+        let (definitions, _) = ExportedDefinitions { file_name: entry.clone() }.get(&self.compiler);
+
+        // Since a listed name is imported from every namespace it is in, defining a name in any namespace
+        // stops it from being imported from the entries which defined it before
+        let names = definitions.values.keys().chain(definitions.types.keys()).chain(definitions.classes.keys());
+        for name in names {
+            self.definitions.remove(name);
+            self.types.remove(name);
+            self.classes.remove(name);
+        }
+
+        for name in definitions.values.into_keys() {
+            self.definitions.insert(name, entry.clone());
+        }
+        for name in definitions.types.into_keys() {
+            self.types.insert(name, entry.clone());
+        }
+        for name in definitions.classes.into_keys() {
+            self.classes.insert(name, entry.clone());
+        }
+        let (instances, _) = ExportedInstances { file_name: entry.clone() }.get(&self.compiler);
+        if !instances.is_empty() {
+            self.instance_entries.insert(entry.clone());
+        }
+        self.entries.insert(entry);
+    }
+
+    fn evaluate_expression(&mut self, expression: &str) {
+        let Some((entry, header_length)) = self.add_entry(&format!("def it = {expression}")) else { return };
+        let ast = Parse { file_name: entry }.get(&self.compiler).ast;
+
+        if let Some(TopLevelStatement::Definition(definition)) = ast.statements.get(header_length) {
+            let typ = GetType(definition.id.clone()).get(&self.compiler);
+            match interpreter::evaluate(&definition.id, &self.compiler) {
+                Ok(Some(value)) => println!("{value} : {}", typ.display(&TypeBindings::new())),
+                Ok(None) => (),
+                Err(error) => println!("error: {error}"),
+            }
+        }
+    }
+
+    fn print_type(&mut self, expression: &str) {
+        let Some((entry, header_length)) = self.add_entry(&format!("def it = {expression}")) else { return };
+        let ast = Parse { file_name: entry }.get(&self.compiler).ast;
+
+        if let Some(TopLevelStatement::Definition(definition)) = ast.statements.get(header_length) {
+            let typ = GetType(definition.id.clone()).get(&self.compiler);
+            println!("{}", typ.display(&TypeBindings::new()));
+        }
+    }
+
+    /// Loading a file is the same as entering `import <file>`
+    fn load(&mut self, file: &str) {
+        let path = Path::new(file);
+        let in_current_directory = path.parent().is_none_or(|parent| parent.as_os_str().is_empty());
+
+        match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(module) if in_current_directory && lexer::is_valid_name(module) => {
+                if self.loaded.contains(&format!("{module}.ex")) {
+                    println!("`{module}.ex` is already loaded, use :reload to re-read it");
+                } else {
+                    self.enter_statements(&format!("import {module}"));
+                }
+            },
+            // Imports are relative to the importing file, and REPL entries are in the current directory
+            _ => println!("error: Only `.ex` files in the current directory can be loaded"),
+        }
+    }
+
+    /// Re-read each file which isn't a REPL entry from disk then re-check every file. Reading a
+    /// file which hasn't changed won't invalidate anything, so we only re-check what changed.
+    fn reload(&mut self) {
+        self.sources.retain(|file, _| self.entries.contains(file));
+
+        let mut errors = Errors::new();
+        let mut files = BTreeSet::new();
+        for file in self.loaded.clone() {
+            let text = read_file(Path::new(file.as_str())).unwrap_or_else(|error| {
+                println!("error: {error}");
+                String::new()
+            });
+            self.sources.insert(file.clone(), text.clone());
+            set_source_file(file.clone(), text, &mut self.compiler);
+
+            let (new_files, new_errors) = self.collect_files(file);
+            files.extend(new_files);
+            errors.extend(new_errors);
+        }
+
+        files.extend(self.entries.iter().cloned());
+        errors.extend(files.iter().flat_map(|file| CheckFile { file_name: file.clone() }.get(&self.compiler)));
+
+        print_errors(&errors, self.options.message_format, &self.compiler);
+        if !contains_errors(&errors) {
+            println!("Ok, {} files checked", files.len());
+        }
+    }
+
+    /// Create a new REPL entry containing the given text and check it for errors. Returns the file
+    /// name of the entry and the number of statements in its implicit header of imports, or `None`
+    /// if the entry had any errors.
+    fn add_entry(&mut self, text: &str) -> Option<(Arc<String>, usize)> {
+        self.entry_count += 1;
+        let entry = Arc::new(format!("repl_{}.ex", self.entry_count));
+
+        // Names defined in this entry shadow any previous definitions of them. Checking which names
+        // are defined only requires parsing.
+        set_source_file(entry.clone(), text.to_string(), &mut self.compiler);
+        let (shadowed, _) = ExportedDefinitions { file_name: entry.clone() }.get(&self.compiler);
+
+        let mut source = String::new();
+        for file in self.loaded.iter() {
+            source += &format!("import {}\n", backend::module_name(file));
+        }
+
+        let entry_imports = self.visible_names(&shadowed);
+        for (file, names) in entry_imports.iter() {
+            let names: Vec<_> = names.iter().map(|name| name.as_str()).collect();
+            source += &format!("import {} ({})\n", backend::module_name(file), names.join(", "));
+        }
+        let header_length = self.loaded.len() + entry_imports.len();
+        source += text;
+
+        self.sources.insert(entry.clone(), source.clone());
+        set_source_file(entry.clone(), source, &mut self.compiler);
+
+        // Only check the files new to us so we don't report errors in previously loaded files again
+        let known_files: BTreeSet<_> = self.sources.keys().cloned().collect();
+        let (files, mut errors) = self.collect_files(entry.clone());
+        let new_files = files.into_iter().filter(|file| !known_files.contains(file) || *file == entry);
+        errors.extend(new_files.flat_map(|file| CheckFile { file_name: file }.get(&self.compiler)));
+
+        print_errors(&errors, self.options.message_format, &self.compiler);
+
+        // Entries with errors are never remembered, otherwise we'd report the same errors again each
+        // time a later entry imports them. Entries with only warnings, e.g. for typed holes, are still loaded.
+        if contains_errors(&errors) { None } else { Some((entry, header_length)) }
+    }
+
+    /// Each entry to import along with the names to import from it: those it was the latest entry to
+    /// define which aren't in `shadowed`. Since a listed name is imported from every namespace it is in,
+    /// a name is left out if it is shadowed in any namespace. Entries defining instances are always
+    /// imported, even if no names are.
+    fn visible_names(&self, shadowed: &Definitions) -> BTreeMap<&Arc<String>, BTreeSet<&Arc<String>>> {
+        let mut imports: BTreeMap<_, BTreeSet<_>> =
+            self.instance_entries.iter().map(|entry| (entry, BTreeSet::new())).collect();
+
+        let names = self.definitions.iter().chain(&self.types).chain(&self.classes);
+        for (name, entry) in names {
+            let is_shadowed = shadowed.values.contains_key(name)
+                || shadowed.types.contains_key(name)
+                || shadowed.classes.contains_key(name);
+
+            if !is_shadowed {
+                imports.entry(entry).or_default().insert(name);
+            }
+        }
+        imports
+    }
+
+    /// Find each file imported by `file`, reading any we haven't seen before from disk
+    fn collect_files(&mut self, file: Arc<String>) -> (BTreeSet<Arc<String>>, Errors) {
+        let new_sources = RefCell::new(BTreeMap::new());
+        let read_source = |file: &Arc<String>| match self.sources.get(file) {
+            Some(text) => Ok(text.clone()),
+            None => {
+                let text = read_file(Path::new(file.as_str()))?;
+                new_sources.borrow_mut().insert(file.clone(), text.clone());
+                Ok(text)
+            },
+        };
+
+        let result = collect_all_changed_files_with(file, &read_source, &mut self.compiler);
+        self.sources.extend(new_sources.into_inner());
+        result
+    }
+
+    fn run_statement(&self, id: &TopLevelId) {
+        match interpreter::evaluate(id, &self.compiler) {
+            Ok(Some(value)) => println!("{value}"),
+            Ok(None) => (),
+            Err(error) => println!("error: {error}"),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Finding which definitions must be type checked together.
+//!
+//! The type of a definition without an annotation depends on the types of each definition its
+//! body refers to. If those definitions (eventually) refer back to it, we can't infer any of them
+//! by querying the types of the others first - `GetType` would end up depending on itself. Instead,
+//! each strongly connected component of the dependency graph is inferred at once as a recursive
+//! group, and only generalized once every definition in the group has been checked.
+//!
+//! Definitions with type annotations never depend on anything for their type, so they have no
+//! outgoing edges. This means annotating any definition in a cycle breaks the cycle.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use crate::{
+    incremental::{
+        self, CompilerHandle, DefinitionDependencies, GetStatement, Parse, RecursiveGroup, RecursiveGroups, Resolve,
+    },
+    name_resolution::Origin,
+    parser::{ast::TopLevelStatement, ids::TopLevelId},
+};
+
+/// Maps each definition to the definitions its type depends on
+pub type DependencyGraph = BTreeMap<TopLevelId, BTreeSet<TopLevelId>>;
+
+/// Build the dependency graph of each definition in a single file. Edges may point to definitions
+/// in other files, so `RecursiveGroups` follows them through the graphs of those files.
+pub fn definition_dependencies_impl(context: &DefinitionDependencies, compiler: &CompilerHandle) -> DependencyGraph {
+    incremental::enter_query();
+    incremental::println(format!("Collecting definition dependencies of {}", context.file_name));
+
+    let ast = Parse { file_name: context.file_name.clone() }.get(compiler).ast;
+    let mut graph = DependencyGraph::new();
+
+    for statement in ast.statements.iter() {
+        if let TopLevelStatement::Definition(definition) = statement {
+            let mut dependencies = BTreeSet::new();
+
+            if definition.typ.is_none() {
+                for origin in Resolve(definition.id.clone()).get(compiler).origins.into_values() {
+                    if let Origin::TopLevelDefinition(dependency) = origin {
+                        dependencies.insert(dependency);
+                    }
+                }
+            }
+            graph.insert(definition.id.clone(), dependencies);
+        }
+    }
+
+    incremental::exit_query();
+    graph
+}
+
+/// Find the strongly connected component containing each definition in a file using Tarjan's algorithm.
+/// Each group is sorted, so each definition in the same group maps to the same list. Edges into
+/// other files are followed so that groups spanning several files are found as well.
+pub fn recursive_groups_impl(
+    context: &RecursiveGroups, compiler: &CompilerHandle,
+) -> BTreeMap<TopLevelId, Vec<TopLevelId>> {
+    incremental::enter_query();
+    incremental::println(format!("Finding recursive groups in {}", context.file_name));
+
+    let mut finder = ComponentFinder::new(compiler);
+    let graph = finder.graph(&context.file_name).clone();
+    for id in graph.keys() {
+        if !finder.indices.contains_key(id) {
+            finder.visit(id.clone());
+        }
+    }
+
+    let mut groups = BTreeMap::new();
+    for component in finder.components {
+        for id in component.iter().filter(|id| graph.contains_key(*id)) {
+            groups.insert(id.clone(), component.clone());
+        }
+    }
+
+    incremental::exit_query();
+    groups
+}
+
+/// Find the group containing the given definition: each definition which both depends on it and
+/// which it depends on, including itself. Statements other than definitions are always in a group
+/// of their own.
+pub fn recursive_group_impl(context: &RecursiveGroup, compiler: &CompilerHandle) -> Vec<TopLevelId> {
+    incremental::enter_query();
+    let statement = GetStatement(context.0.clone()).get(compiler);
+    incremental::println(format!("Finding recursive group of {statement}"));
+
+    let mut groups = RecursiveGroups { file_name: context.0.file_path.clone() }.get(compiler);
+    let group = groups.remove(&context.0).unwrap_or_else(|| vec![context.0.clone()]);
+
+    incremental::exit_query();
+    group
+}
+
+struct ComponentFinder<'local, 'inner> {
+    compiler: &'local CompilerHandle<'inner>,
+
+    /// The dependency graph of each file visited so far
+    graphs: BTreeMap<Arc<String>, DependencyGraph>,
+
+    /// The order each definition was first visited in
+    indices: BTreeMap<TopLevelId, usize>,
+
+    /// Definitions visited whose component hasn't been found yet
+    stack: Vec<TopLevelId>,
+    on_stack: BTreeSet<TopLevelId>,
+
+    components: Vec<Vec<TopLevelId>>,
+}
+
+impl<'local, 'inner> ComponentFinder<'local, 'inner> {
+    fn new(compiler: &'local CompilerHandle<'inner>) -> Self {
+        Self {
+            compiler,
+            graphs: BTreeMap::new(),
+            indices: BTreeMap::new(),
+            stack: Vec::new(),
+            on_stack: BTreeSet::new(),
+            components: Vec::new(),
+        }
+    }
+
+    fn graph(&mut self, file_name: &Arc<String>) -> &DependencyGraph {
+        let compiler = self.compiler;
+        self.graphs
+            .entry(file_name.clone())
+            .or_insert_with(|| DefinitionDependencies { file_name: file_name.clone() }.get(compiler))
+    }
+
+    /// Visit a definition and everything reachable from it which hasn't been visited yet. Returns the
+    /// lowest index of any definition on the stack reachable from it. If that is its own index, no
+    /// definition visited before it can be reached so it and everything above it on the stack form
+    /// a component.
+    fn visit(&mut self, id: TopLevelId) -> usize {
+        let index = self.indices.len();
+        self.indices.insert(id.clone(), index);
+        self.stack.push(id.clone());
+        self.on_stack.insert(id.clone());
+
+        let dependencies = self.graph(&id.file_path).get(&id).cloned().unwrap_or_default();
+        let mut lowest_index = index;
+
+        for dependency in dependencies {
+            match self.indices.get(&dependency) {
+                None => lowest_index = lowest_index.min(self.visit(dependency)),
+                Some(&dependency_index) if self.on_stack.contains(&dependency) => {
+                    lowest_index = lowest_index.min(dependency_index);
+                },
+                // Already in a component found earlier
+                Some(_) => (),
+            }
+        }
+
+        if lowest_index == index {
+            let mut component = Vec::new();
+            loop {
+                let member = self.stack.pop().expect("Each definition being visited is on the stack");
+                self.on_stack.remove(&member);
+                let done = member == id;
+                component.push(member);
+                if done {
+                    break;
+                }
+            }
+            component.sort();
+            self.components.push(component);
+        }
+
+        lowest_index
+    }
+}
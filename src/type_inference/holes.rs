@@ -0,0 +1,216 @@
+//! Typed holes. A hole is written `_` or `?name` in place of an expression which hasn't been written
+//! yet. Type inference gives each hole whatever type its surroundings need, then this pass reports
+//! that type as a warning along with each name in scope which has it, so the program can still be
+//! checked and run up to the point a hole is reached.
+//!
+//! A name fits in a hole if its type unifies with the hole's type. Any type variables still left in
+//! the hole's type may be bound while checking this, but generics of the enclosing definition may
+//! not, since the hole must work for any type they're given.
+use std::{collections::BTreeSet, sync::Arc};
+
+use crate::{
+    builtins::BUILTINS,
+    errors::{Error, Errors},
+    incremental::{self, CheckHoles, CompilerHandle, GetStatement, GetType, TypeCheck, VisibleDefinitions},
+    name_resolution::Origin,
+    parser::{
+        ast::{Expression, TopLevelStatement},
+        ids::{ExprId, TopLevelId},
+    },
+    type_inference::{
+        types::{TopLevelDefinitionType, Type, TypeBindings},
+        ClassSignature, ConstructorSignature, TypeChecker,
+    },
+};
+
+/// Report each hole within a top-level statement
+pub fn check_holes_impl(context: &CheckHoles, compiler: &CompilerHandle) -> Errors {
+    incremental::enter_query();
+    let statement = GetStatement(context.0.clone()).get(compiler);
+    incremental::println(format!("Checking holes in {statement}"));
+
+    let mut finder = HoleFinder { holes: Vec::new(), locals: Vec::new() };
+    match &statement {
+        TopLevelStatement::Import { .. }
+        | TopLevelStatement::TypeDefinition(_)
+        | TopLevelStatement::TypeAlias(_)
+        | TopLevelStatement::Class(_) => (),
+        TopLevelStatement::Definition(definition) => finder.find_holes(&definition.body),
+        TopLevelStatement::Print(expression, _) => finder.find_holes(expression),
+        TopLevelStatement::Instance(instance) => {
+            for (_, body) in instance.methods.iter() {
+                finder.find_holes(body);
+            }
+        },
+    }
+
+    // Avoid depending on the types of every name in scope when there are no holes to fill
+    let errors = if finder.holes.is_empty() {
+        Vec::new()
+    } else {
+        let expr_types = TypeCheck(context.0.clone()).get(compiler).expr_types;
+        let globals = global_candidates(&context.0, compiler);
+
+        let errors = finder.holes.into_iter().map(|hole| {
+            let typ = expr_types[&hole.id].clone();
+            let locals = hole.locals.iter().map(|(name, id)| (name.clone(), expr_types[id].clone()));
+            let candidates = fitting_names(&typ, locals, &globals, &context.0, hole.id, compiler);
+            let location = hole.id.location(&context.0, compiler);
+            Error::TypedHole { name: hole.name, typ, candidates, location }
+        });
+        errors.collect()
+    };
+
+    incremental::exit_query();
+    errors
+}
+
+struct Hole {
+    name: Option<Arc<String>>,
+    id: ExprId,
+    /// Each local variable in scope at the hole, by the id of its name. Later locals shadow earlier ones.
+    locals: Vec<(Arc<String>, ExprId)>,
+}
+
+struct HoleFinder {
+    holes: Vec<Hole>,
+    locals: Vec<(Arc<String>, ExprId)>,
+}
+
+impl HoleFinder {
+    fn find_holes(&mut self, expression: &Expression) {
+        match expression {
+            Expression::IntegerLiteral(..)
+            | Expression::BoolLiteral(..)
+            | Expression::StringLiteral(..)
+            | Expression::Variable(_) => (),
+            Expression::Hole { name, id } => {
+                self.holes.push(Hole { name: name.clone(), id: *id, locals: self.locals.clone() });
+            },
+            Expression::FunctionCall { function, argument, id: _ } => {
+                self.find_holes(function);
+                self.find_holes(argument);
+            },
+            Expression::Lambda { parameter, body, id: _ } => {
+                let variables = parameter.variables().into_iter().map(|variable| (variable.name.clone(), variable.id));
+                self.find_holes_in_scope(variables.collect(), body);
+            },
+            Expression::If { condition, then_branch, else_branch, id: _ } => {
+                self.find_holes(condition);
+                self.find_holes(then_branch);
+                self.find_holes(else_branch);
+            },
+            Expression::Let { name, value, body, id: _ } => {
+                self.find_holes(value);
+                self.find_holes_in_scope(vec![(name.name.clone(), name.id)], body);
+            },
+            Expression::Match { scrutinee, arms, id: _ } => {
+                self.find_holes(scrutinee);
+                for arm in arms {
+                    let variables = arm.pattern.variables().into_iter();
+                    let variables = variables.map(|variable| (variable.name.clone(), variable.id));
+                    self.find_holes_in_scope(variables.collect(), &arm.body);
+                }
+            },
+            Expression::Tuple { elements, id: _ } | Expression::List { elements, id: _ } => {
+                for element in elements {
+                    self.find_holes(element);
+                }
+            },
+            Expression::Record { fields, id: _ } => {
+                for (_, value) in fields {
+                    self.find_holes(value);
+                }
+            },
+            Expression::FieldAccess { record, .. } => self.find_holes(record),
+        }
+    }
+
+    fn find_holes_in_scope(&mut self, locals: Vec<(Arc<String>, ExprId)>, expression: &Expression) {
+        let count = self.locals.len();
+        self.locals.extend(locals);
+        self.find_holes(expression);
+        self.locals.truncate(count);
+    }
+}
+
+/// The type of each top-level definition, constructor, method, and built-in function usable in the
+/// statement's file, sorted by name. Operators are left out since they're rarely what a hole is missing.
+fn global_candidates(item: &TopLevelId, compiler: &CompilerHandle) -> Vec<(Arc<String>, TopLevelDefinitionType)> {
+    let (definitions, _) = VisibleDefinitions { file_name: item.file_path.clone() }.get(compiler);
+
+    let mut candidates: Vec<_> = definitions
+        .values
+        .iter()
+        .filter_map(|(name, origin)| {
+            let typ = match origin {
+                Origin::TopLevelDefinition(id) => GetType(id.clone()).get(compiler),
+                Origin::Constructor(type_id, name) => ConstructorSignature::new(type_id, name, compiler).typ(),
+                Origin::Method(class_id, name) => ClassSignature::new(class_id, compiler).method_type(name),
+                _ => return None,
+            };
+            Some((name.clone(), typ))
+        })
+        .collect();
+
+    for builtin in BUILTINS {
+        let name = Arc::new(builtin.name().to_string());
+        let is_operator = !name.starts_with(|c: char| c.is_alphabetic());
+        if !is_operator && !definitions.values.contains_key(&name) {
+            let typ = builtin.typ();
+            candidates.push((name, TopLevelDefinitionType::new(typ.find_all_generics(), typ)));
+        }
+    }
+
+    candidates.sort_by(|(a, _), (b, _)| a.cmp(b));
+    candidates
+}
+
+/// The names of each local and global which fit in a hole of the given type. Locals are listed first,
+/// innermost first, and shadow any other name they share.
+fn fitting_names(
+    hole_type: &Type, locals: impl DoubleEndedIterator<Item = (Arc<String>, Type)>,
+    globals: &[(Arc<String>, TopLevelDefinitionType)], item: &TopLevelId, hole: ExprId, compiler: &CompilerHandle,
+) -> Vec<Arc<String>> {
+    // Anything fits in a hole whose type is already an error, so listing names would only add noise
+    if *hole_type == Type::Error {
+        return Vec::new();
+    }
+
+    let mut checker = TypeChecker::new(compiler);
+    checker.item = Some(item.clone());
+
+    let locals = locals.rev().map(|(name, typ)| (name, TopLevelDefinitionType::new(Vec::new(), typ)));
+    let candidates = locals.chain(globals.iter().cloned());
+    let mut seen = BTreeSet::new();
+    let mut names = Vec::new();
+
+    for (name, typ) in candidates {
+        // A shadowed name is never suggested, even if it would fit
+        if seen.insert(name.clone()) && typ.typ != Type::Error && fits(&mut checker, hole_type, &typ, hole) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// True if a name of the given type could be used in place of the hole. Any bindings made to check
+/// this are undone afterward so that each candidate is checked independently.
+fn fits(checker: &mut TypeChecker, hole_type: &Type, candidate: &TopLevelDefinitionType, hole: ExprId) -> bool {
+    // Type variables left over from type checking the statement don't exist in this checker yet
+    for typ in [hole_type, &candidate.typ] {
+        for type_variable in typ.free_type_variables(&TypeBindings::new()) {
+            checker.type_variable_levels.entry(type_variable).or_insert(0);
+            checker.next_id = checker.next_id.max(type_variable.0 + 1);
+        }
+    }
+
+    let bindings = checker.bindings.clone();
+    let typ = checker.instantiate(candidate);
+    checker.unify(&typ, hole_type, hole);
+
+    let fits = checker.errors.is_empty();
+    checker.errors.clear();
+    checker.bindings = bindings;
+    fits
+}
@@ -1,19 +1,29 @@
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
+use inc_complete::DbGet;
 use serde::{Deserialize, Serialize};
 use types::TypeBindings;
 
 use crate::{
+    builtins::{builtin_instance, Builtin, BuiltinClass},
     errors::{Error, Errors},
-    incremental::{self, CompilerHandle, GetStatement, GetType, Resolve, TypeCheck},
-    name_resolution::{Origin, ResolutionResult},
+    incremental::{
+        self, parse, CheckFile, CheckHoles, CheckMatches, CompilerHandle, ExpandTypeAlias, GetStatement, GetType,
+        Instances, RecursiveGroup, Resolve, TypeCheck, TypeCheckGroup, VisibleDefinitions, VisibleInstances,
+    },
+    name_resolution::{class_id, Origin},
     parser::{
-        ast::{Expression, TopLevelStatement},
+        ast::{Expression, Identifier, InstanceDefinition, Pattern, TopLevelStatement},
         ids::{ExprId, TopLevelId},
     },
-    type_inference::types::{TopLevelDefinitionType, Type, TypeVariableId},
+    type_inference::types::{Constraint, Substitutions, TopLevelDefinitionType, Type, TypePosition, TypeVariableId},
 };
 
+pub mod dependencies;
+pub mod holes;
 pub mod types;
 
 /// Get the type of the name defined by this TopLevelItemId.
@@ -32,9 +42,12 @@ pub fn get_type_impl(context: &GetType, compiler: &CompilerHandle) -> TopLevelDe
     let typ = match statement {
         TopLevelStatement::Import { .. } => TopLevelDefinitionType::unit(),
         TopLevelStatement::Print(..) => TopLevelDefinitionType::unit(),
+        TopLevelStatement::TypeDefinition(_) | TopLevelStatement::TypeAlias(_) => TopLevelDefinitionType::unit(),
+        TopLevelStatement::Class(_) | TopLevelStatement::Instance(_) => TopLevelDefinitionType::unit(),
         TopLevelStatement::Definition(definition) => {
             if let Some(typ) = &definition.typ {
-                TopLevelDefinitionType::from_ast_type(typ)
+                let origins = Resolve(context.0.clone()).get(compiler).origins;
+                TopLevelDefinitionType::from_annotation(&definition, typ, &origins, compiler)
             } else {
                 let result = compiler.get(TypeCheck(context.0.clone()));
                 result.typ.clone()
@@ -48,74 +61,476 @@ pub fn get_type_impl(context: &GetType, compiler: &CompilerHandle) -> TopLevelDe
 /// Actually type check a statement and its contents.
 /// Unlike `get_type_impl`, this always type checks the expressions inside a statement
 /// to ensure they type check correctly.
+///
+/// If the statement is a definition in a recursive group with other definitions, the whole
+/// group is checked at once by `TypeCheckGroup` and we only return the part for this definition.
 pub fn type_check_impl(context: &TypeCheck, compiler: &CompilerHandle) -> TypeCheckResult {
     incremental::enter_query();
     let statement = GetStatement(context.0.clone()).get(compiler);
     incremental::println(format!("Type checking {statement}"));
 
-    let resolve = Resolve(context.0.clone()).get(compiler);
-    let mut checker = TypeChecker::new(context.0.clone(), resolve, compiler);
+    let group = match statement {
+        TopLevelStatement::Definition(_) => RecursiveGroup(context.0.clone()).get(compiler),
+        _ => vec![context.0.clone()],
+    };
 
-    let typ = match statement {
-        TopLevelStatement::Import { .. } => TopLevelDefinitionType::unit(),
-        TopLevelStatement::Print(expression, _) => {
-            checker.check_expr(&expression);
-            TopLevelDefinitionType::unit()
-        },
-        TopLevelStatement::Definition(definition) => {
-            let actual_type = checker.check_expr(&definition.body);
+    let mut results =
+        if group.len() == 1 { check_group(&group, compiler) } else { TypeCheckGroup(group[0].clone()).get(compiler) };
 
-            if let Some(typ) = &definition.typ {
-                let expected = Type::from_ast_type(typ);
+    incremental::exit_query();
+    results.remove(&context.0).expect("Each statement in a group should have a result")
+}
+
+/// Type check each definition in a recursive group together, returning the results for each.
+pub fn type_check_group_impl(context: &TypeCheckGroup, compiler: &CompilerHandle) -> BTreeMap<TopLevelId, TypeCheckResult> {
+    incremental::enter_query();
+    let group = RecursiveGroup(context.0.clone()).get(compiler);
+    let names: Vec<_> = group.iter().map(|id| definition_name(id, compiler).to_string()).collect();
+    incremental::println(format!("Type checking recursive group {}", names.join(", ")));
+
+    let results = check_group(&group, compiler);
+    incremental::exit_query();
+    results
+}
+
+/// Type check a group of statements together. Within the group, each definition without an
+/// annotation has a single type which every use of it in the group shares, so the types of these
+/// definitions are only generalized after every statement in the group has been checked.
+///
+/// Constraints are solved last, once we know which type variables were generalized. Any constraint
+/// left on a generic of a definition without an annotation becomes a constraint of its type.
+fn check_group(group: &[TopLevelId], compiler: &CompilerHandle) -> BTreeMap<TopLevelId, TypeCheckResult> {
+    let statements: Vec<_> = group.iter().map(|id| GetStatement(id.clone()).get(compiler)).collect();
+    let mut checker = TypeChecker::new(compiler);
+    checker.enter_level();
+
+    for (id, statement) in group.iter().zip(&statements) {
+        if let TopLevelStatement::Definition(definition) = statement
+            && definition.typ.is_none()
+        {
+            let typ = checker.next_type_variable();
+            checker.group_types.insert(id.clone(), typ);
+        }
+    }
+
+    for (id, statement) in group.iter().zip(&statements) {
+        checker.start_statement(id.clone());
+
+        match statement {
+            TopLevelStatement::Import { .. }
+            | TopLevelStatement::TypeDefinition(_)
+            | TopLevelStatement::TypeAlias(_)
+            | TopLevelStatement::Class(_) => (),
+            TopLevelStatement::Print(expression, _) => {
+                checker.check_expr(expression);
+            },
+            TopLevelStatement::Instance(instance) => checker.check_instance(instance),
+            TopLevelStatement::Definition(definition) => {
+                let actual_type = checker.check_expr(&definition.body);
+                let expected = match &definition.typ {
+                    Some(typ) => Type::from_annotation(typ, definition.forall.as_deref(), &checker.origins, compiler),
+                    None => checker.group_types[id].clone(),
+                };
                 checker.unify(&actual_type, &expected, definition.body.id());
-                checker.generalize(&expected)
-            } else {
-                checker.generalize(&actual_type)
-            }
-        },
+            },
+        }
+    }
+
+    checker.exit_level();
+    checker.unify_recursive_uses();
+
+    let types = group.iter().zip(statements).map(|(id, statement)| {
+        let typ = match statement {
+            TopLevelStatement::Import { .. }
+            | TopLevelStatement::Print(..)
+            | TopLevelStatement::TypeDefinition(_)
+            | TopLevelStatement::TypeAlias(_)
+            | TopLevelStatement::Class(_)
+            | TopLevelStatement::Instance(_) => TopLevelDefinitionType::unit(),
+            TopLevelStatement::Definition(definition) => match &definition.typ {
+                Some(typ) => {
+                    let origins = Resolve(id.clone()).get(compiler).origins;
+                    let constraints = Constraint::from_ast_constraints(&definition.constraints, &origins);
+                    let typ = Type::from_annotation(typ, definition.forall.as_deref(), &origins, compiler);
+                    checker.generalize(&typ).with_constraints(constraints)
+                },
+                None => checker.generalize(&checker.group_types[id].clone()),
+            },
+        };
+        (id.clone(), typ)
+    });
+
+    let types: Vec<_> = types.collect();
+    let types = checker.solve_constraints(types);
+    checker.finish(types)
+}
+
+/// Type check each statement in a file, collecting every error in the file along the way.
+pub fn check_file_impl(context: &CheckFile, compiler: &CompilerHandle) -> Errors {
+    incremental::enter_query();
+    incremental::println(format!("Checking {}", context.file_name));
+
+    // Ignore errors for this parse, they'll be included in the VisibleDefinitions call.
+    let ast = parse(context.file_name.clone(), compiler).0;
+
+    // inc-complete doesn't currently provide an accumulator abstraction so we have to manually
+    // call VisibleDefinitions to collect the errors that are discarded in resolution.
+    let (_, mut errors) = VisibleDefinitions { file_name: context.file_name.clone() }.get(compiler);
+    errors.extend(VisibleInstances { file_name: context.file_name.clone() }.get(compiler).1);
+
+    for statement in ast.statements.iter() {
+        let results = TypeCheck(statement.id().clone()).get(compiler);
+        errors.extend(results.errors);
+        errors.extend(CheckMatches(statement.id().clone()).get(compiler));
+        errors.extend(CheckHoles(statement.id().clone()).get(compiler));
+
+        if let TopLevelStatement::TypeAlias(alias) = statement {
+            errors.extend(ExpandTypeAlias(alias.id.clone()).get(compiler).1);
+        }
+    }
+
+    incremental::exit_query();
+    errors
+}
+
+/// Expand a type alias, which is generic over the alias's parameters. An alias which refers to
+/// itself, directly or through other aliases, expands to `Type::Error` instead.
+pub fn expand_type_alias_impl(
+    context: &ExpandTypeAlias, compiler: &CompilerHandle,
+) -> (TopLevelDefinitionType, Errors) {
+    incremental::enter_query();
+    let TopLevelStatement::TypeAlias(alias) = GetStatement(context.0.clone()).get(compiler) else {
+        unreachable!("ExpandTypeAlias should only be called on type aliases")
+    };
+    incremental::println(format!("Expanding type alias {}", alias.name));
+
+    let generics = alias.generics.iter().map(|generic| generic.name.clone()).collect();
+    let result = if refers_to_alias(&context.0, &context.0, &mut BTreeSet::new(), compiler) {
+        let location = alias.name.id.location(&context.0, compiler);
+        let error = Error::RecursiveTypeAlias { name: alias.name.name.clone(), location };
+        (TopLevelDefinitionType::new(generics, Type::Error), vec![error])
+    } else {
+        let origins = Resolve(context.0.clone()).get(compiler).origins;
+        (TopLevelDefinitionType::new(generics, Type::from_ast_type(&alias.typ, &origins, compiler)), Vec::new())
     };
 
     incremental::exit_query();
-    checker.finish(typ)
+    result
+}
+
+/// True if the type alias `alias` uses `target`, either directly or through the other aliases it uses.
+/// Each alias searched is added to `visited` so that we stop at any cycle which doesn't include `target`.
+fn refers_to_alias(
+    alias: &TopLevelId, target: &TopLevelId, visited: &mut BTreeSet<TopLevelId>, compiler: &CompilerHandle,
+) -> bool {
+    for origin in Resolve(alias.clone()).get(compiler).origins.into_values() {
+        if let Origin::TypeAlias(used) = origin
+            && (used == *target || (visited.insert(used.clone()) && refers_to_alias(&used, target, visited, compiler)))
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// The signature of a constructor of a type defined by a `type` statement
+pub struct ConstructorSignature {
+    /// The generics of the type being constructed
+    pub generics: Vec<Arc<String>>,
+    pub fields: Vec<Type>,
+    /// The type being constructed, applied to each of its generics
+    pub result: Type,
+}
+
+impl ConstructorSignature {
+    /// Look up the constructor with the given name defined by the `type` statement `type_id`
+    pub fn new(
+        type_id: &TopLevelId, name: &str,
+        compiler: &(impl DbGet<GetStatement> + DbGet<Resolve> + DbGet<ExpandTypeAlias>),
+    ) -> ConstructorSignature {
+        let TopLevelStatement::TypeDefinition(definition) = GetStatement(type_id.clone()).get(compiler) else {
+            unreachable!("Constructors are only defined by type definitions")
+        };
+        let origins = Resolve(type_id.clone()).get(compiler).origins;
+
+        let constructor = definition.constructors.iter().find(|constructor| constructor.name.name.as_str() == name);
+        let constructor = constructor.expect("Constructor origins should refer to a constructor of the type");
+        let fields = constructor.fields.iter().map(|field| Type::from_ast_type(field, &origins, compiler)).collect();
+
+        let generics: Vec<_> = definition.generics.iter().map(|generic| generic.name.clone()).collect();
+        let arguments = generics.iter().map(|generic| Type::Generic(generic.clone())).collect();
+        let result = Type::Data { id: type_id.clone(), name: definition.name.name, arguments };
+        ConstructorSignature { generics, fields, result }
+    }
+
+    /// The type of the constructor when used as a function, e.g. `forall a. a -> Option a` for `Some`
+    pub fn typ(&self) -> TopLevelDefinitionType {
+        let typ = self.fields.iter().rev().fold(self.result.clone(), |return_type, field| Type::Function {
+            parameter: Arc::new(field.clone()),
+            return_type: Arc::new(return_type),
+        });
+        TopLevelDefinitionType::new(self.generics.clone(), typ)
+    }
+}
+
+/// The methods of a class defined by a `class` statement
+pub struct ClassSignature {
+    pub id: TopLevelId,
+    pub name: Arc<String>,
+    /// The generic standing in for the type of each instance of the class
+    pub generic: Arc<String>,
+    pub methods: Vec<(Arc<String>, Type)>,
+}
+
+impl ClassSignature {
+    pub fn new(
+        class_id: &TopLevelId, compiler: &(impl DbGet<GetStatement> + DbGet<Resolve> + DbGet<ExpandTypeAlias>),
+    ) -> ClassSignature {
+        if let Some(class) = BuiltinClass::from_id(class_id) {
+            return ClassSignature::builtin(class);
+        }
+
+        let TopLevelStatement::Class(class) = GetStatement(class_id.clone()).get(compiler) else {
+            unreachable!("Class origins should refer to a class")
+        };
+        let origins = Resolve(class_id.clone()).get(compiler).origins;
+
+        let methods = class.methods.iter().map(|(name, typ)| {
+            let typ = Type::from_ast_type(typ, &origins, compiler);
+            (name.name.clone(), typ)
+        });
+        let methods = methods.collect();
+        ClassSignature { id: class_id.clone(), name: class.name.name, generic: class.generic.name, methods }
+    }
+
+    /// The signature of a built-in class, whose methods are its operators named by their `method_name`
+    fn builtin(class: BuiltinClass) -> ClassSignature {
+        let methods = class.methods().map(|method| (Arc::new(method.method_name().to_string()), method.typ()));
+        let (name, generic) = (Arc::new(class.name().to_string()), Arc::new("a".to_string()));
+        ClassSignature { id: class.id(), name, generic, methods: methods.collect() }
+    }
+
+    /// The type of a method when used in an expression, e.g. `forall a. Show a => a -> String` for `show`
+    pub fn method_type(&self, name: &str) -> TopLevelDefinitionType {
+        let method = self.methods.iter().find(|(method, _)| method.as_str() == name);
+        let (_, typ) = method.expect("Method origins should refer to a method of the class");
+
+        let mut generics = typ.find_all_generics();
+        if !generics.contains(&self.generic) {
+            generics.push(self.generic.clone());
+        }
+
+        let (class, class_name) = (self.id.clone(), self.name.clone());
+        let constraint = Constraint { class, class_name, typ: Type::Generic(self.generic.clone()) };
+        TopLevelDefinitionType::new(generics, typ.clone()).with_constraints(vec![constraint])
+    }
+
+    /// The type a method must have in an instance for the given type. Any generics of the method
+    /// other than the class's own are renamed so they can't clash with those of the instance's type.
+    pub fn instance_method_type(&self, name: &str, instance_type: &Type) -> Option<Type> {
+        let (_, typ) = self.methods.iter().find(|(method, _)| method.as_str() == name)?;
+        let mut names_in_use = instance_type.find_all_generics();
+        let mut next_name = 0;
+
+        let substitutions = typ.find_all_generics().into_iter().map(|generic| {
+            if generic == self.generic {
+                (generic, instance_type.clone())
+            } else {
+                let name = fresh_generic_name(&names_in_use, &mut next_name);
+                names_in_use.push(name.clone());
+                (generic, Type::Generic(name))
+            }
+        });
+        let substitutions = substitutions.collect();
+        Some(typ.substitute(&substitutions, &TypeBindings::new()))
+    }
+}
+
+/// The type and context of an instance defined by an `instance` statement
+pub struct InstanceSignature {
+    pub typ: Type,
+    /// The constraints on the generics of `typ` which the instance requires, e.g. `Show a` in
+    /// `instance Show a => Show (List a)`
+    pub context: Vec<Constraint>,
+}
+
+impl InstanceSignature {
+    pub fn new(
+        instance_id: &TopLevelId, compiler: &(impl DbGet<GetStatement> + DbGet<Resolve> + DbGet<ExpandTypeAlias>),
+    ) -> Self {
+        // Built-in instances are all for types without arguments, so they never need a context
+        if let Some((_, typ)) = builtin_instance(instance_id) {
+            return InstanceSignature { typ, context: Vec::new() };
+        }
+
+        let TopLevelStatement::Instance(instance) = GetStatement(instance_id.clone()).get(compiler) else {
+            unreachable!("Evidence should refer to an instance")
+        };
+        let origins = Resolve(instance_id.clone()).get(compiler).origins;
+        let typ = Type::from_ast_type(&instance.typ, &origins, compiler);
+        InstanceSignature { typ, context: Constraint::from_ast_constraints(&instance.constraints, &origins) }
+    }
+}
+
+/// How a use of a definition or method with constraints is given the instance for each of them.
+/// This is the argument passed for each constraint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Evidence {
+    /// Use this instance, given the evidence for each constraint of its context
+    Instance(TopLevelId, Vec<Evidence>),
+    /// Use the evidence given for the constraint at this index of the enclosing statement,
+    /// whether those are the constraints of a definition's type or the context of an instance
+    Parameter(usize),
 }
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TypeCheckResult {
     pub typ: TopLevelDefinitionType,
     pub expr_types: BTreeMap<ExprId, Type>,
+    /// The evidence for each constraint of each variable referring to a definition or method with constraints
+    pub evidence: BTreeMap<ExprId, Vec<Evidence>>,
     pub errors: Errors,
 }
 
 struct TypeChecker<'local, 'inner> {
     compiler: &'local CompilerHandle<'inner>,
-    origins: BTreeMap<ExprId, Origin>,
-    expr_types: BTreeMap<ExprId, Type>,
     bindings: TypeBindings,
-    item: TopLevelId,
     next_id: u32,
+
+    /// The statement currently being checked, along with its name resolution results
+    /// and the types and errors found in it so far
+    item: Option<TopLevelId>,
+    origins: BTreeMap<ExprId, Origin>,
+    expr_types: BTreeMap<ExprId, Type>,
     errors: Errors,
+
+    /// The generalized type of each let binding in the current statement, by the id of its name
+    let_types: BTreeMap<ExprId, TopLevelDefinitionType>,
+
+    /// The types and errors of each statement in the group we've finished checking
+    finished: BTreeMap<TopLevelId, (BTreeMap<ExprId, Type>, Errors)>,
+
+    /// The type of each definition in the recursive group currently being checked.
+    /// These are not generalized until the whole group is checked.
+    group_types: BTreeMap<TopLevelId, Type>,
+
+    /// Each use of a definition in the group from within the group. Instead of unifying these
+    /// right away, they're unified after the group is checked so that we can tell when a
+    /// definition is used at a different type than its own and needs a type annotation.
+    recursive_uses: Vec<RecursiveUse>,
+
+    /// The level each type variable was created at, lowered as needed when it is unified with a
+    /// type variable from a shallower level. Only type variables deeper than the current level
+    /// can be generalized.
+    type_variable_levels: BTreeMap<TypeVariableId, u32>,
+    level: u32,
+
+    /// Each constraint needed by a use of a definition or method, solved once the group is checked
+    wanted: Vec<Wanted>,
+
+    /// The constraints each statement in the group may use for the generics in its type
+    givens: BTreeMap<TopLevelId, Givens>,
+
+    /// The evidence solving each wanted constraint, or `None` if it couldn't be solved
+    evidence: BTreeMap<TopLevelId, BTreeMap<ExprId, Vec<Option<Evidence>>>>,
+
+    /// The instances visible in each file, cached here since they're needed for each constraint solved
+    instances: BTreeMap<Arc<String>, Instances>,
+}
+
+struct RecursiveUse {
+    item: TopLevelId,
+    variable: ExprId,
+    definition: TopLevelId,
+    typ: Type,
+}
+
+/// A constraint needed by the variable `variable` in `item`. The evidence for each constraint of a
+/// variable is passed in the order its constraints are wanted.
+struct Wanted {
+    item: TopLevelId,
+    variable: ExprId,
+    constraint: Constraint,
+}
+
+#[derive(Default)]
+struct Givens {
+    constraints: Vec<Constraint>,
+    /// The generics of a definition without a type annotation. Instead of being given, constraints
+    /// on these are inferred: any needed is added to `constraints`.
+    inferred_generics: Option<Vec<Arc<String>>>,
 }
 
 impl<'local, 'inner> TypeChecker<'local, 'inner> {
-    fn new(item: TopLevelId, resolve: ResolutionResult, compiler: &'local CompilerHandle<'inner>) -> Self {
+    fn new(compiler: &'local CompilerHandle<'inner>) -> Self {
         Self {
             compiler,
-            item,
             bindings: Default::default(),
-            origins: resolve.origins,
             next_id: 0,
+            item: None,
+            origins: Default::default(),
             expr_types: Default::default(),
-            errors: resolve.errors,
+            errors: Vec::new(),
+            let_types: Default::default(),
+            finished: Default::default(),
+            group_types: Default::default(),
+            recursive_uses: Vec::new(),
+            type_variable_levels: Default::default(),
+            level: 0,
+            wanted: Vec::new(),
+            givens: Default::default(),
+            evidence: Default::default(),
+            instances: Default::default(),
+        }
+    }
+
+    /// Start checking the given statement, setting aside the results of the previous one
+    fn start_statement(&mut self, item: TopLevelId) {
+        let resolve = Resolve(item.clone()).get(self.compiler);
+        self.finish_statement();
+        self.item = Some(item);
+        self.origins = resolve.origins;
+        self.errors = resolve.errors;
+    }
+
+    fn finish_statement(&mut self) {
+        if let Some(item) = self.item.take() {
+            let expr_types = std::mem::take(&mut self.expr_types);
+            let errors = std::mem::take(&mut self.errors);
+            self.let_types.clear();
+            self.finished.insert(item, (expr_types, errors));
         }
     }
 
-    fn finish(self, typ: TopLevelDefinitionType) -> TypeCheckResult {
-        TypeCheckResult { typ, expr_types: self.expr_types, errors: self.errors }
+    fn item(&self) -> &TopLevelId {
+        self.item.as_ref().expect("A statement should be being checked")
+    }
+
+    fn finish(mut self, types: Vec<(TopLevelId, TopLevelDefinitionType)>) -> BTreeMap<TopLevelId, TypeCheckResult> {
+        self.finish_statement();
+
+        types
+            .into_iter()
+            .map(|(id, typ)| {
+                let (expr_types, errors) = self.finished.remove(&id).unwrap_or_default();
+                // The bindings are discarded after type checking so make sure no bound type variables remain
+                let expr_types = expr_types.iter().map(|(id, typ)| (*id, typ.zonk(&self.bindings))).collect();
+                let errors = errors.into_iter().map(|error| zonk_error(error, &self.bindings)).collect();
+
+                // Evidence is only used by programs without errors, which have evidence for every constraint
+                let evidence = self.evidence.remove(&id).unwrap_or_default().into_iter();
+                let evidence = evidence
+                    .filter_map(|(variable, evidence)| Some((variable, evidence.into_iter().collect::<Option<_>>()?)));
+                let evidence = evidence.collect();
+                (id, TypeCheckResult { typ, expr_types, evidence, errors })
+            })
+            .collect()
     }
 
     fn next_type_variable(&mut self) -> Type {
         let id = TypeVariableId(self.next_id);
         self.next_id += 1;
+        self.type_variable_levels.insert(id, self.level);
         Type::TypeVariable(id)
     }
 
@@ -129,95 +544,363 @@ impl<'local, 'inner> TypeChecker<'local, 'inner> {
             // A name resolution error occurred, don't issue a type error as well
             None => Type::Error,
             Some(Origin::Parameter(id)) => self.expr_types.get(id).unwrap().clone(),
+            Some(Origin::LetBinding(id)) => {
+                let typ = self.let_types[id].clone();
+                self.instantiate(&typ)
+            },
+            Some(Origin::Constructor(type_id, name)) => {
+                let typ = ConstructorSignature::new(type_id, name, self.compiler).typ();
+                self.instantiate(&typ)
+            },
+            Some(Origin::Method(class_id, name)) => {
+                let typ = ClassSignature::new(class_id, self.compiler).method_type(name);
+                self.instantiate_with_constraints(&typ, expr)
+            },
+            Some(Origin::TypeDefinition(_) | Origin::TypeAlias(_) | Origin::Class(_)) => {
+                unreachable!("Type and class names are never used as values")
+            },
+            Some(Origin::TopLevelDefinition(id)) if self.group_types.contains_key(id) => {
+                let definition = id.clone();
+                let typ = self.next_type_variable();
+                let item = self.item().clone();
+                self.recursive_uses.push(RecursiveUse { item, variable: expr, definition, typ: typ.clone() });
+                typ
+            },
             Some(Origin::TopLevelDefinition(id)) => {
-                // We may recursively type check here. This can lead to infinite
-                // recursion for mutually recursive functions with inferred types.
-                // To simplify type inference for this toy compiler, we don't handle this case.
+                // Since this definition isn't in our recursive group, it can't depend on our type
+                // so there's no risk of `GetType` depending on itself here.
                 let typ = GetType(id.clone()).get(self.compiler);
-                self.instantiate(&typ)
+                self.instantiate_with_constraints(&typ, expr)
             },
         }
     }
 
-    /// Returns the type of `+` or `-`. Returns `None` if `name` is not `+` or `-`.
-    fn try_get_type_for_builtin(&self, name: &str) -> Option<Type> {
-        if name == "+" || name == "-" {
-            Some(Type::Function {
-                parameter: Arc::new(Type::Int),
-                return_type: Arc::new(Type::Function { parameter: Arc::new(Type::Int), return_type: Arc::new(Type::Int) }),
-            })
-        } else {
-            None
+    /// Unify each recursive use of a definition in the group with the definition's own type.
+    /// If this fails, the definition would need to be generic within its own group - polymorphic
+    /// recursion - which requires a type annotation.
+    fn unify_recursive_uses(&mut self) {
+        self.finish_statement();
+
+        let recursive_uses = std::mem::take(&mut self.recursive_uses);
+        for recursive_use in recursive_uses.iter() {
+            let definition_type = self.group_types[&recursive_use.definition].clone();
+            let actual = recursive_use.typ.zonk(&self.bindings);
+            let expected = definition_type.zonk(&self.bindings);
+
+            self.item = Some(recursive_use.item.clone());
+            self.unify(&recursive_use.typ, &definition_type, recursive_use.variable);
+            self.item = None;
+
+            // Replace any type errors with a single error explaining why the types must match
+            if !self.errors.is_empty() {
+                self.errors.clear();
+                let location = recursive_use.variable.location(&recursive_use.item, self.compiler);
+                let name = definition_name(&recursive_use.definition, self.compiler);
+                let error = Error::RecursiveUseNeedsAnnotation { name, actual, expected, location };
+                self.finished.entry(recursive_use.item.clone()).or_default().1.push(error);
+            }
+        }
+
+        // These are still needed to pass on constraints when solving them
+        self.recursive_uses = recursive_uses;
+    }
+
+    /// Unify the types of both branches of an `if`. A mismatch is reported as a single error
+    /// pointing at both branches since neither branch is more likely to be the wrong one.
+    fn unify_branches(&mut self, then_type: &Type, else_type: &Type, then_branch: ExprId, else_branch: ExprId) {
+        let previous_errors = self.errors.len();
+        self.unify(else_type, then_type, else_branch);
+
+        // Any other errors, e.g. from the occurs check, are still reported as they are
+        let new_errors = self.errors.split_off(previous_errors);
+        let (mismatches, others): (Vec<_>, Vec<_>) =
+            new_errors.into_iter().partition(|error| matches!(error, Error::ExpectedType { .. }));
+        self.errors.extend(others);
+
+        if !mismatches.is_empty() {
+            let then_location = then_branch.location(self.item(), self.compiler);
+            let else_location = else_branch.location(self.item(), self.compiler);
+            let (then_type, else_type) = (then_type.clone(), else_type.clone());
+            self.errors.push(Error::IfBranchMismatch { then_type, else_type, then_location, else_location });
+        }
+    }
+
+    /// Returns the type of a built-in operator or function. Returns `None` if `name` is not a built-in.
+    /// Operators are methods of their built-in class, so like other methods they're constrained on it.
+    fn try_get_type_for_builtin(&self, name: &str) -> Option<TopLevelDefinitionType> {
+        let builtin = Builtin::from_name(name)?;
+        match builtin.class() {
+            Some(class) => Some(ClassSignature::builtin(class).method_type(builtin.method_name())),
+            None => Some(TopLevelDefinitionType::new(builtin.typ().find_all_generics(), builtin.typ())),
         }
     }
 
-    /// Generalize a type, making it generic. Any holes in the type become generic types.
+    /// Generalize a type, making it generic over each of its unbound type variables which were
+    /// created at a deeper level than the current one. Any other type variables are still in use
+    /// by the enclosing scope and generalizing them would let each use of the definition choose a
+    /// different type for them. See http://okmij.org/ftp/ML/generalization.html.
+    ///
+    /// Each generalized type variable is bound to a new `Type::Generic` so that the types of
+    /// expressions within the definition refer to the same names as the generalized type.
     fn generalize(&mut self, typ: &Type) -> TopLevelDefinitionType {
-        // The `generics` list in `TopLevelDefinitionType` can only map `Type::Generic` so
-        // we have to manually replace type variables here with generics with somewhat arbitrary
-        // names.
-        let typ = self.replace_type_variables_with_named_generics(typ);
-
-        // TODO: We should exclude any type variables which "escape" when generalizing.
-        // See http://okmij.org/ftp/ML/generalization.html.
-        let generics = typ.find_all_generics();
-        TopLevelDefinitionType::new(generics, typ)
-    }
-
-    /// Replaces any `Type::TypeVariable`s with `Type::Generic`s.
-    /// Their names are simply their integer ids converted to a string to ensure they do not
-    /// collide with the names of any existing generics in the same type.
-    fn replace_type_variables_with_named_generics(&mut self, typ: &Type) -> Type {
-        match typ {
-            Type::Error => Type::Error,
-            Type::Unit => Type::Unit,
-            Type::Int => Type::Int,
-            Type::Generic(name) => Type::Generic(name.clone()),
-            Type::TypeVariable(id) => {
-                let name = Arc::new(id.0.to_string());
-                // FIXME: We have to provide an ExprId when creating the generic identifier but we
-                // lack the context to create a fresh one. It's not too important for this toy
-                // compiler but a real one would probably want to rework type generalization in
-                // general.
-                let id = ExprId::new(u32::MAX);
-                Type::Generic(crate::parser::ast::Identifier { name, id })
-            },
-            Type::Function { parameter, return_type } => {
-                let parameter = Arc::new(self.replace_type_variables_with_named_generics(parameter));
-                let return_type = Arc::new(self.replace_type_variables_with_named_generics(return_type));
-                Type::Function { parameter, return_type }
-            },
+        let mut generics = typ.zonk(&self.bindings).find_all_generics();
+        let new_generics = self.generalize_type_variables(typ, &generics);
+        generics.extend(new_generics);
+        TopLevelDefinitionType::new(generics, typ.zonk(&self.bindings))
+    }
+
+    /// Generalize the type of a let binding. Any generics already in its type come from the type
+    /// annotation of the enclosing definition, so unlike `generalize` these aren't quantified over:
+    /// every use of the binding must agree on them.
+    ///
+    /// Let bindings are never given constraints. Any type variable a constraint is still wanted on is
+    /// left monomorphic instead, so the constraint is solved for whichever type the binding is used at.
+    /// Likewise, uses of the recursive group aren't unified with its types until the whole group is
+    /// checked, so any type variable in them is left monomorphic to be determined by that unification.
+    fn generalize_let(&mut self, typ: &Type) -> TopLevelDefinitionType {
+        let wanted_types = self.wanted.iter().map(|wanted| &wanted.constraint.typ);
+        let recursive_use_types = self.recursive_uses.iter().map(|recursive_use| &recursive_use.typ);
+
+        for pending_type in wanted_types.chain(recursive_use_types) {
+            for type_variable in pending_type.free_type_variables(&self.bindings) {
+                let level = self.type_variable_levels.get_mut(&type_variable).unwrap();
+                *level = (*level).min(self.level);
+            }
+        }
+
+        let existing_generics = typ.zonk(&self.bindings).find_all_generics();
+        let generics = self.generalize_type_variables(typ, &existing_generics);
+        TopLevelDefinitionType::new(generics, typ.zonk(&self.bindings))
+    }
+
+    /// Bind each generalizable type variable in the given type to a new generic, avoiding the names
+    /// of any generics already in it. Returns the names of the new generics.
+    fn generalize_type_variables(&mut self, typ: &Type, existing_generics: &[Arc<String>]) -> Vec<Arc<String>> {
+        let mut names_in_use = existing_generics.to_vec();
+        let mut new_generics = Vec::new();
+        let mut next_name = 0;
+
+        for type_variable in typ.free_type_variables(&self.bindings) {
+            if self.type_variable_levels[&type_variable] > self.level {
+                let name = fresh_generic_name(&names_in_use, &mut next_name);
+                self.bindings.insert(type_variable, Type::Generic(name.clone()));
+                names_in_use.push(name.clone());
+                new_generics.push(name);
+            }
+        }
+
+        new_generics
+    }
+
+    /// Enter a new level. Any type variables created within it are eligible to be generalized after
+    /// returning to the previous level, unless they are unified with a type from a shallower level.
+    fn enter_level(&mut self) {
+        self.level += 1;
+    }
+
+    fn exit_level(&mut self) {
+        self.level -= 1;
+    }
+
+    /// Bind a type variable to the given type. Any unbound type variables in that type deeper than
+    /// the type variable are lowered to its level, since they are now reachable from it.
+    fn bind(&mut self, type_variable: TypeVariableId, typ: &Type) {
+        let level = self.type_variable_levels[&type_variable];
+        for other in typ.free_type_variables(&self.bindings) {
+            let other_level = self.type_variable_levels.get_mut(&other).unwrap();
+            *other_level = (*other_level).min(level);
         }
+        self.bindings.insert(type_variable, typ.clone());
     }
 
     fn check_expr(&mut self, expr: &Expression) -> Type {
         match expr {
             Expression::IntegerLiteral(_, id) => self.store_and_return_type(*id, Type::Int),
+            Expression::BoolLiteral(_, id) => self.store_and_return_type(*id, Type::Bool),
+            Expression::StringLiteral(_, id) => self.store_and_return_type(*id, Type::String),
+            // Holes may have any type. They are reported separately by `CheckHoles` once the whole
+            // statement has been checked and the hole's type is fully known.
+            Expression::Hole { name: _, id } => {
+                let typ = self.next_type_variable();
+                self.store_and_return_type(*id, typ)
+            },
             Expression::Variable(identifier) => {
                 // If this is a built-in, get that type. Otherwise, lookup or query its type.
-                let typ =
-                    self.try_get_type_for_builtin(&identifier.name).unwrap_or_else(|| self.lookup_type(identifier.id));
+                // Definitions and local variables may shadow built-ins so only names without an origin are built-ins.
+                let typ = match self.try_get_type_for_builtin(&identifier.name) {
+                    Some(typ) if !self.origins.contains_key(&identifier.id) => {
+                        self.instantiate_with_constraints(&typ, identifier.id)
+                    },
+                    _ => self.lookup_type(identifier.id),
+                };
                 self.store_and_return_type(identifier.id, typ)
             },
             Expression::FunctionCall { function, argument, id } => {
-                let return_type = self.next_type_variable();
-                let expected = Type::Function {
-                    parameter: Arc::new(self.check_expr(argument)),
-                    return_type: Arc::new(return_type.clone()),
-                };
-                let actual = self.check_expr(function);
-                self.unify(&actual, &expected, *id);
+                let function_type = self.check_expr(function);
+                let (parameter_type, return_type) = self.function_parts(&function_type, function.id());
+
+                // The argument is what must match, so a mismatch is reported at the argument
+                let argument_type = self.check_expr(argument);
+                self.unify(&argument_type, &parameter_type, argument.id());
                 self.store_and_return_type(*id, return_type)
             },
-            Expression::Lambda { parameter_name, body, id } => {
+            Expression::Lambda { parameter, body, id } => {
                 let parameter_type = self.next_type_variable();
-                self.expr_types.insert(parameter_name.id, parameter_type.clone());
+                self.check_pattern(parameter, &parameter_type);
                 let body_type = self.check_expr(body);
                 let function_type =
                     Type::Function { parameter: Arc::new(parameter_type), return_type: Arc::new(body_type) };
                 self.store_and_return_type(*id, function_type)
             },
+            Expression::If { condition, then_branch, else_branch, id } => {
+                let condition_type = self.check_expr(condition);
+                self.unify(&condition_type, &Type::Bool, condition.id());
+
+                let then_type = self.check_expr(then_branch);
+                let else_type = self.check_expr(else_branch);
+                self.unify_branches(&then_type, &else_type, then_branch.id(), else_branch.id());
+                self.store_and_return_type(*id, then_type)
+            },
+            Expression::Let { name, value, body, id } => {
+                // Check the value at a deeper level so it can be generalized like a top-level definition
+                self.enter_level();
+                let value_type = self.check_expr(value);
+                self.exit_level();
+
+                let typ = self.generalize_let(&value_type);
+                self.expr_types.insert(name.id, typ.typ.clone());
+                self.let_types.insert(name.id, typ);
+
+                let body_type = self.check_expr(body);
+                self.store_and_return_type(*id, body_type)
+            },
+            Expression::Match { scrutinee, arms, id } => {
+                let scrutinee_type = self.check_expr(scrutinee);
+                let result_type = self.next_type_variable();
+
+                for arm in arms {
+                    self.check_pattern(&arm.pattern, &scrutinee_type);
+                    let body_type = self.check_expr(&arm.body);
+                    self.unify(&body_type, &result_type, arm.body.id());
+                }
+                self.store_and_return_type(*id, result_type)
+            },
+            Expression::Tuple { elements, id } => {
+                let elements = elements.iter().map(|element| self.check_expr(element)).collect();
+                self.store_and_return_type(*id, Type::Tuple(elements))
+            },
+            Expression::List { elements, id } => {
+                let element_type = self.next_type_variable();
+                for element in elements {
+                    let actual = self.check_expr(element);
+                    self.unify(&actual, &element_type, element.id());
+                }
+                self.store_and_return_type(*id, Type::List(Arc::new(element_type)))
+            },
+            Expression::Record { fields, id } => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|(name, value)| {
+                        let typ = self.check_expr(value);
+                        self.expr_types.insert(name.id, typ.clone());
+                        (name.name.clone(), typ)
+                    })
+                    .collect();
+                self.store_and_return_type(*id, Type::record(fields, None))
+            },
+            Expression::FieldAccess { record, field, id } => {
+                let record_type = self.check_expr(record);
+                let field_type = self.check_field_access(&record_type, field, record.id());
+                self.expr_types.insert(field.id, field_type.clone());
+                self.store_and_return_type(*id, field_type)
+            },
+        }
+    }
+
+    /// Returns the type of the given field of a record. If the type of the record isn't known yet,
+    /// it is inferred to be any record with at least this field.
+    fn check_field_access(&mut self, record_type: &Type, field: &Identifier, record: ExprId) -> Type {
+        // A record type may only gain more fields if the rest of its fields are still unknown
+        let has_field = match record_type.record_parts(&self.bindings) {
+            Some((fields, rest)) => {
+                fields.iter().any(|(name, _)| *name == field.name) || matches!(rest, Some(Type::TypeVariable(_)))
+            },
+            None => matches!(record_type.follow_bindings(&self.bindings), Type::TypeVariable(_) | Type::Error),
+        };
+
+        if !has_field {
+            let location = field.id.location(self.item(), self.compiler);
+            let typ = record_type.zonk(&self.bindings);
+            self.errors.push(Error::NoSuchField { field: field.name.clone(), typ, location });
+            return Type::Error;
         }
+
+        let field_type = self.next_type_variable();
+        let rest = self.next_type_variable();
+        let expected = Type::record([(field.name.clone(), field_type.clone())], Some(rest));
+        self.unify(record_type, &expected, record);
+        field_type
+    }
+
+    /// Check a pattern matches values of the expected type, giving each variable in it the type
+    /// of the value it binds.
+    fn check_pattern(&mut self, pattern: &Pattern, expected: &Type) {
+        let typ = match pattern {
+            Pattern::Wildcard(_) | Pattern::Variable(_) => expected.clone(),
+            Pattern::IntegerLiteral(_, id) => {
+                self.unify(&Type::Int, expected, *id);
+                Type::Int
+            },
+            Pattern::BoolLiteral(_, id) => {
+                self.unify(&Type::Bool, expected, *id);
+                Type::Bool
+            },
+            Pattern::Constructor { name, arguments, id } => {
+                let Some(Origin::Constructor(type_id, constructor)) = self.origins.get(&name.id).cloned() else {
+                    // A name resolution error occurred, don't issue a type error as well
+                    for argument in arguments {
+                        self.check_pattern(argument, &Type::Error);
+                    }
+                    return;
+                };
+
+                let signature = ConstructorSignature::new(&type_id, &constructor, self.compiler);
+                let substitutions =
+                    signature.generics.iter().map(|name| (name.clone(), self.next_type_variable())).collect();
+                let result = signature.result.substitute(&substitutions, &self.bindings);
+                self.unify(&result, expected, *id);
+
+                if signature.fields.len() != arguments.len() {
+                    let location = id.location(self.item(), self.compiler);
+                    let (name, expected, actual) = (name.name.clone(), signature.fields.len(), arguments.len());
+                    self.errors.push(Error::ConstructorPatternArity { name, expected, actual, location });
+                }
+
+                // Check each argument against its field, or against `Error` if there are too many
+                let fields = signature.fields.iter().map(Some).chain(std::iter::repeat(None));
+                for (argument, field) in arguments.iter().zip(fields) {
+                    let field = field.map_or(Type::Error, |field| field.substitute(&substitutions, &self.bindings));
+                    self.check_pattern(argument, &field);
+                }
+                result
+            },
+            Pattern::Tuple { elements, id } => {
+                let element_types: Vec<_> = elements.iter().map(|_| self.next_type_variable()).collect();
+                let typ = Type::Tuple(element_types.iter().cloned().collect());
+                self.unify(&typ, expected, *id);
+
+                for (element, element_type) in elements.iter().zip(&element_types) {
+                    self.check_pattern(element, element_type);
+                }
+                typ
+            },
+        };
+
+        let id = match pattern {
+            Pattern::Variable(identifier) => identifier.id,
+            other => other.id(),
+        };
+        self.expr_types.insert(id, typ);
     }
 
     fn instantiate(&mut self, typ: &TopLevelDefinitionType) -> Type {
@@ -225,49 +908,371 @@ impl<'local, 'inner> TypeChecker<'local, 'inner> {
         typ.typ.substitute(&substitutions, &self.bindings)
     }
 
+    /// Instantiate the type of the given variable, wanting each of the type's constraints for
+    /// whichever types its generics are instantiated with
+    fn instantiate_with_constraints(&mut self, typ: &TopLevelDefinitionType, variable: ExprId) -> Type {
+        let substitutions: Substitutions =
+            typ.generics.iter().map(|name| (name.clone(), self.next_type_variable())).collect();
+
+        for constraint in typ.constraints.iter() {
+            let constraint = constraint.substitute(&substitutions, &self.bindings);
+            self.wanted.push(Wanted { item: self.item().clone(), variable, constraint });
+        }
+        typ.typ.substitute(&substitutions, &self.bindings)
+    }
+
+    /// Check each method of an instance has the type given in its class, with the class's generic
+    /// replaced by the type of the instance. The methods may use the constraints of the instance's context.
+    fn check_instance(&mut self, instance: &InstanceDefinition) {
+        let context = Constraint::from_ast_constraints(&instance.constraints, &self.origins);
+        self.givens.insert(self.item().clone(), Givens { constraints: context, inferred_generics: None });
+
+        let class = class_id(&instance.class, &self.origins).map(|id| ClassSignature::new(&id, self.compiler));
+        let typ = Type::from_ast_type(&instance.typ, &self.origins, self.compiler);
+
+        for (name, body) in instance.methods.iter() {
+            let actual = self.check_expr(body);
+
+            // Methods which aren't in the class are reported during name resolution
+            if let Some(expected) = class.as_ref().and_then(|class| class.instance_method_type(&name.name, &typ)) {
+                self.expr_types.insert(name.id, expected.clone());
+                self.unify(&actual, &expected, body.id());
+            }
+        }
+    }
+
+    /// Solve each wanted constraint now that the types in the group are generalized, returning the
+    /// types of the group with the constraints inferred for any definitions without type annotations.
+    fn solve_constraints(
+        &mut self, types: Vec<(TopLevelId, TopLevelDefinitionType)>,
+    ) -> Vec<(TopLevelId, TopLevelDefinitionType)> {
+        for (id, typ) in types.iter() {
+            if self.group_types.contains_key(id) {
+                let givens = Givens { constraints: Vec::new(), inferred_generics: Some(typ.generics.clone()) };
+                self.givens.insert(id.clone(), givens);
+            } else {
+                // An instance's context is already given while checking it
+                let constraints = typ.constraints.clone();
+                self.givens.entry(id.clone()).or_insert(Givens { constraints, inferred_generics: None });
+            }
+        }
+
+        for wanted in std::mem::take(&mut self.wanted) {
+            let evidence = self.solve(&wanted.item, &wanted.constraint, wanted.variable);
+            self.evidence.entry(wanted.item).or_default().entry(wanted.variable).or_default().push(evidence);
+        }
+
+        // Definitions in the group need the constraints of any definition in it they use, which
+        // may in turn use them. Keep passing constraints along until no more are needed.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for recursive_use in self.recursive_uses.iter() {
+                let needed = self.givens[&recursive_use.definition].constraints.clone();
+                let givens = self.givens.get_mut(&recursive_use.item).unwrap();
+
+                for constraint in needed {
+                    let inferred = match &constraint.typ {
+                        Type::Generic(name) => {
+                            givens.inferred_generics.as_ref().is_some_and(|generics| generics.contains(name))
+                        },
+                        _ => false,
+                    };
+                    if inferred && !givens.constraints.contains(&constraint) {
+                        givens.constraints.push(constraint);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        for recursive_use in std::mem::take(&mut self.recursive_uses) {
+            for constraint in self.givens[&recursive_use.definition].constraints.clone() {
+                let evidence = self.solve(&recursive_use.item, &constraint, recursive_use.variable);
+                let evidence_for_item = self.evidence.entry(recursive_use.item.clone()).or_default();
+                evidence_for_item.entry(recursive_use.variable).or_default().push(evidence);
+            }
+        }
+
+        let types = types.into_iter().map(|(id, typ)| match self.givens.get(&id) {
+            Some(Givens { constraints, inferred_generics: Some(_) }) => {
+                let constraints = constraints.clone();
+                (id, typ.with_constraints(constraints))
+            },
+            _ => (id, typ),
+        });
+        types.collect()
+    }
+
+    /// Find the evidence that the constraint holds for the variable `variable` in `item`, issuing an
+    /// error if it doesn't. A type with a type constructor needs an instance for that type constructor
+    /// while a generic needs the constraint to be given, or inferred if that is possible.
+    fn solve(&mut self, item: &TopLevelId, constraint: &Constraint, variable: ExprId) -> Option<Evidence> {
+        let typ = constraint.typ.zonk(&self.bindings);
+        let (class, class_name) = (constraint.class.clone(), constraint.class_name.clone());
+        let compiler = self.compiler;
+        let location = || variable.location(item, compiler);
+
+        let error = match &typ {
+            // There was already an error involving this type
+            Type::Error => return None,
+            Type::Generic(name) => {
+                let givens = self.givens.entry(item.clone()).or_default();
+                let same = |given: &Constraint| given.class == constraint.class && given.typ == typ;
+                if let Some(index) = givens.constraints.iter().position(same) {
+                    return Some(Evidence::Parameter(index));
+                }
+
+                match &givens.inferred_generics {
+                    Some(generics) if generics.contains(name) => {
+                        givens.constraints.push(Constraint { class, class_name, typ });
+                        return Some(Evidence::Parameter(givens.constraints.len() - 1));
+                    },
+                    // A generic which isn't in the definition's type can never be chosen by a caller
+                    Some(_) => Error::AmbiguousType { class: class_name, typ, location: location() },
+                    None => Error::NoInstance { class: class_name, typ, location: location() },
+                }
+            },
+            Type::TypeVariable(_) => Error::AmbiguousType { class: class_name, typ, location: location() },
+            other => match self.find_instance(item, &class, other) {
+                Some(instance) => {
+                    let signature = InstanceSignature::new(&instance, self.compiler);
+
+                    // The instance's type is applied only to generics, so these are what we instantiate them with
+                    let arguments = signature.typ.constructor_arguments().into_iter();
+                    let arguments = arguments.zip(other.constructor_arguments());
+                    let substitutions: Substitutions = arguments
+                        .filter_map(|(generic, argument)| match generic {
+                            Type::Generic(name) => Some((name, argument)),
+                            _ => None,
+                        })
+                        .collect();
+
+                    let context = signature.context.iter().map(|constraint| {
+                        self.solve(item, &constraint.substitute(&substitutions, &self.bindings), variable)
+                    });
+                    return Some(Evidence::Instance(instance, context.collect::<Option<_>>()?));
+                },
+                None => Error::NoInstance { class: class_name, typ, location: location() },
+            },
+        };
+
+        self.finished.entry(item.clone()).or_default().1.push(error);
+        None
+    }
+
+    /// Find the instance of `class` for the type constructor of `typ` visible in the file of `item`.
+    /// Built-in instances are visible everywhere.
+    fn find_instance(&mut self, item: &TopLevelId, class: &TopLevelId, typ: &Type) -> Option<TopLevelId> {
+        let compiler = self.compiler;
+        let file_name = item.file_path.clone();
+        let instances = self.instances.entry(file_name.clone());
+        let instances = instances.or_insert_with(|| VisibleInstances { file_name }.get(compiler).0);
+        match instances.get(&(class.clone(), typ.constructor()?)) {
+            Some(instance) => Some(instance.clone()),
+            None => BuiltinClass::from_id(class)?.instance(typ),
+        }
+    }
+
+    /// Split the type of a function being called into its parameter and return types. If it isn't
+    /// known to be a function yet, it is unified with a new function type, issuing an error at the
+    /// given expression if it can't be called.
+    fn function_parts(&mut self, function_type: &Type, id: ExprId) -> (Type, Type) {
+        match function_type.follow_bindings(&self.bindings) {
+            Type::Function { parameter, return_type } => (parameter.as_ref().clone(), return_type.as_ref().clone()),
+            _ => {
+                let parameter = self.next_type_variable();
+                let return_type = self.next_type_variable();
+                let expected = Type::Function {
+                    parameter: Arc::new(parameter.clone()),
+                    return_type: Arc::new(return_type.clone()),
+                };
+                self.unify(function_type, &expected, id);
+                (parameter, return_type)
+            },
+        }
+    }
+
+    /// Unify the actual type of an expression with the type it is expected to have, issuing an
+    /// error at the given expression if they don't match.
     fn unify(&mut self, actual: &Type, expected: &Type, id: ExprId) {
+        self.unify_at(actual, expected, id, &mut Vec::new(), (actual, expected));
+    }
+
+    /// Unify two types within the types being unified by `unify`. `path` is the position of these
+    /// types within the `root` types so that any error can point out where they differ.
+    fn unify_at(
+        &mut self, actual: &Type, expected: &Type, id: ExprId, path: &mut Vec<TypePosition>, root: (&Type, &Type),
+    ) {
         match (actual, expected) {
             (Type::Error, _) | (_, Type::Error) => (),
             (Type::Unit, Type::Unit) => (),
             (Type::Int, Type::Int) => (),
-            (Type::Generic(name1), Type::Generic(name2)) if name1.name == name2.name => (),
+            (Type::Bool, Type::Bool) => (),
+            (Type::String, Type::String) => (),
+            (Type::Generic(name1), Type::Generic(name2)) if name1 == name2 => (),
+            (
+                Type::Data { id: actual_id, arguments: actual_arguments, .. },
+                Type::Data { id: expected_id, arguments: expected_arguments, .. },
+            ) if actual_id == expected_id => {
+                for (index, (actual, expected)) in actual_arguments.iter().zip(expected_arguments.iter()).enumerate() {
+                    path.push(TypePosition::Argument(index));
+                    self.unify_at(actual, expected, id, path, root);
+                    path.pop();
+                }
+            },
+            (Type::List(actual_element), Type::List(expected_element)) => {
+                path.push(TypePosition::Argument(0));
+                self.unify_at(actual_element, expected_element, id, path, root);
+                path.pop();
+            },
+            (Type::Tuple(actual_elements), Type::Tuple(expected_elements))
+                if actual_elements.len() == expected_elements.len() =>
+            {
+                for (index, (actual, expected)) in actual_elements.iter().zip(expected_elements.iter()).enumerate() {
+                    path.push(TypePosition::Element(index));
+                    self.unify_at(actual, expected, id, path, root);
+                    path.pop();
+                }
+            },
+            (Type::Record { .. }, Type::Record { .. }) => self.unify_records(actual, expected, id, path, root),
             (
                 Type::Function { parameter: actual_parameter, return_type: actual_return_type },
                 Type::Function { parameter: expected_parameter, return_type: expected_return_type },
             ) => {
-                self.unify(&actual_parameter, &expected_parameter, id);
-                self.unify(&actual_return_type, &expected_return_type, id);
+                path.push(TypePosition::Parameter);
+                self.unify_at(actual_parameter, expected_parameter, id, path, root);
+                path.pop();
+
+                path.push(TypePosition::ReturnType);
+                self.unify_at(actual_return_type, expected_return_type, id, path, root);
+                path.pop();
             },
             // If the type variable is already bound to something, recur on that binding
-            (Type::TypeVariable(type_var), expected) if self.bindings.contains_key(&type_var) => {
+            (Type::TypeVariable(type_var), expected) if self.bindings.contains_key(type_var) => {
                 let actual = self.bindings.get(type_var).unwrap().clone();
-                self.unify(&actual, expected, id);
+                self.unify_at(&actual, expected, id, path, root);
             },
             // If the type variable is already bound to something, recur on that binding
-            (actual, Type::TypeVariable(type_var)) if self.bindings.contains_key(&type_var) => {
+            (actual, Type::TypeVariable(type_var)) if self.bindings.contains_key(type_var) => {
                 let expected = self.bindings.get(type_var).unwrap().clone();
-                self.unify(actual, &expected, id);
+                self.unify_at(actual, &expected, id, path, root);
             },
             (type_var_type @ Type::TypeVariable(type_var), other)
             | (other, type_var_type @ Type::TypeVariable(type_var)) => {
                 if type_var_type == other {
                     // Don't bind a type variable to itself
                 } else if !type_var.occurs_in(other, &self.bindings) {
-                    self.bindings.insert(*type_var, other.clone());
+                    self.bind(*type_var, other);
                 } else {
                     // Error, binding here would make a recursive type!
-                    let location = id.location(&self.item, self.compiler);
-                    let actual = actual.display(&self.bindings).to_string();
-                    let expected = expected.display(&self.bindings).to_string();
-                    self.errors.push(Error::ExpectedType { actual, expected, location })
+                    let location = id.location(self.item(), self.compiler);
+                    let (type_variable, typ) = (type_var_type.clone(), other.zonk(&self.bindings));
+                    self.errors.push(Error::RecursiveType { type_variable, typ, location })
                 }
             },
-            (actual, expected) => {
-                let location = id.location(&self.item, self.compiler);
-                let actual = actual.display(&self.bindings).to_string();
-                let expected = expected.display(&self.bindings).to_string();
-                self.errors.push(Error::ExpectedType { actual, expected, location })
+            _ => self.type_mismatch(id, path, root),
+        }
+    }
+
+    /// Unify two record types. Fields they have in common must have the same type, and any fields
+    /// only one of them has are added to the unknown rest of the fields of the other one, if it has
+    /// one. Two records which both have fields the other lacks share the rest of their fields.
+    fn unify_records(
+        &mut self, actual: &Type, expected: &Type, id: ExprId, path: &mut Vec<TypePosition>, root: (&Type, &Type),
+    ) {
+        let (actual_fields, actual_rest) = actual.record_parts(&self.bindings).expect("Expected a record type");
+        let (expected_fields, expected_rest) = expected.record_parts(&self.bindings).expect("Expected a record type");
+
+        let find = |fields: &[(Arc<String>, Type)], name: &Arc<String>| {
+            fields.iter().find(|(field_name, _)| field_name == name).map(|(_, typ)| typ.clone())
+        };
+
+        for (name, actual_type) in actual_fields.iter() {
+            if let Some(expected_type) = find(&expected_fields, name) {
+                path.push(TypePosition::Field(name.clone()));
+                self.unify_at(actual_type, &expected_type, id, path, root);
+                path.pop();
+            }
+        }
+
+        let only_in = |fields: &[(Arc<String>, Type)], other: &[(Arc<String>, Type)]| -> Vec<_> {
+            fields.iter().filter(|(name, _)| find(other, name).is_none()).cloned().collect()
+        };
+        let only_actual = only_in(&actual_fields, &expected_fields);
+        let only_expected = only_in(&expected_fields, &actual_fields);
+
+        match (actual_rest, expected_rest) {
+            (None, None) if only_actual.is_empty() && only_expected.is_empty() => (),
+            (actual_rest, expected_rest) if only_actual.is_empty() && only_expected.is_empty() => {
+                // Records without a rest have exactly the fields given, so they have no more
+                let closed = || Type::record([], None);
+                let actual_rest = actual_rest.unwrap_or_else(closed);
+                let expected_rest = expected_rest.unwrap_or_else(closed);
+                self.unify_at(&actual_rest, &expected_rest, id, path, root);
             },
+            (Some(actual_rest), expected_rest) if only_actual.is_empty() => {
+                let expected = Type::record(only_expected, expected_rest);
+                self.unify_at(&actual_rest, &expected, id, path, root);
+            },
+            (actual_rest, Some(expected_rest)) if only_expected.is_empty() => {
+                let actual = Type::record(only_actual, actual_rest);
+                self.unify_at(&actual, &expected_rest, id, path, root);
+            },
+            (Some(actual_rest), Some(expected_rest)) if actual_rest != expected_rest => {
+                let rest = self.next_type_variable();
+                let expected = Type::record(only_expected, Some(rest.clone()));
+                self.unify_at(&actual_rest, &expected, id, path, root);
+                let actual = Type::record(only_actual, Some(rest));
+                self.unify_at(&actual, &expected_rest, id, path, root);
+            },
+            _ => self.type_mismatch(id, path, root),
+        }
+    }
+
+    fn type_mismatch(&mut self, id: ExprId, path: &[TypePosition], root: (&Type, &Type)) {
+        let location = id.location(self.item(), self.compiler);
+        let (actual, expected) = (root.0.clone(), root.1.clone());
+        self.errors.push(Error::ExpectedType { actual, expected, path: path.into(), location })
+    }
+}
+
+/// Resolve the types in a type mismatch error against the final bindings of its group. Other type
+/// errors are resolved when they're issued since later bindings would change what they describe,
+/// e.g. the type variable in a `RecursiveType` error may be bound afterward.
+fn zonk_error(error: Error, bindings: &TypeBindings) -> Error {
+    match error {
+        Error::ExpectedType { actual, expected, path, location } => {
+            Error::ExpectedType { actual: actual.zonk(bindings), expected: expected.zonk(bindings), path, location }
+        },
+        Error::IfBranchMismatch { then_type, else_type, then_location, else_location } => {
+            let (then_type, else_type) = (then_type.zonk(bindings), else_type.zonk(bindings));
+            Error::IfBranchMismatch { then_type, else_type, then_location, else_location }
+        },
+        other => other,
+    }
+}
+
+fn definition_name(id: &TopLevelId, compiler: &CompilerHandle) -> Arc<String> {
+    match GetStatement(id.clone()).get(compiler) {
+        TopLevelStatement::Definition(definition) => definition.name.name,
+        _ => unreachable!("Only definitions can be in a recursive group with other statements"),
+    }
+}
+
+/// Returns the first name in `a`, `b`, .., `z`, `a1`, `b1`, .. from `next_name` onward which isn't
+/// already in `generics`, such as a generic the user wrote in a type annotation.
+fn fresh_generic_name(generics: &[Arc<String>], next_name: &mut usize) -> Arc<String> {
+    loop {
+        let letter = (b'a' + (*next_name % 26) as u8) as char;
+        let name = match *next_name / 26 {
+            0 => letter.to_string(),
+            suffix => format!("{letter}{suffix}"),
+        };
+        *next_name += 1;
+
+        if !generics.iter().any(|generic| generic.as_str() == name) {
+            return Arc::new(name);
         }
     }
 }
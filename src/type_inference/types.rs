@@ -1,18 +1,31 @@
 use std::{collections::{BTreeMap, BTreeSet}, sync::Arc};
 
+use inc_complete::DbGet;
 use serde::{Deserialize, Serialize};
 
-use crate::parser::ast::Identifier;
+use crate::{
+    builtins::LIST_TYPE,
+    incremental::ExpandTypeAlias,
+    name_resolution::{class_id, Origin},
+    parser::{
+        ast::{Definition, Identifier},
+        ids::{ExprId, TopLevelId},
+    },
+};
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Type {
     /// This isn't a real type but can be useful to stop further type errors.
     /// It should generally be hidden from users - e.g. it unifies successfully with any other type.
     Error,
     Unit,
     Int,
-    /// A user-supplied generic type. We don't want to bind over these like we do with type variables.
-    Generic(Identifier),
+    Bool,
+    String,
+    /// A generic type, either written by the user or created when generalizing a definition.
+    /// We don't want to bind over these like we do with type variables.
+    Generic(Arc<String>),
     /// We represent type variables with unique ids and an external bindings map instead of a
     /// `Arc<RwLock<..>>` or similar because these need to be compared for equality, serialized, and
     /// be performant. We want the faster insertion of a local BTreeMap compared to a thread-safe
@@ -23,6 +36,68 @@ pub enum Type {
         parameter: Arc<Type>,
         return_type: Arc<Type>,
     },
+    /// A type declared by a `type` statement, applied to an argument for each of its generics.
+    /// The name is only kept for printing, types are identified by the statement defining them.
+    Data {
+        id: TopLevelId,
+        name: Arc<String>,
+        arguments: Arc<[Type]>,
+    },
+    /// The built-in list type, `List a`
+    List(Arc<Type>),
+    Tuple(Arc<[Type]>),
+    /// A record type, with its fields sorted by name. If `rest` is given the record may have
+    /// more fields than these, given by `rest`: either a type variable, which may be bound to
+    /// another record type with the remaining fields, or a generic standing for them.
+    Record {
+        fields: Arc<[(Arc<String>, Type)]>,
+        rest: Option<Arc<Type>>,
+    },
+}
+
+/// The outermost part of a type which decides which instance of a class is used for it.
+/// `Type::Data` types are identified by the `type` statement defining them.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TypeConstructor {
+    Int,
+    Bool,
+    String,
+    Function,
+    List,
+    Tuple(usize),
+    Data(TopLevelId),
+}
+
+/// A requirement that a type has an instance of a class, e.g. `Show a`
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Constraint {
+    /// The `class` statement defining the class, or the id standing in for it for a built-in class
+    pub class: TopLevelId,
+    /// The name of the class, only kept for printing
+    pub class_name: Arc<String>,
+    pub typ: Type,
+}
+
+impl Constraint {
+    /// Convert the constraints written in a type annotation or instance. Constraints on classes
+    /// which failed to resolve are left out.
+    pub fn from_ast_constraints(
+        constraints: &[crate::parser::ast::Constraint], origins: &BTreeMap<ExprId, Origin>,
+    ) -> Vec<Constraint> {
+        let constraints = constraints.iter().filter_map(|constraint| {
+            Some(Constraint {
+                class: class_id(&constraint.class, origins)?,
+                class_name: constraint.class.name.clone(),
+                typ: Type::Generic(constraint.generic.name.clone()),
+            })
+        });
+        constraints.collect()
+    }
+
+    pub fn substitute(&self, substitutions: &Substitutions, bindings: &TypeBindings) -> Constraint {
+        let typ = self.typ.substitute(substitutions, bindings);
+        Constraint { class: self.class.clone(), class_name: self.class_name.clone(), typ }
+    }
 }
 
 /// Maps type variables to their bindings
@@ -31,32 +106,108 @@ pub type TypeBindings = BTreeMap<TypeVariableId, Type>;
 /// Maps generics to new types to instantiate them with
 pub type Substitutions = BTreeMap<Arc<String>, Type>;
 
+/// The name and type of each field of a record type
+pub type Fields = Vec<(Arc<String>, Type)>;
+
 impl Type {
-    pub fn from_ast_type(typ: &crate::parser::ast::Type) -> Type {
+    /// Convert a type written in the source. `origins` are the name resolution results of the
+    /// statement it was written in, used to find which `type` statement each name refers to.
+    /// Type aliases are replaced with the type they stand for. Names which failed to resolve
+    /// become `Type::Error`.
+    pub fn from_ast_type(
+        typ: &crate::parser::ast::Type, origins: &BTreeMap<ExprId, Origin>, compiler: &impl DbGet<ExpandTypeAlias>,
+    ) -> Type {
+        let convert = |typ| Self::from_ast_type(typ, origins, compiler);
         match typ {
             crate::parser::ast::Type::Int => Type::Int,
-            crate::parser::ast::Type::Generic(identifier) => Type::Generic(identifier.clone()),
+            crate::parser::ast::Type::Bool => Type::Bool,
+            crate::parser::ast::Type::String => Type::String,
+            crate::parser::ast::Type::Generic(identifier) => Type::Generic(identifier.name.clone()),
+            crate::parser::ast::Type::Named { name, arguments } => match origins.get(&name.id) {
+                Some(Origin::TypeDefinition(id)) => {
+                    let arguments = arguments.iter().map(convert).collect();
+                    Type::Data { id: id.clone(), name: name.name.clone(), arguments }
+                },
+                Some(Origin::TypeAlias(id)) => {
+                    let alias = ExpandTypeAlias(id.clone()).get(compiler).0;
+                    if alias.generics.len() != arguments.len() {
+                        return Type::Error;
+                    }
+                    let substitutions = alias.generics.into_iter().zip(arguments.iter().map(convert)).collect();
+                    alias.typ.substitute(&substitutions, &TypeBindings::new())
+                },
+                // Only built-in types are left unlinked by name resolution
+                None if name.name.as_str() == LIST_TYPE && arguments.len() == 1 => {
+                    Type::List(Arc::new(convert(&arguments[0])))
+                },
+                _ => Type::Error,
+            },
+            crate::parser::ast::Type::Tuple(elements) => Type::Tuple(elements.iter().map(convert).collect()),
+            crate::parser::ast::Type::Record { fields, rest } => {
+                let fields = fields.iter().map(|(name, typ)| (name.name.clone(), convert(typ)));
+                Type::record(fields, rest.as_ref().map(|rest| Type::Generic(rest.name.clone())))
+            },
             crate::parser::ast::Type::Function { parameter, return_type } => {
-                let parameter = Arc::new(Self::from_ast_type(parameter));
-                let return_type = Arc::new(Self::from_ast_type(return_type));
+                let parameter = Arc::new(convert(parameter));
+                let return_type = Arc::new(convert(return_type));
                 Type::Function { parameter, return_type }
             },
         }
     }
 
+    /// Convert the type annotation of a definition. If its generics are listed with `forall`, using
+    /// any other generic was already reported by name resolution, so these become `Type::Error`
+    /// rather than being quantified over.
+    pub fn from_annotation(
+        typ: &crate::parser::ast::Type, forall: Option<&[Identifier]>, origins: &BTreeMap<ExprId, Origin>,
+        compiler: &impl DbGet<ExpandTypeAlias>,
+    ) -> Type {
+        let typ = Self::from_ast_type(typ, origins, compiler);
+        let Some(forall) = forall else { return typ };
+
+        let undeclared = typ.find_all_generics().into_iter().filter(|name| !forall.iter().any(|g| &g.name == name));
+        let substitutions = undeclared.map(|name| (name, Type::Error)).collect();
+        typ.substitute(&substitutions, &TypeBindings::new())
+    }
+
+    /// The outermost type constructor of this type, or `None` if it has none which may have an instance:
+    /// generics, type variables, records, `Unit`, and `Error`
+    pub fn constructor(&self) -> Option<TypeConstructor> {
+        match self {
+            Type::Int => Some(TypeConstructor::Int),
+            Type::Bool => Some(TypeConstructor::Bool),
+            Type::String => Some(TypeConstructor::String),
+            Type::Function { .. } => Some(TypeConstructor::Function),
+            Type::List(_) => Some(TypeConstructor::List),
+            Type::Tuple(elements) => Some(TypeConstructor::Tuple(elements.len())),
+            Type::Data { id, .. } => Some(TypeConstructor::Data(id.clone())),
+            Type::Error | Type::Unit | Type::Generic(_) | Type::TypeVariable(_) | Type::Record { .. } => None,
+        }
+    }
+
+    /// The types the outermost type constructor of this type is applied to, in order
+    pub fn constructor_arguments(&self) -> Vec<Type> {
+        match self {
+            Type::Function { parameter, return_type } => vec![parameter.as_ref().clone(), return_type.as_ref().clone()],
+            Type::List(element) => vec![element.as_ref().clone()],
+            Type::Data { arguments: types, .. } | Type::Tuple(types) => types.to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
     /// Substitutes any generics with the given names with the corresponding type in the map
     pub fn substitute(&self, substitutions: &Substitutions, bindings: &TypeBindings) -> Type {
         match self {
-            Type::Error | Type::Unit | Type::Int => self.clone(),
+            Type::Error | Type::Unit | Type::Int | Type::Bool | Type::String => self.clone(),
             Type::TypeVariable(id) => {
-                if let Some(binding) = bindings.get(&id) {
+                if let Some(binding) = bindings.get(id) {
                     binding.substitute(substitutions, bindings)
                 } else {
                     Type::TypeVariable(*id)
                 }
             },
             Type::Generic(name) => {
-                if let Some(substitution) = substitutions.get(&name.name) {
+                if let Some(substitution) = substitutions.get(name) {
                     // Don't recur here, we've already substituted
                     substitution.clone()
                 } else {
@@ -68,27 +219,163 @@ impl Type {
                 let return_type = Arc::new(return_type.substitute(substitutions, bindings));
                 Type::Function { parameter, return_type }
             },
+            Type::Data { id, name, arguments } => {
+                let arguments = arguments.iter().map(|argument| argument.substitute(substitutions, bindings)).collect();
+                Type::Data { id: id.clone(), name: name.clone(), arguments }
+            },
+            Type::List(element) => Type::List(Arc::new(element.substitute(substitutions, bindings))),
+            Type::Tuple(elements) => {
+                Type::Tuple(elements.iter().map(|element| element.substitute(substitutions, bindings)).collect())
+            },
+            Type::Record { fields, rest } => {
+                let fields = fields.iter().map(|(name, typ)| (name.clone(), typ.substitute(substitutions, bindings)));
+                Type::record(fields, rest.as_ref().map(|rest| rest.substitute(substitutions, bindings)))
+            },
+        }
+    }
+
+    /// Create a record type with the given fields. If `rest` is itself a record type, its fields
+    /// are merged in so that the fields of a record are all in one place.
+    pub fn record(fields: impl IntoIterator<Item = (Arc<String>, Type)>, rest: Option<Type>) -> Type {
+        let mut fields: Vec<_> = fields.into_iter().collect();
+        let rest = match rest {
+            Some(Type::Record { fields: more_fields, rest }) => {
+                fields.extend(more_fields.iter().cloned());
+                rest
+            },
+            rest => rest.map(Arc::new),
+        };
+
+        // A record type written with the same field twice is an error reported during name resolution
+        fields.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+        fields.dedup_by(|(name1, _), (name2, _)| name1 == name2);
+        Type::Record { fields: fields.into(), rest }
+    }
+
+    /// If this is a record type, returns each of its fields, including those of any type variables
+    /// in `rest` bound to more fields, along with whatever `rest` is after following those bindings.
+    pub fn record_parts(&self, bindings: &TypeBindings) -> Option<(Fields, Option<Type>)> {
+        match self {
+            Type::TypeVariable(id) => bindings.get(id)?.record_parts(bindings),
+            Type::Record { fields, rest } => {
+                let mut fields = fields.to_vec();
+                let rest = match rest.as_deref().map(|rest| (rest, rest.record_parts(bindings))) {
+                    None => None,
+                    Some((_, Some((more_fields, rest)))) => {
+                        fields.extend(more_fields);
+                        rest
+                    },
+                    Some((rest, None)) => Some(rest.follow_bindings(bindings)),
+                };
+                fields.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+                Some((fields, rest))
+            },
+            _ => None,
         }
     }
 
+    /// Follow any bindings of this type if it is a type variable, without zonking the rest of the type
+    pub fn follow_bindings(&self, bindings: &TypeBindings) -> Type {
+        match self {
+            Type::TypeVariable(id) if bindings.contains_key(id) => bindings[id].follow_bindings(bindings),
+            other => other.clone(),
+        }
+    }
+
+    /// Replaces any bound type variables with what they are bound to, recursively.
+    /// Type variables which are still unbound are left as-is.
+    pub fn zonk(&self, bindings: &TypeBindings) -> Type {
+        self.substitute(&Substitutions::new(), bindings)
+    }
+
     pub fn display<'a, 'b>(&'a self, bindings: &'b TypeBindings) -> TypePrinter<'a, 'b> {
         TypePrinter { typ: self, bindings }
     }
 
+    /// Returns the part of this type at the given path, or `None` if there is no such part.
+    /// Any type variables along the path are expected to be zonked already.
+    pub fn at_path(&self, path: &[TypePosition]) -> Option<&Type> {
+        match (path.split_first(), self) {
+            (None, typ) => Some(typ),
+            (Some((TypePosition::Parameter, rest)), Type::Function { parameter, .. }) => parameter.at_path(rest),
+            (Some((TypePosition::ReturnType, rest)), Type::Function { return_type, .. }) => return_type.at_path(rest),
+            (Some((TypePosition::Argument(index), rest)), Type::Data { arguments, .. }) => {
+                arguments.get(*index)?.at_path(rest)
+            },
+            (Some((TypePosition::Argument(0), rest)), Type::List(element)) => element.at_path(rest),
+            (Some((TypePosition::Element(index), rest)), Type::Tuple(elements)) => elements.get(*index)?.at_path(rest),
+            (Some((TypePosition::Field(name), rest)), Type::Record { fields, .. }) => {
+                let (_, field) = fields.iter().find(|(field_name, _)| field_name == name)?;
+                field.at_path(rest)
+            },
+            (Some(_), _) => None,
+        }
+    }
+
     pub fn find_all_generics(&self) -> Vec<Arc<String>> {
         let mut found = BTreeSet::new();
         self.find_all_generics_helper(&mut found);
         found.into_iter().collect()
     }
 
+    /// Returns each unbound type variable in this type, in the order they first appear
+    pub fn free_type_variables(&self, bindings: &TypeBindings) -> Vec<TypeVariableId> {
+        let mut found = Vec::new();
+        self.free_type_variables_helper(bindings, &mut found);
+        found
+    }
+
+    fn free_type_variables_helper(&self, bindings: &TypeBindings, found: &mut Vec<TypeVariableId>) {
+        match self {
+            Type::Error | Type::Unit | Type::Int | Type::Bool | Type::String | Type::Generic(_) => (),
+            Type::TypeVariable(id) => match bindings.get(id) {
+                Some(binding) => binding.free_type_variables_helper(bindings, found),
+                None if !found.contains(id) => found.push(*id),
+                None => (),
+            },
+            Type::Function { parameter, return_type } => {
+                parameter.free_type_variables_helper(bindings, found);
+                return_type.free_type_variables_helper(bindings, found);
+            },
+            Type::List(element) => element.free_type_variables_helper(bindings, found),
+            Type::Data { arguments: types, .. } | Type::Tuple(types) => {
+                for typ in types.iter() {
+                    typ.free_type_variables_helper(bindings, found);
+                }
+            },
+            Type::Record { fields, rest } => {
+                for (_, typ) in fields.iter() {
+                    typ.free_type_variables_helper(bindings, found);
+                }
+                if let Some(rest) = rest {
+                    rest.free_type_variables_helper(bindings, found);
+                }
+            },
+        }
+    }
+
     fn find_all_generics_helper(&self, found: &mut BTreeSet<Arc<String>>) {
         match self {
-            Type::Error | Type::Unit | Type::Int | Type::TypeVariable(_) => (),
-            Type::Generic(name) => { found.insert(name.name.clone()); },
+            Type::Error | Type::Unit | Type::Int | Type::Bool | Type::String | Type::TypeVariable(_) => (),
+            Type::Generic(name) => { found.insert(name.clone()); },
             Type::Function { parameter, return_type } => {
                 parameter.find_all_generics_helper(found);
                 return_type.find_all_generics_helper(found);
             },
+            Type::List(element) => element.find_all_generics_helper(found),
+            Type::Data { arguments: types, .. } | Type::Tuple(types) => {
+                for typ in types.iter() {
+                    typ.find_all_generics_helper(found);
+                }
+            },
+            Type::Record { fields, rest } => {
+                for (_, typ) in fields.iter() {
+                    typ.find_all_generics_helper(found);
+                }
+                if let Some(rest) = rest {
+                    rest.find_all_generics_helper(found);
+                }
+            },
         }
     }
 }
@@ -100,7 +387,7 @@ pub struct TypePrinter<'typ, 'bindings> {
 
 impl std::fmt::Display for TypePrinter<'_, '_> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        self.fmt_type(&self.typ, f)
+        self.fmt_type(self.typ, f)
     }
 }
 
@@ -110,9 +397,11 @@ impl TypePrinter<'_, '_> {
             Type::Error => write!(f, "(error)"),
             Type::Unit => write!(f, "Unit"),
             Type::Int => write!(f, "Int"),
-            Type::Generic(identifier) => write!(f, "{}", identifier.name),
+            Type::Bool => write!(f, "Bool"),
+            Type::String => write!(f, "String"),
+            Type::Generic(name) => write!(f, "{name}"),
             Type::TypeVariable(id) => {
-                if let Some(binding) = self.bindings.get(&id) {
+                if let Some(binding) = self.bindings.get(id) {
                     self.fmt_type(binding, f)
                 } else {
                     write!(f, "{id}")
@@ -129,8 +418,128 @@ impl TypePrinter<'_, '_> {
                 }
                 self.fmt_type(return_type, f)
             },
+            Type::Data { name, arguments, .. } => {
+                write!(f, "{name}")?;
+                for argument in arguments.iter() {
+                    if self.needs_parentheses_as_argument(argument) {
+                        write!(f, " (")?;
+                        self.fmt_type(argument, f)?;
+                        write!(f, ")")?;
+                    } else {
+                        write!(f, " ")?;
+                        self.fmt_type(argument, f)?;
+                    }
+                }
+                Ok(())
+            },
+            Type::List(element) => {
+                if self.needs_parentheses_as_argument(element) {
+                    write!(f, "List (")?;
+                    self.fmt_type(element, f)?;
+                    write!(f, ")")
+                } else {
+                    write!(f, "List ")?;
+                    self.fmt_type(element, f)
+                }
+            },
+            Type::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    self.fmt_type(element, f)?;
+                }
+                write!(f, ")")
+            },
+            Type::Record { .. } => {
+                let (fields, rest) = typ.record_parts(self.bindings).expect("This is a record type");
+                write!(f, "{{")?;
+                for (i, (name, field)) in fields.iter().enumerate() {
+                    write!(f, "{} {name}: ", if i == 0 { "" } else { "," })?;
+                    self.fmt_type(field, f)?;
+                }
+                match rest {
+                    Some(rest) => {
+                        write!(f, " | ")?;
+                        self.fmt_type(&rest, f)?;
+                        write!(f, " }}")
+                    },
+                    None if fields.is_empty() => write!(f, "}}"),
+                    None => write!(f, " }}"),
+                }
+            },
+        }
+    }
+
+    /// Functions and types with arguments of their own need parentheses as arguments to another type,
+    /// e.g. `Option (List Int)`
+    fn needs_parentheses_as_argument(&self, typ: &Type) -> bool {
+        match typ {
+            Type::TypeVariable(id) => self.bindings.get(id).is_some_and(|typ| self.needs_parentheses_as_argument(typ)),
+            Type::Function { .. } | Type::List(_) => true,
+            Type::Data { arguments, .. } => !arguments.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+/// A position within a function type, the arguments of a type, or a tuple or record type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TypePosition {
+    Parameter,
+    ReturnType,
+    Argument(usize),
+    Element(usize),
+    Field(Arc<String>),
+}
+
+/// Describe a path of `TypePosition`s within a type for an error message, e.g.
+/// `[ReturnType, Parameter]` is the second parameter, and `[Parameter, ReturnType]` is the
+/// return type of the first parameter. Returns `None` for an empty path.
+pub fn describe_type_position(path: &[TypePosition]) -> Option<String> {
+    let mut descriptions = Vec::new();
+    let mut return_types = 0;
+
+    for position in path {
+        match position {
+            TypePosition::ReturnType => return_types += 1,
+            TypePosition::Parameter => {
+                descriptions.push(format!("parameter {}", return_types + 1));
+                return_types = 0;
+            },
+            TypePosition::Argument(index) => {
+                push_return_types(&mut descriptions, return_types);
+                descriptions.push(format!("type argument {}", index + 1));
+                return_types = 0;
+            },
+            TypePosition::Element(index) => {
+                push_return_types(&mut descriptions, return_types);
+                descriptions.push(format!("element {}", index + 1));
+                return_types = 0;
+            },
+            TypePosition::Field(name) => {
+                push_return_types(&mut descriptions, return_types);
+                descriptions.push(format!("field `{name}`"));
+                return_types = 0;
+            },
         }
     }
+
+    push_return_types(&mut descriptions, return_types);
+
+    // Positions later in the path are nested inside the earlier ones
+    descriptions.reverse();
+    (!descriptions.is_empty()).then(|| descriptions.join(" of "))
+}
+
+/// Describe a run of `return_types` consecutive `ReturnType` positions, if there are any
+fn push_return_types(descriptions: &mut Vec<String>, return_types: usize) {
+    match return_types {
+        0 => (),
+        1 => descriptions.push("the return type".to_string()),
+        n => descriptions.push(format!("the return type after {n} arguments")),
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
@@ -142,15 +551,25 @@ impl TypeVariableId {
             Type::Error => false,
             Type::Unit => false,
             Type::Int => false,
+            Type::Bool => false,
+            Type::String => false,
             Type::Generic(_) => false,
             Type::TypeVariable(id) => {
-                if let Some(binding) = bindings.get(&id) {
+                if let Some(binding) = bindings.get(id) {
                     self.occurs_in(binding, bindings)
                 } else {
                     self == *id
                 }
             },
             Type::Function { parameter, return_type } => self.occurs_in(parameter, bindings) || self.occurs_in(return_type, bindings),
+            Type::List(element) => self.occurs_in(element, bindings),
+            Type::Data { arguments: types, .. } | Type::Tuple(types) => {
+                types.iter().any(|typ| self.occurs_in(typ, bindings))
+            },
+            Type::Record { fields, rest } => {
+                fields.iter().any(|(_, typ)| self.occurs_in(typ, bindings))
+                    || rest.as_ref().is_some_and(|rest| self.occurs_in(rest, bindings))
+            },
         }
     }
 }
@@ -166,12 +585,18 @@ impl std::fmt::Display for TypeVariableId {
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct TopLevelDefinitionType {
     pub generics: Vec<Arc<String>>,
+    /// Each use of the definition must satisfy these constraints on its generics
+    pub constraints: Vec<Constraint>,
     pub typ: Type,
 }
 
 impl TopLevelDefinitionType {
     pub fn new(generics: Vec<Arc<String>>, typ: Type) -> Self {
-        Self { typ, generics }
+        Self { typ, constraints: Vec::new(), generics }
+    }
+
+    pub fn with_constraints(self, constraints: Vec<Constraint>) -> Self {
+        Self { constraints, ..self }
     }
 
     pub fn unit() -> TopLevelDefinitionType {
@@ -182,10 +607,14 @@ impl TopLevelDefinitionType {
         TopLevelTypePrinter { typ: self, bindings }
     }
 
-    pub fn from_ast_type(typ: &crate::parser::ast::Type) -> Self {
-        let typ = Type::from_ast_type(typ);
+    /// The type of a definition with a type annotation
+    pub fn from_annotation(
+        definition: &Definition, typ: &crate::parser::ast::Type, origins: &BTreeMap<ExprId, Origin>,
+        compiler: &impl DbGet<ExpandTypeAlias>,
+    ) -> Self {
+        let typ = Type::from_annotation(typ, definition.forall.as_deref(), origins, compiler);
         let generics = typ.find_all_generics();
-        Self::new(generics, typ)
+        Self::new(generics, typ).with_constraints(Constraint::from_ast_constraints(&definition.constraints, origins))
     }
 }
 
@@ -203,6 +632,16 @@ impl std::fmt::Display for TopLevelTypePrinter<'_, '_> {
             }
             write!(f, ". ")?;
         }
-        write!(f, "{}", self.typ.display(self.bindings))
+        // Constraints in a definition's type are always on its generics so the types need no parentheses
+        let constraints = self.typ.constraints.iter();
+        let constraints: Vec<_> = constraints
+            .map(|constraint| format!("{} {}", constraint.class_name, constraint.typ.display(self.bindings)))
+            .collect();
+        match constraints.as_slice() {
+            [] => (),
+            [constraint] => write!(f, "{constraint} => ")?,
+            constraints => write!(f, "({}) => ", constraints.join(", "))?,
+        }
+        write!(f, "{}", self.typ.typ.display(self.bindings))
     }
 }
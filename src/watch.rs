@@ -0,0 +1,133 @@
+//! Watch mode: rebuild the program each time one of its files changes.
+//!
+//! Unlike running the compiler once per build, this keeps a single `Compiler` in memory for the
+//! lifetime of the process. So there is no need to deserialize the incremental metadata before
+//! each build or serialize it again afterward - we only need to tell the compiler which
+//! `SourceFile`s changed and re-run the queries we want.
+//!
+//! To find out which files changed we simply poll the modification time of each file in the
+//! import graph rather than using an OS-specific file notification API. The text of each file is
+//! kept in memory so files which haven't changed are never re-read.
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    cli::{MessageFormat, Options},
+    compile_all,
+    find_changed_files::collect_all_changed_files_with,
+    incremental::{self, set_source_file, Compiler},
+    make_compiler, print_errors, read_file,
+};
+
+/// How long to wait between checking whether any files have changed
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Build the program, then rebuild it each time one of its files changes. This never returns.
+pub fn run(options: &Options) {
+    incremental::set_quiet(options.message_format == MessageFormat::Json);
+
+    // The metadata file is only read to speed up the first build. Afterward everything is kept in memory.
+    let mut watcher = Watcher { compiler: make_compiler(options), options, files: BTreeMap::new() };
+    watcher.rebuild();
+
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        if watcher.refresh_changed_files() {
+            watcher.rebuild();
+        }
+    }
+}
+
+struct Watcher<'a> {
+    compiler: Compiler,
+    options: &'a Options,
+
+    /// Each file in the program as of the last build
+    files: BTreeMap<Arc<String>, WatchedFile>,
+}
+
+struct WatchedFile {
+    /// `None` if the file doesn't exist or the platform doesn't support modification times
+    modified: Option<SystemTime>,
+    text: Result<String, String>,
+}
+
+impl WatchedFile {
+    fn read(file_name: &str) -> WatchedFile {
+        let path = Path::new(file_name);
+        WatchedFile { modified: modified_time(path), text: read_file(path) }
+    }
+}
+
+impl Watcher<'_> {
+    /// Re-read each file whose modification time has changed since it was last read.
+    /// Returns true if any file changed.
+    fn refresh_changed_files(&mut self) -> bool {
+        let mut changed = false;
+        for (file_name, file) in self.files.iter_mut() {
+            if modified_time(Path::new(file_name.as_str())) != file.modified {
+                *file = WatchedFile::read(file_name);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn rebuild(&mut self) {
+        let start = Instant::now();
+        let human_output = self.options.message_format == MessageFormat::Human;
+
+        let root = self.options.input_file.clone();
+        let root_file = self.files.entry(root.clone()).or_insert_with(|| WatchedFile::read(&root));
+        match &root_file.text {
+            Ok(text) => set_source_file(root.clone(), text.clone(), &mut self.compiler),
+            Err(error) => return eprintln!("error: {error}\n\nWaiting for changes..."),
+        }
+
+        if human_output {
+            println!("Passes Run:");
+        }
+
+        // Any files we haven't seen before are read from disk and remembered for the next build.
+        // Setting a `SourceFile` to the same text it had before is not considered a change, so only
+        // the files which actually changed will be re-checked.
+        let new_files = RefCell::new(BTreeMap::new());
+        let read_source = |file_name: &Arc<String>| match self.files.get(file_name) {
+            Some(file) => file.text.clone(),
+            None => {
+                let file = WatchedFile::read(file_name);
+                let text = file.text.clone();
+                new_files.borrow_mut().insert(file_name.clone(), file);
+                text
+            },
+        };
+
+        let (files, mut errors) = collect_all_changed_files_with(root, &read_source, &mut self.compiler);
+        self.files.extend(new_files.into_inner());
+        self.files.retain(|file_name, _| files.contains(file_name));
+
+        match compile_all(files, self.options, &self.compiler) {
+            Ok(compiled) => errors.extend(compiled),
+            Err(error) => return eprintln!("error: {error}\n\nWaiting for changes..."),
+        }
+
+        if human_output {
+            println!("Compiler finished in {:.2?}.\n", start.elapsed());
+        }
+
+        print_errors(&errors, self.options.message_format, &self.compiler);
+
+        if human_output {
+            println!("Waiting for changes...");
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,43 @@
+//! Tests which build a program, edit it, then build it again, checking from the trace of queries printed
+//! by `build` that only the queries depending on the edit are run again.
+use std::{path::Path, process::Command};
+
+/// Build the program, returning the trace of queries which were run
+fn build(program: &Path, directory: &Path) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_modern_compiler_architecture"))
+        .arg("build")
+        .arg(program)
+        .arg("-o")
+        .arg(directory.join("out"))
+        .arg("--cache-dir")
+        .arg(directory.join("cache"))
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{} failed to build:\n{stdout}", program.display());
+    stdout
+}
+
+/// Each statement compiled by `CompileStatement` in the trace. The trace of `CompileFile` also starts
+/// with `Compiling`, but is followed by a file name instead of a statement.
+fn compiled_statements<'a>(trace: &'a str, program: &Path) -> Vec<&'a str> {
+    let compiled = trace.lines().filter_map(|line| line.split_once("- Compiling ").map(|(_, statement)| statement));
+    compiled.filter(|statement| Path::new(statement) != program).collect()
+}
+
+#[test]
+fn editing_a_definition_only_recompiles_that_definition() {
+    let directory = std::env::temp_dir().join("modern_compiler_architecture_tests/incremental/edit_definition");
+    std::fs::remove_dir_all(&directory).ok();
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let program = directory.join("main.ex");
+    std::fs::write(&program, "def double = fn x -> x * 2\n\ndef triple = fn x -> x * 3\n\nprint double 4\n").unwrap();
+    let trace = build(&program, &directory);
+    assert_eq!(compiled_statements(&trace, &program).len(), 3, "{trace}");
+
+    std::fs::write(&program, "def double = fn x -> x * 2\n\ndef triple = fn x -> x * 4\n\nprint double 4\n").unwrap();
+    let trace = build(&program, &directory);
+    assert_eq!(compiled_statements(&trace, &program), ["def triple = fn x -> * x 4"], "{trace}");
+}
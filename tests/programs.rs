@@ -0,0 +1,265 @@
+//! Regression tests which run the compiler on each program in `tests/programs`.
+//!
+//! - Each program in `tests/programs/unsound` was accepted by an earlier version of the type
+//!   checker even though it would crash at runtime. These must fail to type check with the error
+//!   code given by an `// expect: <code>` comment in the program. The comment may also give the
+//!   line and column the error must be reported at, e.g. `// expect: E0006 at 3:11`. A program
+//!   must report one diagnostic for each of these comments and no others, so a program reporting
+//!   the same error twice needs two comments.
+//! - Each program in `tests/programs/errors` must fail with the error code given by its
+//!   `// expect: <code>` comment in the same way.
+//! - Each program in `tests/programs/well_typed` must type check without errors. Running it must
+//!   print exactly the contents of the `.out` file of the same name.
+//! - Each program in `tests/programs/warnings` must type check, reporting the warning given by its
+//!   `// expect: <code>` comment. If it has a `.out` file, running it must print exactly its contents
+//!   and then stop with an error upon reaching a hole.
+//!
+//! A program made of several files is a directory in one of these, whose entry file is `main.ex`.
+//! The other files in the directory are only checked or run when `main.ex` imports them.
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+#[test]
+fn unsound_programs_fail_to_type_check() {
+    assert_programs_fail("unsound");
+}
+
+#[test]
+fn programs_with_errors_report_them() {
+    assert_programs_fail("errors");
+}
+
+#[test]
+fn well_typed_programs_type_check() {
+    for program in programs_in("well_typed") {
+        let output = check(&program);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{} failed to type check:\n{stdout}", program.display());
+    }
+}
+
+#[test]
+fn well_typed_programs_print_expected_output() {
+    for program in programs_in("well_typed") {
+        let expected = expected_output(&program);
+        let output = compiler(&program, "run").output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(output.status.success(), "{} failed to run:\n{stdout}{stderr}", program.display());
+        assert_eq!(stdout, expected, "{} printed unexpected output", program.display());
+    }
+}
+
+#[test]
+fn programs_with_warnings_stop_at_holes() {
+    for program in programs_reaching_holes() {
+        let mut output = compiler(&program, "run").args(["--message-format", "json"]).output().unwrap();
+
+        // Leave out the warnings, which are printed as json one per line, to keep only what the program printed
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let printed: String = stdout.split_inclusive('\n').filter(|line| !line.starts_with("{\"code\":")).collect();
+        output.stdout = printed.into_bytes();
+        assert_printed_expected_output(&program, &output);
+    }
+}
+
+/// Compiled programs print values the same way the interpreter does, so these check that each program
+/// prints the same output once compiled. These are skipped if python or node isn't installed.
+#[test]
+fn compiled_python_prints_expected_output() {
+    let programs = programs_in("well_typed").into_iter().chain(programs_reaching_holes());
+    assert_compiled_output("python", "python3", "py", programs);
+}
+
+#[test]
+fn compiled_javascript_prints_expected_output() {
+    let programs = programs_in("well_typed").into_iter().chain(programs_reaching_holes());
+    assert_compiled_output("javascript", "node", "mjs", programs);
+}
+
+#[test]
+fn clean_removes_the_output_of_every_target() {
+    let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs/well_typed/strings.ex");
+    let output_dir = cache_dir("clean_output", &program);
+    let cache = cache_dir("clean", &program);
+    let run = |args: &[&str]| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_modern_compiler_architecture"));
+        command.args(args).arg(&program).arg("-o").arg(&output_dir).arg("--cache-dir").arg(&cache);
+        assert!(command.output().unwrap().status.success(), "{args:?} failed");
+    };
+
+    run(&["build", "--target", "python"]);
+    run(&["build", "--target", "javascript"]);
+    assert!(output_dir.join("strings.py").exists() && output_dir.join("strings.mjs").exists());
+
+    run(&["clean"]);
+    assert!(!output_dir.join("strings.py").exists());
+    assert!(!output_dir.join("strings.mjs").exists());
+    assert!(!output_dir.join("_runtime.py").exists());
+    assert!(!output_dir.join("_runtime.mjs").exists());
+}
+
+#[test]
+fn programs_with_warnings_report_them() {
+    for program in programs_in("warnings") {
+        let output = check(&program);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success(), "{} failed to type check:\n{stdout}", program.display());
+        assert_reports_expected(&program, &stdout);
+    }
+}
+
+/// Check that each program in the directory fails with the error code in its `// expect: <code>` comment
+fn assert_programs_fail(directory: &str) {
+    for program in programs_in(directory) {
+        let output = check(&program);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(!output.status.success(), "{} checked successfully", program.display());
+        assert_reports_expected(&program, &stdout);
+    }
+}
+
+/// Check the diagnostics printed as json by `check` are exactly those given by the program's
+/// `// expect: <code>` comments, one for each comment, at the location a comment gives if it has one
+fn assert_reports_expected(program: &Path, stdout: &str) {
+    let expected = expected_diagnostics(program);
+    let mut expected_codes: Vec<_> = expected.iter().map(|(code, _)| code.as_str()).collect();
+    let mut reported_codes: Vec<_> = stdout.lines().filter_map(diagnostic_code).collect();
+    expected_codes.sort();
+    reported_codes.sort();
+    assert_eq!(reported_codes, expected_codes, "{} reported unexpected diagnostics:\n{stdout}", program.display());
+
+    for (code, location) in expected {
+        let Some((line, column)) = location else { continue };
+        let mut diagnostics = stdout.lines().filter(|diagnostic| diagnostic_code(diagnostic) == Some(&code));
+        let found = diagnostics.any(|diagnostic| starts_at(diagnostic, line, column));
+        assert!(found, "{} did not report {code} at {line}:{column}:\n{stdout}", program.display());
+    }
+}
+
+/// The error code of a diagnostic printed as json
+fn diagnostic_code(diagnostic: &str) -> Option<&str> {
+    let (_, rest) = diagnostic.split_once("\"code\":\"")?;
+    rest.split('"').next()
+}
+
+/// True if the primary location of the json diagnostic starts at the given line and column
+fn starts_at(diagnostic: &str, line: u32, column: u32) -> bool {
+    let start = format!("\"line\":{line},\"column\":{column},");
+    diagnostic.split("{\"file\":").any(|location| location.contains(&start) && location.contains("\"primary\":true"))
+}
+
+/// Build each program for the target then run the output with `interpreter`, checking that it prints
+/// the same thing as running the program with the compiler does
+fn assert_compiled_output(target: &str, interpreter: &str, extension: &str, programs: impl Iterator<Item = PathBuf>) {
+    for program in programs {
+        let output_dir = cache_dir(&format!("{target}_output"), &program);
+        let output = Command::new(env!("CARGO_BIN_EXE_modern_compiler_architecture"))
+            .arg("build")
+            .arg(&program)
+            .args(["--target", target, "-o"])
+            .arg(&output_dir)
+            .arg("--cache-dir")
+            .arg(cache_dir(&format!("build_{target}"), &program))
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{} failed to build:\n{stdout}", program.display());
+
+        let module = output_dir.join(program.file_stem().unwrap()).with_extension(extension);
+        let Ok(output) = Command::new(interpreter).arg(module).output() else {
+            eprintln!("{interpreter} could not be run, skipping");
+            return;
+        };
+        assert_printed_expected_output(&program, &output);
+    }
+}
+
+/// Check that running the program printed exactly the contents of its `.out` file. Programs with
+/// warnings must then stop upon reaching a hole, while every other program must run to completion.
+fn assert_printed_expected_output(program: &Path, output: &Output) {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stdout, expected_output(program), "{} printed unexpected output:\n{stderr}", program.display());
+
+    if program.starts_with(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs/warnings")) {
+        // Tracebacks may quote the source raising the error, so look for the error message itself
+        let reached_hole = !output.status.success() && stderr.contains(": Reached a hole");
+        assert!(reached_hole, "{} did not stop at a hole:\n{stderr}", program.display());
+    } else {
+        assert!(output.status.success(), "{} failed to run:\n{stderr}", program.display());
+    }
+}
+
+/// The error code given by each `// expect: <code>` comment in the program, along with the line and
+/// column of the `// expect: <code> at <line>:<column>` form
+fn expected_diagnostics(program: &Path) -> Vec<(String, Option<(u32, u32)>)> {
+    let source = std::fs::read_to_string(program).unwrap();
+    let expected: Vec<_> = source.lines().filter_map(|line| line.strip_prefix("// expect:")).collect();
+    assert!(!expected.is_empty(), "{} has no `// expect: <code>` comment", program.display());
+
+    let parse = |number: &str| number.parse().expect("Expected locations should be `<line>:<column>`");
+    let expected = expected.into_iter().map(|expect| match expect.split_once(" at ") {
+        Some((code, location)) => {
+            let location = location.trim().split_once(':');
+            let (line, column) = location.expect("Expected locations should be `<line>:<column>`");
+            (code.trim().to_string(), Some((parse(line), parse(column))))
+        },
+        None => (expect.trim().to_string(), None),
+    });
+    expected.collect()
+}
+
+/// The contents of the `.out` file next to the program
+fn expected_output(program: &Path) -> String {
+    let path = program.with_extension("out");
+    std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("{} has no expected output", program.display()))
+}
+
+/// Each program with warnings which has a `.out` file, and so is expected to run until it reaches a hole
+fn programs_reaching_holes() -> impl Iterator<Item = PathBuf> {
+    programs_in("warnings").into_iter().filter(|program| program.with_extension("out").exists())
+}
+
+/// The entry file of each program in the directory: each `.ex` file in it, and the `main.ex` of each subdirectory
+fn programs_in(directory: &str) -> Vec<PathBuf> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs").join(directory);
+    let mut programs: Vec<_> = std::fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .map(|path| if path.is_dir() { path.join("main.ex") } else { path })
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ex"))
+        .collect();
+
+    programs.sort();
+    assert!(!programs.is_empty(), "No programs found in {}", directory.display());
+    programs
+}
+
+fn check(program: &Path) -> Output {
+    compiler(program, "check").args(["--message-format", "json"]).output().unwrap()
+}
+
+/// Run the given compiler command on a program, keeping the incremental metadata out of the source tree
+fn compiler(program: &Path, command: &str) -> Command {
+    let cache_dir = cache_dir(command, program);
+    let mut compiler = Command::new(env!("CARGO_BIN_EXE_modern_compiler_architecture"));
+    compiler.arg(command).arg(program).arg("--cache-dir").arg(&cache_dir);
+    compiler
+}
+
+/// A temporary directory for the given use by the given program. Each program and use gets its own
+/// directory since tests run in parallel. This is cleared first since files left by a different
+/// version of the compiler may be out of date.
+fn cache_dir(purpose: &str, program: &Path) -> PathBuf {
+    let programs = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+    let name = program.strip_prefix(&programs).unwrap_or(program).with_extension("");
+    let directory = std::env::temp_dir().join("modern_compiler_architecture_tests").join(purpose).join(name);
+    std::fs::remove_dir_all(&directory).ok();
+    directory
+}
@@ -0,0 +1,10 @@
+// expect: E0023
+class Show a {
+    show: a -> String
+}
+
+instance Show a => Show (List a) {
+    show = fn xs -> "list"
+}
+
+def empty = show []
@@ -0,0 +1,2 @@
+// expect: E0007
+def apply_self = fn g -> g g 1
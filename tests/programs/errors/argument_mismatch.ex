@@ -0,0 +1,5 @@
+// In `print inc inc`, the argument `inc` has the type `Int -> Int` where `Int` is expected
+// expect: E0006 at 5:11
+def inc = fn x -> x + 1
+
+print inc inc
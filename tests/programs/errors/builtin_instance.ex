@@ -0,0 +1,6 @@
+// `Eq` already has a built-in instance for `String`, so it can't be given another
+// expect: E0030 at 3:10
+instance Eq String {
+    equal = fn a b -> true,
+    not_equal = fn a b -> false
+}
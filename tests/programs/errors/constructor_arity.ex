@@ -0,0 +1,6 @@
+// expect: E0015
+type Option a = None | Some a
+
+def get = fn o -> match o with
+    | Some x y -> x
+    | None -> 0
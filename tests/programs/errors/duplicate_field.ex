@@ -0,0 +1,2 @@
+// expect: E0019
+def point = { x = 1, y = 2, x = 3 }
@@ -0,0 +1,12 @@
+// expect: E0024
+class Show a {
+    show: a -> String
+}
+
+instance Show (List a) {
+    show = fn xs -> "list"
+}
+
+instance Show (List b) {
+    show = fn xs -> "other list"
+}
@@ -0,0 +1,2 @@
+// expect: E0005
+def const: forall a. a -> b -> a = fn x y -> x
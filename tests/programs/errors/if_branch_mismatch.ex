@@ -0,0 +1,2 @@
+// expect: E0012
+def choose = fn c -> if c then 1 else fn x -> x
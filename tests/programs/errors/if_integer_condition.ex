@@ -0,0 +1,2 @@
+// expect: E0006
+def choose = fn x -> if x + 1 then x else 0
@@ -0,0 +1,4 @@
+// expect: E0001 at 4:1
+import shapes (Shape, area
+
+print 1
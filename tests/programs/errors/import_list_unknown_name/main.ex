@@ -0,0 +1,5 @@
+// `shapes.ex` defines no `perimeter` to import
+// expect: E0005 at 3:23
+import shapes (Shape, perimeter)
+
+print 1
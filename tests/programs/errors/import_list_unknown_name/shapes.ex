@@ -0,0 +1,6 @@
+// Shapes can't be given a perimeter
+type Shape = Circle Int | Square Int
+
+def area = fn shape -> match shape with
+    | Circle r -> 3 * r * r
+    | Square side -> side * side
@@ -0,0 +1,2 @@
+// expect: E0018
+def greeting = "hello\qworld"
@@ -0,0 +1,5 @@
+// `y` has the same type as the parameter `x`, so it can't be generalized.
+// `+` is then used on `Bool`, which has no instance of `Num`.
+// expect: E0006
+// expect: E0022 at 5:37
+def escape = fn x -> let y = x in y + (if y then 1 else 2)
@@ -0,0 +1,2 @@
+// expect: E0006
+def mixed = [1, true, 3]
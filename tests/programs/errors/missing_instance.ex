@@ -0,0 +1,10 @@
+// expect: E0022
+class Show a {
+    show: a -> String
+}
+
+instance Show Int {
+    show = int_to_string
+}
+
+def message = show "string"
@@ -0,0 +1,5 @@
+// `first` and `second` are inferred together, so `first` can't be used
+// at both `Int` and `Int -> Int` within the group.
+// expect: E0011
+def first = fn x -> second x
+def second = fn y -> first 1 + first (fn z -> z)
@@ -0,0 +1,3 @@
+// expect: E0020
+def point: { x: Int, y: Int } = { x = 1, y = 2 }
+def z = point.z
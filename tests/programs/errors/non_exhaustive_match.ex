@@ -0,0 +1,6 @@
+// expect: E0013
+type Option a = None | Some a
+
+def get = fn o -> match o with
+    | Some (Some x) -> x
+    | None -> 0
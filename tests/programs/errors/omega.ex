@@ -0,0 +1,3 @@
+// expect: E0007 at 3:18
+// expect: E0007 at 3:32
+print (fn x -> x x) (fn x -> x x)
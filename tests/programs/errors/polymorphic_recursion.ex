@@ -0,0 +1,4 @@
+// `poly` is used at two different types within its own definition,
+// which can't be inferred without an annotation.
+// expect: E0011
+def poly = fn x -> poly 1 (poly x)
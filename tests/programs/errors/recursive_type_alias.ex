@@ -0,0 +1,4 @@
+// expect: E0028 at 3:6
+// expect: E0028 at 4:6
+type Callback = Int -> Handler
+type Handler = (List Callback)
@@ -0,0 +1,5 @@
+// expect: E0014
+def sign = fn x -> match x with
+    | 0 -> 0
+    | n -> 1
+    | 1 -> 2
@@ -0,0 +1,2 @@
+// expect: E0021
+def first_if_zero = fn (0, x) -> x
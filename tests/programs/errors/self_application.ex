@@ -0,0 +1,3 @@
+// The parameter would need to be a function taking itself as an argument
+// expect: E0007
+def self_apply = fn x -> x x
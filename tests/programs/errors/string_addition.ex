@@ -0,0 +1,3 @@
+// `+` is a method of `Num`, which has no instance for `String`. Strings are joined with `++` instead.
+// expect: E0022 at 3:11
+print "a" + "b"
@@ -0,0 +1,5 @@
+// `b` isn't listed in the `forall`, so it must not also be a generic which `a` fails to match
+// expect: E0005
+def id: forall a. a -> b = fn x -> x
+
+print id 1
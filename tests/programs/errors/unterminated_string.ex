@@ -0,0 +1,4 @@
+// expect: E0017
+def greeting = "hello
+
+print greeting
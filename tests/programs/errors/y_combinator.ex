@@ -0,0 +1,4 @@
+// Without recursive types, the Y combinator can't be typed
+// expect: E0007
+// expect: E0007
+def fix = fn f -> (fn x -> f (x x)) (fn x -> f (x x))
@@ -0,0 +1,5 @@
+// `inc` was generalized to `forall 0 1. 0 -> 1`, letting its result be called.
+// expect: E0006
+def inc = fn x -> x + 1
+
+print inc 1 2
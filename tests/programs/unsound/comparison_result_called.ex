@@ -0,0 +1,5 @@
+// `less` was generalized to `forall 0 1 2. 0 -> 1 -> 2`, letting its result be called.
+// expect: E0006
+def less = fn x -> fn y -> x < y
+
+print less 1 2 3
@@ -0,0 +1,5 @@
+// `inc` was generalized to `forall 0 1. 0 -> 1`, so it could be given itself as an argument.
+// expect: E0006
+def inc = fn x -> x + 1
+
+print inc inc
@@ -0,0 +1,7 @@
+// The type of `one` is the result of a call, which is bound to `Int` during inference.
+// It was still generalized to `forall 0. 0`.
+// expect: E0006
+def id = fn x -> x
+def one = id 1
+
+print one one
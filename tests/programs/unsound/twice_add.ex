@@ -0,0 +1,8 @@
+// `add` was generalized to `forall 0 1 2. 0 -> 1 -> 2`, so it could be used as an `Int -> Int`.
+// Now that `+` works on any type with a `Num` instance, `add` is `Num a => a -> a -> a`, and using
+// it as an `a -> a` would need `a` to be `a -> a`.
+// expect: E0007
+def add = fn x -> fn y -> x + y
+def twice = fn f -> fn x -> f (f x)
+
+print twice add 1
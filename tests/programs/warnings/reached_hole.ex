@@ -0,0 +1,7 @@
+// Reaching a hole must still stop the program when it defines its own `Exception` and `Error`
+// expect: E0029 at 6:7
+type Failure = Exception Int | Error
+
+print 1
+print ?unfinished
+print 2
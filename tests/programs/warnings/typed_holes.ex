@@ -0,0 +1,15 @@
+// expect: E0029 at 11:22
+// expect: E0029 at 13:62
+// expect: E0029 at 15:27
+type Shape =
+   | Circle Int
+   | Square Int
+
+def area = fn shape ->
+    match shape with
+    | Circle r -> 3 * r * r
+    | Square side -> ?side_area
+
+def twice: forall a. (a -> a) -> a -> a = fn f -> fn x -> f (_ x)
+
+print length (tail [1, 2, ?rest])
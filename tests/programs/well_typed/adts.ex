@@ -0,0 +1,33 @@
+type Option a = None | Some a
+type List a = Nil | Cons a (List a)
+
+// A type may share its name with one of its constructors
+type Pair a b = Pair a b
+
+def map = fn f o -> match o with
+    | None -> None
+    | Some x -> Some (f x)
+
+def length = fn list -> match list with
+    | Nil -> 0
+    | Cons _ rest -> 1 + length rest
+
+def head: List a -> Option a = fn list -> match list with
+    | Cons x _ -> Some x
+    | Nil -> None
+
+// Nested constructor patterns along with integer and boolean literals
+def describe = fn o -> match o with
+    | Some (Pair 0 true) -> 1
+    | Some (Pair 0 false) -> 2
+    | Some (Pair _ b) -> if b then 3 else 4
+    | None -> 5
+
+def swap = fn p -> match p with
+    | Pair a b -> Pair b a
+
+print map (fn x -> x + 1) (Some 2)
+print length (Cons 1 (Cons 2 Nil))
+print head (Cons true Nil)
+print describe (Some (Pair 0 false))
+print swap (Pair 1 true)
@@ -0,0 +1,5 @@
+def apply: (a -> b) -> a -> b = fn f -> fn x -> f x
+def id: a -> a = fn x -> x
+
+print apply id 1
+print apply (apply id) 2
@@ -0,0 +1,13 @@
+// Each operator must compute the right result, not just have the right type
+print 5 - 3
+print 10 - 2 - 3
+print 7 * 6
+print 7 / 2
+print 7 % 3
+print 2 + 3 * 4 - 1
+print 1 < 2
+print 3 >= 4
+
+// Division and remainder round toward negative infinity
+print (0 - 7) / 2
+print (0 - 7) % 3
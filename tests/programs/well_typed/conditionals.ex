@@ -0,0 +1,11 @@
+// Comparisons produce `Bool`s, which `if` expressions branch on
+def max = fn a b -> if a > b then a else b
+def factorial = fn n -> if n <= 1 then 1 else n * factorial (n - 1)
+def not: Bool -> Bool = fn b -> if b then false else true
+
+// Both branches may be functions, as long as they have the same type
+def pick = fn c -> if c then fn x -> x + 1 else fn x -> x * 2
+
+print max 3 (factorial 4)
+print not (1 == 2)
+print pick true 41
@@ -0,0 +1,8 @@
+// Only the listed names are imported, so this file may define its own `unit`.
+// Instances are always imported since they have no name.
+import shapes (Shape, Circle, Square, scale, area)
+
+def unit = Circle 1
+
+print area (scale 2 unit)
+print area (Square 3)
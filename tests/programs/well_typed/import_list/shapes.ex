@@ -0,0 +1,17 @@
+// Imported by `main.ex`, which only imports some of these names
+type Shape = Circle Int | Square Int
+
+class Area a {
+    area: a -> Int
+}
+
+instance Area Shape {
+    area = fn shape -> match shape with
+        | Circle r -> 3 * r * r
+        | Square side -> side * side
+}
+
+def unit = Square 1
+def scale = fn factor shape -> match shape with
+    | Circle r -> Circle (factor * r)
+    | Square side -> Square (factor * side)
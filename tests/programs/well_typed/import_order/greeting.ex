@@ -0,0 +1,4 @@
+// Imported by `main.ex` after its first `print`
+def message = "after"
+
+print "during"
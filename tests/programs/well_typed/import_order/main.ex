@@ -0,0 +1,6 @@
+// The `print`s in an imported file run when the import is reached, after any `print`s before it
+print "before"
+
+import greeting
+
+print message
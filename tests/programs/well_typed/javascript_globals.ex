@@ -0,0 +1,13 @@
+// Definitions and constructors may have the same names as javascript's globals without changing
+// what the built-in functions do
+type Global = Math | Error Int
+def globalThis = 5
+def console = 2
+
+def describe = fn g -> match g with
+    | Math -> 1
+    | Error x -> x
+
+print describe Math + describe (Error 7) + 7 / 2 + 7 % 3 + globalThis + console
+print int_to_string 42
+print length (tail [1, 2])
@@ -0,0 +1,8 @@
+// Names which are keywords in the languages we compile to must still work once compiled
+def not = fn b -> if b then 0 else 1
+def lambda = fn from -> from
+def pass = fn (yield, is) -> let or = yield + is in or
+
+print not true
+print lambda 3
+print pass (1, 2)
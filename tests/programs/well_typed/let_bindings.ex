@@ -0,0 +1,13 @@
+// Let bindings are generalized, so a local `id` can be used at two types
+def both = fn x -> let id = fn y -> y in if id true then id x else id 0
+def nested = let a = 1 in let b = a + 1 in a * b
+
+// A let binding shadows parameters and earlier let bindings of the same name
+def shadow = fn x -> let x = x + 1 in let f = fn x -> x * 2 in f x
+
+// Generics from the enclosing definition's annotation stay fixed within let bindings
+def annotated: a -> a = fn x -> let same = fn unused -> x in same 1
+
+print both 5
+print nested
+print shadow 3
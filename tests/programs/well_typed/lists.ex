@@ -0,0 +1,16 @@
+def numbers = [1, 2, 3]
+def sum = fold (fn total x -> total + x) 0
+def empty: List Int = []
+def words: List String = cons "a" ["b", "c"]
+def nested: List (List Int) = [[1], [], [2, 3]]
+
+// Local variables and definitions hide the built-in functions of the same name
+def shadowed = fn length -> length + 1
+def tail = fn list -> list
+
+print sum (map (fn x -> x * 2) numbers)
+print head (tail numbers)
+print length words + length nested + length empty
+print map (fn (a, b) -> a + b) [(1, 2), (3, 4)]
+print fold (fn acc s -> acc ++ s) "" words
+print shadowed 2
@@ -0,0 +1,30 @@
+// The arithmetic operators are the methods of the built-in `Num` class, so giving a type an
+// instance of it lets `+` and the others be used on that type too. Since operators can't be
+// written as names, an instance defines them by name instead.
+type Vector = Vector Int Int
+
+instance Num Vector {
+    add = fn (Vector x1 y1) (Vector x2 y2) -> Vector (x1 + x2) (y1 + y2),
+    subtract = fn (Vector x1 y1) (Vector x2 y2) -> Vector (x1 - x2) (y1 - y2),
+    multiply = fn (Vector x1 y1) (Vector x2 y2) -> Vector (x1 * x2) (y1 * y2),
+    divide = fn (Vector x1 y1) (Vector x2 y2) -> Vector (x1 / x2) (y1 / y2),
+    modulo = fn (Vector x1 y1) (Vector x2 y2) -> Vector (x1 % x2) (y1 % y2)
+}
+
+// Definitions using an operator are inferred to work on any type with an instance of its class
+def sum = fn zero xs -> fold (fn total x -> total + x) zero xs
+
+def double: Num a => a -> a = fn x -> x + x
+
+def max = fn x y -> if x > y then x else y
+
+print Vector 1 2 + Vector 3 4
+print Vector 10 7 - Vector 3 4 * Vector 2 1
+print Vector 7 9 / Vector 2 4 % Vector 5 3
+print sum (Vector 0 0) [Vector 1 1, Vector 2 3, Vector 4 5]
+print sum 0 [1, 2, 3]
+print double (Vector 5 6)
+print double 21
+print max 3 8
+print "abc" == "abc"
+print "abc" != "abd"
@@ -0,0 +1,9 @@
+def const = fn x -> fn y -> x
+def compose = fn f -> fn g -> fn x -> f (g x)
+def twice = fn f -> fn x -> f (f x)
+def double = fn x -> x * 2
+
+print compose double double 3
+print twice double 5
+print const 1 compose
+print twice (const 7) 0
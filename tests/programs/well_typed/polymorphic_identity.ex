@@ -0,0 +1,6 @@
+// `id` is used at two different types
+def id = fn x -> x
+def inc = fn x -> x + 1
+
+print id 3
+print (id inc) 4
@@ -0,0 +1,15 @@
+// Every kind of value is printed the same way by the interpreter and by each backend
+type Shape = Circle Int | Named String Shape | Empty
+
+print true
+print "top-level strings aren't quoted"
+print ("quoted \"inside\"", "tab\tnewline\n")
+print [1, 2, 3]
+print [[true], []]
+print Named "ring" (Circle 2)
+print Named "nothing" Empty
+print (Circle 1, [Empty])
+print { z = "last", a = (1, false), m = { } }
+print {}
+print fn x -> x
+print [fn x -> x + 1]
@@ -0,0 +1,6 @@
+// Definitions may have the same names as python's builtins without changing the built-in functions
+def str = fn x -> x + 1
+def len = fn xs -> xs
+
+print int_to_string (str 1)
+print length (len [1, 2, 3])
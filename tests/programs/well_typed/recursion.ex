@@ -0,0 +1,11 @@
+// Self and mutual recursion without any annotations
+def loop = fn x -> loop x
+def factorial = fn n -> n * factorial (n - 1)
+def even = fn n -> odd (n - 1)
+def odd = fn n -> even (n - 1)
+
+// An annotated definition may be used at other types within its own definition
+def annotated: a -> a = fn x -> (fn unused -> x) (annotated 1)
+
+// Uses of a recursive group from outside of it are generalized as usual
+def use_loop = fn x -> loop 1 + loop x x
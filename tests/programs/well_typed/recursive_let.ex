@@ -0,0 +1,5 @@
+// A let binding using its own recursive group is checked before that use is unified with the
+// group's type, so the type of the use must not be generalized
+def count = fn n -> if n == 0 then 0 else let rest = count (n - 1) in rest + 1
+
+print count 3
@@ -0,0 +1,14 @@
+// Multi-byte characters shouldn't throw off the locations of anything after them
+def greeting: String = "héllo, wörld 👋"
+
+def greet = fn name -> "Hello, " ++ name ++ "!"
+
+def describe = fn x -> "x = " ++ int_to_string x
+
+def escapes = "She said \"hi\"\tand left\\\n"
+
+def either = fn b -> if b then "yes" else "no"
+
+print greet greeting
+print describe (3 * 14)
+print escapes ++ either true
@@ -0,0 +1,19 @@
+// Tuple patterns may be used as parameters since they match every tuple
+def swap = fn (a, b) -> (b, a)
+def first: (a, b) -> a = fn (a, _) -> a
+
+// Field access works on any record which has the field
+def get_x = fn r -> r.x
+def name: { name: String | r } -> String = fn r -> r.name
+
+def origin: { x: Int, y: Int } = { y = 0, x = 0 }
+def move = fn (dx, dy) p -> { x = p.x + dx, y = p.y + dy }
+def nested = { inner = { name = "ex", pair = (1, "two") }, flag = true }
+
+print swap (1, true)
+print first (3, 4)
+print get_x origin + get_x { x = 5, label = "five" }
+print move (1, 2) origin
+print name nested.inner
+print nested.inner.pair
+print (fn ((a, b), c) -> a + b + c) ((1, 2), 3)
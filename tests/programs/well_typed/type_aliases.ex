@@ -0,0 +1,23 @@
+type Binop = Int -> Int -> Int
+type Pair a = (a, a)
+type Point = { x: Int, y: Int }
+
+// A single named type must be parenthesized, otherwise it would define a constructor
+type Pairs a = (List (Pair a))
+type Option a = None | Some a
+type Getter a = Option a -> a
+
+def add: Binop = fn x y -> x + y
+def swap: forall a. Pair a -> Pair a = fn (x, y) -> (y, x)
+def origin: Point = { x = 0, y = 0 }
+def firsts: forall a. Pairs a -> List a = map (fn (x, y) -> x)
+def get_or: Getter Int = fn o -> match o with
+    | Some x -> x
+    | None -> 0
+def constant: forall. Int = 3
+
+print add 1 2
+print swap (1, 2)
+print origin.x
+print firsts [(1, 2), (3, 4)]
+print get_or (Some 3)
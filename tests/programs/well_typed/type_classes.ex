@@ -0,0 +1,51 @@
+class Show a {
+    show: a -> String
+}
+
+class Eq a {
+    equals: a -> a -> Bool
+}
+
+type Color = Red | Green
+
+instance Show Int {
+    show = int_to_string
+}
+
+instance Show Bool {
+    show = fn b -> if b then "true" else "false"
+}
+
+instance Show Color {
+    show = fn c -> match c with
+        | Red -> "Red"
+        | Green -> "Green"
+}
+
+instance Show a => Show (List a) {
+    show = fn xs -> "[" ++ fold (fn acc x -> acc ++ show x ++ ";") "" xs ++ "]"
+}
+
+instance (Show a, Show b) => Show (a, b) {
+    show = fn (a, b) -> "(" ++ show a ++ ", " ++ show b ++ ")"
+}
+
+instance Eq Int {
+    equals = fn a b -> a == b
+}
+
+// Constraints are inferred for definitions without annotations
+def describe = fn x -> "<" ++ show x ++ ">"
+
+def showTwice: Show a => a -> String = fn x -> show x ++ show x
+
+def contains: Eq a => a -> List a -> Bool =
+    fn x xs -> fold (fn found y -> if found then true else equals x y) false xs
+
+def countdown = fn x n -> if n == 0 then show x else show x ++ countdown x (n - 1)
+
+print show 3
+print describe [true, false]
+print showTwice (Red, [Green])
+print contains 2 [1, 2, 3]
+print countdown (1, true) 2
@@ -0,0 +1,48 @@
+//! Tests which run the REPL, entering each line of a script and checking what is printed in response.
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Enter each line into a new REPL, returning what the REPL printed in response to each of them
+fn run_repl(lines: &[&str]) -> Vec<String> {
+    let mut repl = Command::new(env!("CARGO_BIN_EXE_modern_compiler_architecture"))
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = repl.stdin.take().unwrap();
+    for line in lines {
+        writeln!(stdin, "{line}").unwrap();
+    }
+    drop(stdin);
+
+    let output = repl.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    // Each response is followed by the prompt for the next line
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut responses: Vec<_> = stdout.split("\n> ").skip(1).map(|response| response.trim_end().to_string()).collect();
+    responses.pop();
+    responses
+}
+
+#[test]
+fn evaluates_expressions_and_definitions() {
+    let responses = run_repl(&["def double = fn x -> x * 2", "double 21", ":type double"]);
+    assert_eq!(responses, ["double : Int -> Int", "42 : Int", "Int -> Int"]);
+}
+
+#[test]
+fn redefining_a_name_keeps_the_other_names_of_its_entry() {
+    let responses = run_repl(&["def a = 1 def b = 2", "def a = 5", "a + b", "b"]);
+    assert_eq!(responses, ["a : Int\nb : Int", "a : Int", "7 : Int", "2 : Int"]);
+}
+
+#[test]
+fn earlier_entries_keep_using_the_old_definition() {
+    let responses = run_repl(&["def a = 1", "def get = fn unused -> a", "def a = 2", "get 0 + a"]);
+    assert_eq!(responses, ["a : Int", "get : forall a. a -> Int", "a : Int", "3 : Int"]);
+}
@@ -0,0 +1,48 @@
+//! Tests which run the compiler in watch mode, editing the files it watches and checking what it prints.
+use std::{
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    sync::mpsc,
+    time::Duration,
+};
+
+/// How long to wait for watch mode to print each diagnostic before failing the test
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[test]
+fn changing_a_watched_file_reports_fresh_diagnostics() {
+    let directory = std::env::temp_dir().join("modern_compiler_architecture_tests/watch/fresh_diagnostics");
+    std::fs::remove_dir_all(&directory).ok();
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let program = directory.join("main.ex");
+    std::fs::write(&program, "print foo\n").unwrap();
+
+    // Diagnostics are printed as json so that nothing else is printed between them
+    let mut watch = Command::new(env!("CARGO_BIN_EXE_modern_compiler_architecture"))
+        .arg("watch")
+        .arg(&program)
+        .args(["--message-format", "json", "-o"])
+        .arg(directory.join("out"))
+        .arg("--cache-dir")
+        .arg(&directory)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Watch mode never exits, so its output is read on another thread to be able to time out
+    let (sender, lines) = mpsc::channel();
+    let stdout = BufReader::new(watch.stdout.take().unwrap());
+    std::thread::spawn(move || stdout.lines().map_while(Result::ok).try_for_each(|line| sender.send(line)));
+
+    let first = lines.recv_timeout(TIMEOUT);
+    std::fs::write(&program, "def bar = 1\n\nprint baz\n").unwrap();
+    let second = lines.recv_timeout(TIMEOUT);
+    watch.kill().unwrap();
+    watch.wait().unwrap();
+
+    let first = first.expect("No diagnostics were reported for the first build");
+    let second = second.expect("No diagnostics were reported after changing the file");
+    assert!(first.contains("`foo` is not defined"), "{first}");
+    assert!(second.contains("`baz` is not defined") && second.contains("\"line\":3"), "{second}");
+}